@@ -1,8 +1,10 @@
 use std::iter::repeat_with;
 use std::time::Duration;
-use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use criterion::{black_box, BenchmarkId, Criterion, criterion_group, criterion_main};
 use itertools::Itertools;
-use spaced_list_5::HollowSpacedList;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use spaced_list_5::{HollowRangeSpacedList, HollowSpacedList};
 
 fn criterion_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("before");
@@ -25,17 +27,61 @@ fn criterion_benchmark(c: &mut Criterion) {
             let list = &lists[list_index];
             list_index += 3;
             list_index %= lists.len();
-            list.before(pos % n);
+            black_box(list.before(pos % n));
             pos = pos.wrapping_add(step);
         }));
     }
     group.finish();
 }
 
+/// The positions of a cursor that starts in the middle and moves by a few positions at a time.
+fn random_walk(rng: &mut StdRng, end: u32) -> Vec<u32> {
+    let mut cursor = end / 2;
+    (0..1 << 16).map(|_| {
+        cursor = (cursor + rng.gen_range(0..8)).saturating_sub(4).min(end);
+        cursor
+    }).collect()
+}
+
+/// Many traversals around a cursor that moves by a few elements at a time, in lists built from
+/// random insertions, with and without shortcuts.
+fn local_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("local");
+    for e in [12, 20] {
+        let n: u32 = 1 << e;
+        for capacity in [0, 4] {
+            let mut rng = StdRng::seed_from_u64(0);
+            let mut list = HollowSpacedList::new();
+            for _ in 0..n {
+                list.insert(rng.gen_range(0..4 * n));
+            }
+            list.set_shortcut_capacity(capacity);
+            let mut cursors = random_walk(&mut rng, list.end()).into_iter().cycle();
+            let name = if capacity == 0 { "at_or_before" } else { "at_or_before with shortcuts" };
+            group.bench_with_input(BenchmarkId::new(name, e), &list, |b, list| b.iter(|| {
+                black_box(list.at_or_before(cursors.next().unwrap()));
+            }));
+
+            let mut list = HollowRangeSpacedList::new();
+            for _ in 0..n {
+                let start = rng.gen_range(0..8 * n);
+                let _ = list.try_insert(start, start + rng.gen_range(0..4));
+            }
+            list.set_shortcut_capacity(capacity);
+            let mut cursors = random_walk(&mut rng, list.end()).into_iter().cycle();
+            let name = if capacity == 0 { "starting_at_or_after" } else { "starting_at_or_after with shortcuts" };
+            group.bench_with_input(BenchmarkId::new(name, e), &list, |b, list| b.iter(|| {
+                black_box(list.starting_at_or_after(cursors.next().unwrap()));
+            }));
+        }
+    }
+    group.finish();
+}
+
 // criterion_group!(benches, criterion_benchmark);
 criterion_group!{
     name = benches;
     config = Criterion::default().warm_up_time(Duration::from_secs(3)).measurement_time(Duration::from_secs(5)).noise_threshold(0.04);
-    targets = criterion_benchmark
+    targets = criterion_benchmark, local_benchmark
 }
 criterion_main!(benches);
\ No newline at end of file
@@ -15,7 +15,6 @@
 
 #![feature(trait_alias)]
 #![feature(macro_metavar_expr)]
-#![feature(option_take_if)]
 
 // used ONLY for the prefetch_read_data intrinsic, which is used in loop.rs for a significant performance gain
 #![allow(internal_features)]
@@ -63,6 +62,34 @@ pub use {
     skeleton::nested_range::NestedRangePushError,
     skeleton::nested_range::NestedRangeInsertionError,
     skeleton::change_spacing::SpacingError,
+    skeleton::change_spacing::SpacingEdit,
+    skeleton::removal::RemovalError,
+    skeleton::index::StaleIndexError,
+    skeleton::compaction::Compaction,
+    skeleton::construction::FromSortedIterError,
+    skeleton::insertion::BatchInsertionError,
+    skeleton::insertion::RelativeInsertionError,
+    skeleton::deletion::StraddlePolicy,
+    skeleton::deletion::DeletionError,
+    skeleton::splitting::SplitError,
+    skeleton::splitting::AppendError,
+    skeleton::nesting::RangeOrder,
+    skeleton::nesting::NestedRanges,
+    skeleton::nesting::HollowNestedRanges,
+    skeleton::traversal::iteration::Iter,
+    skeleton::traversal::iteration::HollowIter,
+    skeleton::traversal::iteration::Ranges,
+    skeleton::traversal::iteration::HollowRanges,
+    skeleton::traversal::iteration::Window,
+    skeleton::traversal::iteration::HollowWindow,
+    skeleton::traversal::iteration::RangeWindow,
+    skeleton::traversal::iteration::HollowRangeWindow,
+    skeleton::values::Values,
+    skeleton::values::ValuesMut,
+    skeleton::values::ValuesIter,
+    skeleton::values::ValuesIterMut,
+    skeleton::cursor::Cursor,
+    skeleton::cursor::CursorMut,
 };
 
 
@@ -1,7 +1,7 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use crate::{PushError, SpacingError, Spacing};
+use crate::{PushError, SpacingEdit, SpacingError, Spacing};
 use crate::manager::{LockedPosition, Manager};
 
 macro_rules! handle {
@@ -67,6 +67,15 @@ impl<S: Spacing, T> PositionsHandle<S, T> {
     pub fn try_decrease_spacing_before(&mut self, position: S, change: S) -> Result<(), SpacingError<S>> {
         self.manager.borrow_mut().list.try_decrease_spacing_before(position, change)
     }
+
+
+    pub fn apply_spacing_edits(&mut self, edits: impl IntoIterator<Item=SpacingEdit<S>>) {
+        self.manager.borrow_mut().list.apply_spacing_edits(edits);
+    }
+
+    pub fn try_apply_spacing_edits(&mut self, edits: impl IntoIterator<Item=SpacingEdit<S>>) -> Result<(), SpacingError<S>> {
+        self.manager.borrow_mut().list.try_apply_spacing_edits(edits)
+    }
 }
 
 impl<S: Spacing, T> InsertionsHandle<S, T> {
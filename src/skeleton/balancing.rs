@@ -0,0 +1,231 @@
+//! Inserting an element between two others puts it into a sub, and inserting it before the first
+//! element of a skeleton displaces that element into a sub, so some insertion orders make subs
+//! nest very deeply. Inserting random elements, for example, makes them about as deep as the square
+//! root of the number of elements, and inserting elements in descending order right after another
+//! element makes them as deep as the number of elements. Traversal has to descend through all of
+//! them, so it would take linear time.
+//!
+//! To prevent this, skeletons are kept balanced in the same way as a scapegoat tree. The only subs
+//! that count are the ones in gaps between elements (for nested ranges, not the subs holding inner
+//! ranges, as their nesting is part of the data, and for hypers, not their subs, as they never get
+//! deeper than a few levels). After an element has been inserted more than `log(n)` such subs deep
+//! (with `n` being the size of the spaced list, and the base of the logarithm being
+//! `1 / WEIGHT_LIMIT`), the lowest skeleton on its path that has a sub holding more than
+//! [`WEIGHT_LIMIT`] of its elements is compacted (see [`compaction`]). Such a skeleton always
+//! exists, and as compacting it leaves no gap subs behind, it takes many insertions into the same
+//! sub before it needs to be compacted again.
+//!
+//! This gives the following bounds, for a spaced list of `n` elements, which is `m` ranges deep for
+//! nested ranges:
+//! - Every element is at most `O(log n + m)` subs deep, so `before`, `at`, `after` and all other
+//!   traversals take `O((log n + m) * log n)` time in the worst case.
+//! - Insertions take the same time, plus `O(log n)` amortized for compacting.
+//!
+//! Removals don't make elements any deeper, but they make `n` smaller, so after many of them, the
+//! bound is the one for the size that the spaced list had before.
+//!
+//! [`compaction`]: super::compaction
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::{EphemeralPosition, NestedRange, Node, ParentData, Range, Skeleton, Spacing};
+use crate::skeleton::insertion::Inserted;
+
+/// The largest part of the elements of a skeleton that one of its subs may contain, when an
+/// insertion has made elements deeper than allowed.
+pub(crate) const WEIGHT_LIMIT: f64 = 2.0 / 3.0;
+
+/// Returns how many gap subs deep an element may be inserted into a spaced list of the given size.
+fn max_depth(size: usize) -> usize {
+    (size.max(1) as f64).log(1.0 / WEIGHT_LIMIT) as usize
+}
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Returns the skeleton that needs to be compacted after an insertion into the given skeleton,
+    /// if any. `nested` tells whether the subs at start indices hold inner ranges.
+    fn scapegoat(deepest: Rc<RefCell<Self>>, nested: bool) -> Option<Rc<RefCell<Self>>> {
+        // the skeletons on the path to the root that are gap subs, and the root itself
+        let mut path = vec![];
+        let mut skeleton = deepest;
+        loop {
+            let parent_data = skeleton.borrow().parent_data.as_ref()
+                .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent));
+            match parent_data {
+                None => break,
+                Some((parent, index_in_parent)) => {
+                    if !parent.borrow().hyper && (!nested || index_in_parent % 2 == 1) {
+                        path.push(skeleton);
+                    }
+                    skeleton = parent;
+                }
+            }
+        }
+        // the root skeleton holds all elements of the spaced list
+        if path.len() <= max_depth(skeleton.borrow().count()) {
+            return None;
+        }
+        path.push(skeleton);
+
+        for pair in path.windows(2) {
+            let (sub, skeleton) = (&pair[0], &pair[1]);
+            if sub.borrow().count() as f64 > WEIGHT_LIMIT * skeleton.borrow().count() as f64 {
+                return Some(skeleton.clone());
+            }
+        }
+        None
+    }
+
+    /// Compacts the skeleton that an insertion has made too deep, if any (see the module
+    /// documentation), using the given compaction function. Returns the root skeleton, which may
+    /// have been replaced, and where the inserted element is now.
+    fn balance_with(
+        root: Rc<RefCell<Self>>,
+        inserted: Inserted<Kind, S, T>,
+        nested: bool,
+        compact: impl Fn(Rc<RefCell<Self>>) -> Rc<RefCell<Self>>,
+    ) -> (Rc<RefCell<Self>>, EphemeralPosition<Kind, S, T>) {
+        let Some(scapegoat) = Self::scapegoat(inserted.deepest, nested) else {
+            return (root, inserted.position);
+        };
+        let index = inserted.position.index().persistent();
+        let root = if scapegoat.borrow().parent_data.is_none() {
+            compact(scapegoat)
+        } else {
+            compact(scapegoat);
+            root
+        };
+        // cannot fail, as compacting keeps indices valid
+        (root, index.resolve().unwrap().position())
+    }
+}
+
+impl<S: Spacing, T> Skeleton<Node, S, T> {
+    pub(crate) fn balance(root: Rc<RefCell<Self>>, inserted: Inserted<Node, S, T>)
+                          -> (Rc<RefCell<Self>>, EphemeralPosition<Node, S, T>) {
+        Self::balance_with(root, inserted, false, |skeleton| Self::compact(skeleton).0)
+    }
+}
+
+impl<S: Spacing, T> Skeleton<Range, S, T> {
+    pub(crate) fn balance(root: Rc<RefCell<Self>>, inserted: Inserted<Range, S, T>)
+                          -> (Rc<RefCell<Self>>, EphemeralPosition<Range, S, T>) {
+        Self::balance_with(root, inserted, false, |skeleton| Self::compact(skeleton).0)
+    }
+}
+
+impl<S: Spacing, T> Skeleton<NestedRange, S, T> {
+    pub(crate) fn balance(root: Rc<RefCell<Self>>, inserted: Inserted<NestedRange, S, T>)
+                          -> (Rc<RefCell<Self>>, EphemeralPosition<NestedRange, S, T>) {
+        Self::balance_with(root, inserted, true, |skeleton| Self::compact(skeleton).0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{NestedRangeSpacedList, RangeSpacedList, Skeleton, SpacedList, Spacing};
+
+    use super::max_depth;
+
+    /// Returns how many gap subs deep the deepest skeleton is.
+    fn depth<Kind, S: Spacing, T>(root: Rc<RefCell<Skeleton<Kind, S, T>>>, nested: bool) -> usize {
+        let mut deepest = 0;
+        let mut skeletons = vec![(root, 0)];
+        while let Some((skeleton, depth)) = skeletons.pop() {
+            deepest = deepest.max(depth);
+            let skeleton = skeleton.borrow();
+            for (index, sub) in skeleton.subs.iter().enumerate() {
+                if let Some(sub) = sub {
+                    let gap = !skeleton.hyper && (!nested || index % 2 == 1);
+                    skeletons.push((sub.clone(), depth + gap as usize));
+                }
+            }
+        }
+        deepest
+    }
+
+    #[test]
+    fn descending() {
+        let mut list: SpacedList<i64, i64> = SpacedList::new();
+        list.insert(0, 0);
+        let mut indices = vec![];
+        for i in (1..5_000).rev() {
+            let position = list.insert(i, i);
+            assert_eq!(position.position(), i);
+            indices.push((position.index(), i));
+        }
+        assert!(depth(list.skeleton.clone(), false) <= max_depth(list.size()));
+        assert_eq!(list.iter().map(|pos| pos.element().unwrap()).collect_vec(), (0..5_000).collect_vec());
+        for (index, element) in indices {
+            assert_eq!(index.element().unwrap(), element);
+            assert_eq!(index.position().position(), element);
+        }
+    }
+
+    #[test]
+    fn random() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut list: SpacedList<u64, u64> = SpacedList::new();
+        let mut indices = vec![];
+        for _ in 0..20_000 {
+            let position = rng.gen_range(0..1_000_000);
+            indices.push((list.insert(position, position).index(), position));
+        }
+        assert!(depth(list.skeleton.clone(), false) <= max_depth(list.size()));
+        let positions = list.iter().map(|pos| pos.position()).collect_vec();
+        assert_eq!(positions, indices.iter().map(|(_, position)| *position).sorted().collect_vec());
+        for (index, position) in indices {
+            assert_eq!(index.element().unwrap(), position);
+        }
+        assert_eq!(list.at_or_after(500_000).unwrap().position(),
+                   *positions.iter().find(|&&position| position >= 500_000).unwrap());
+    }
+
+    #[test]
+    fn ranges() {
+        let mut list: RangeSpacedList<i64, i64> = RangeSpacedList::new();
+        list.insert(0, 1, 0);
+        let mut indices = vec![];
+        for i in (1..3_000).rev() {
+            let position = list.insert(2 * i, 2 * i + 1, i);
+            assert_eq!(position.position(), 2 * i);
+            indices.push((position.index(), i));
+        }
+        assert!(depth(list.skeleton.clone(), false) <= max_depth(list.size()));
+        assert_eq!(list.iter_ranges().map(|(start, end)| (start.position(), end.position())).collect_vec(),
+                   (0..3_000).map(|i| (2 * i, 2 * i + 1)).collect_vec());
+        for (index, element) in indices {
+            assert_eq!(index.element().unwrap(), element);
+        }
+    }
+
+    #[test]
+    fn nested_ranges() {
+        let mut list: NestedRangeSpacedList<i64, i64> = NestedRangeSpacedList::new();
+        list.insert(0, 100_000, -1);
+        list.insert(0, 1, 0);
+        let mut indices = vec![];
+        for i in (1..3_000).rev() {
+            let outer = list.insert(10 * i, 10 * i + 5, i);
+            let inner = list.insert(10 * i + 1, 10 * i + 2, -i);
+            indices.push((outer.index(), i));
+            indices.push((inner.index(), -i));
+        }
+        assert!(depth(list.skeleton.clone(), true) <= max_depth(list.size()) + 2);
+        assert_eq!(list.starting_at(20).unwrap().element().unwrap(), 2);
+        assert_eq!(list.starting_after(20).unwrap().element().unwrap(), -2);
+        assert_eq!(list.ending_at(25).unwrap().element().unwrap(), 2);
+        assert_eq!(list.ending_after(25).unwrap().element().unwrap(), -3);
+        for (index, element) in indices {
+            assert_eq!(index.element().unwrap(), element);
+        }
+        assert!(list.try_insert(24, 26, 0).is_err());
+    }
+}
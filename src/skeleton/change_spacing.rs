@@ -5,7 +5,7 @@ use std::rc::Rc;
 use num_traits::zero;
 use thiserror::Error;
 
-use crate::{display_unwrap, Skeleton, Spacing};
+use crate::{display_unwrap, EphemeralPosition, ParentData, Skeleton, Spacing, StaleIndexError};
 use crate::skeleton::{get_link_index, relative_depth};
 
 #[derive(Error, Debug)]
@@ -24,6 +24,249 @@ pub enum SpacingError<S: Spacing> {
         change: S,
         spacing: S,
     },
+    #[error("Cannot apply the spacing edit at position {position}, as it comes after an edit at a later position.")]
+    EditsNotSorted {
+        position: S,
+    },
+    #[error("Cannot change spacing next to an element that is no longer in the list: {0}")]
+    StaleIndex(#[from] StaleIndexError),
+    #[error("Cannot change spacing next to an element of another list.")]
+    ElementOfOtherList,
+}
+
+/// A change of the spacing at a position, made like the function of the same name does (for
+/// example, [`Skeleton::try_increase_spacing_after`] for [`SpacingEdit::IncreaseAfter`]).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SpacingEdit<S: Spacing> {
+    IncreaseAfter {
+        position: S,
+        change: S,
+    },
+    IncreaseBefore {
+        position: S,
+        change: S,
+    },
+    DecreaseAfter {
+        position: S,
+        change: S,
+    },
+    DecreaseBefore {
+        position: S,
+        change: S,
+    },
+}
+
+impl<S: Spacing> SpacingEdit<S> {
+    pub fn position(&self) -> S {
+        match *self {
+            Self::IncreaseAfter { position, .. } | Self::IncreaseBefore { position, .. }
+            | Self::DecreaseAfter { position, .. } | Self::DecreaseBefore { position, .. } => position,
+        }
+    }
+
+    pub fn change(&self) -> S {
+        match *self {
+            Self::IncreaseAfter { change, .. } | Self::IncreaseBefore { change, .. }
+            | Self::DecreaseAfter { change, .. } | Self::DecreaseBefore { change, .. } => change,
+        }
+    }
+
+    fn is_after(&self) -> bool {
+        matches!(self, Self::IncreaseAfter { .. } | Self::DecreaseAfter { .. })
+    }
+
+    fn is_increase(&self) -> bool {
+        matches!(self, Self::IncreaseAfter { .. } | Self::IncreaseBefore { .. })
+    }
+
+    /// Returns the edit that undoes this one.
+    fn inverse(self) -> Self {
+        match self {
+            Self::IncreaseAfter { position, change } => Self::DecreaseAfter { position, change },
+            Self::IncreaseBefore { position, change } => Self::DecreaseBefore { position, change },
+            Self::DecreaseAfter { position, change } => Self::IncreaseAfter { position, change },
+            Self::DecreaseBefore { position, change } => Self::IncreaseBefore { position, change },
+        }
+    }
+
+    /// Returns an edit with the same effect whose change isn't negative.
+    fn normalized(self) -> Self {
+        let change = self.change();
+        if change >= zero() {
+            return self;
+        }
+        match self.inverse() {
+            Self::IncreaseAfter { position, .. } => Self::IncreaseAfter { position, change: zero::<S>() - change },
+            Self::IncreaseBefore { position, .. } => Self::IncreaseBefore { position, change: zero::<S>() - change },
+            Self::DecreaseAfter { position, .. } => Self::DecreaseAfter { position, change: zero::<S>() - change },
+            Self::DecreaseBefore { position, .. } => Self::DecreaseBefore { position, change: zero::<S>() - change },
+        }
+    }
+}
+
+/// The number of links per edit up to which a skeleton is swept through linearly by
+/// [`Skeleton::try_apply_spacing_edits`], instead of being traversed for every edit.
+const LINEAR_SWEEP_RATIO: usize = 64;
+
+/// The skeletons that [`Skeleton::try_apply_spacing_edits`] has descended into, from the root to
+/// the deepest one, and every change it has made to a link or offset so far, to undo them if an
+/// edit fails.
+struct Sweep<Kind, S: Spacing, T> {
+    levels: Vec<SweepLevel<Kind, S, T>>,
+    changes: Vec<Change<Kind, S, T>>,
+}
+
+/// An increase (if the bool is true) or decrease of a link of a skeleton, or of its offset if
+/// there is no index.
+type Change<Kind, S, T> = (Rc<RefCell<Skeleton<Kind, S, T>>>, Option<usize>, bool, S);
+
+impl<Kind, S: Spacing, T> Sweep<Kind, S, T> {
+    fn change(&mut self, depth: usize, index: Option<usize>, increase: bool, change: S) {
+        let level = &mut self.levels[depth];
+        level.change(index, increase, change);
+        self.changes.push((level.skeleton.clone(), index, increase, change));
+    }
+
+    /// Leaves the deepest skeleton, applying the changes of its extent to its link in its parent.
+    fn leave(&mut self) {
+        let level = self.levels.pop().unwrap();
+        let index = level.skeleton.borrow().parent_data.as_ref().unwrap().index_in_parent;
+        let depth = self.levels.len() - 1;
+        // increasing first keeps the link from becoming negative in between
+        for (increase, change) in [(true, level.increase), (false, level.decrease)] {
+            if change != zero() {
+                self.change(depth, Some(index), increase, change);
+            }
+        }
+        let parent = &mut self.levels[depth];
+        parent.increase += level.increase;
+        parent.decrease += level.decrease;
+        level.finish();
+    }
+}
+
+/// A skeleton that [`Skeleton::try_apply_spacing_edits`] has descended into, along with the
+/// position its positions are relative to, and the changes of its extent that haven't been
+/// applied to its link in its parent yet.
+///
+/// If the skeleton receives an edit for every [`LINEAR_SWEEP_RATIO`] links or fewer, its links are
+/// turned into the spacings between its indices, which are changed directly instead, and a cursor
+/// walks back through them to find the index of every edit. The links are rebuilt from the
+/// spacings when the sweep leaves the skeleton.
+struct SweepLevel<Kind, S: Spacing, T> {
+    skeleton: Rc<RefCell<Skeleton<Kind, S, T>>>,
+    origin: S,
+    increase: S,
+    decrease: S,
+    linear: Option<LinearSweep<S>>,
+}
+
+struct LinearSweep<S> {
+    spacings: Vec<S>,
+    /// The index that the last edit was found at (or the last index), and its position.
+    index: usize,
+    position: S,
+}
+
+impl<Kind, S: Spacing, T> SweepLevel<Kind, S, T> {
+    fn new(skeleton: Rc<RefCell<Skeleton<Kind, S, T>>>, origin: S, edits: usize) -> Self {
+        let linear = {
+            let skeleton = skeleton.borrow();
+            (edits * LINEAR_SWEEP_RATIO >= skeleton.links.len()).then(|| LinearSweep {
+                spacings: (0..skeleton.links.len()).map(|index| skeleton.link(index)).collect(),
+                index: skeleton.links.len(),
+                position: skeleton.last_position(),
+            })
+        };
+        Self {
+            skeleton,
+            origin,
+            increase: zero(),
+            decrease: zero(),
+            linear,
+        }
+    }
+
+    /// Returns whether the given edit descends into this skeleton from its parent.
+    fn reaches(&self, edit: &SpacingEdit<S>) -> bool {
+        let position = edit.position();
+        let end = self.skeleton.borrow().last_position();
+        if edit.is_after() {
+            position >= self.origin && position - self.origin < end
+        } else {
+            position > self.origin && position - self.origin <= end
+        }
+    }
+
+    fn record(&mut self, edit: &SpacingEdit<S>) {
+        if edit.is_increase() {
+            self.increase += edit.change();
+        } else {
+            self.decrease += edit.change();
+        }
+    }
+
+    /// Returns the last index (and its position) that is at or before the given position if
+    /// `after` is true, or before it if not. Edits must be found from the last to the first.
+    fn find(&mut self, position: S, after: bool) -> (usize, S) {
+        match &mut self.linear {
+            Some(linear) => {
+                while after && linear.position > position || !after && linear.position >= position {
+                    linear.index -= 1;
+                    linear.position -= linear.spacings[linear.index];
+                }
+                (linear.index, linear.position)
+            }
+            None => {
+                let result = if after {
+                    Skeleton::shallow_at_or_before(self.skeleton.clone(), position)
+                } else {
+                    Skeleton::shallow_before(self.skeleton.clone(), position)
+                }.unwrap();
+                (result.index, result.position)
+            }
+        }
+    }
+
+    fn link(&self, index: usize) -> S {
+        match &self.linear {
+            Some(linear) => linear.spacings[index],
+            None => self.skeleton.borrow().link(index),
+        }
+    }
+
+    fn change(&mut self, index: Option<usize>, increase: bool, change: S) {
+        let mut skeleton = self.skeleton.borrow_mut();
+        match (&mut self.linear, index) {
+            (Some(linear), Some(index)) => {
+                // the length is kept up to date, as the end of the skeleton decides which edits
+                // reach it
+                if increase {
+                    linear.spacings[index] += change;
+                    skeleton.length += change;
+                } else {
+                    linear.spacings[index] -= change;
+                    skeleton.length -= change;
+                }
+            }
+            (Some(linear), None) => {
+                skeleton.change_link_or_offset(None, increase, change);
+                if increase {
+                    linear.position += change;
+                } else {
+                    linear.position -= change;
+                }
+            }
+            (None, index) => skeleton.change_link_or_offset(index, increase, change),
+        }
+    }
+
+    /// Rebuilds the links of the skeleton, if it was swept through linearly.
+    fn finish(self) {
+        if let Some(linear) = self.linear {
+            self.skeleton.borrow_mut().rebuild_links(linear.spacings);
+        }
+    }
 }
 
 // NOTE FOR DUMMIES (LIKE ME): there are separate increase and decrease functions because S might be a non-negative type
@@ -99,17 +342,29 @@ impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
             return Ok(());
         }
         if position < this.borrow().offset {
+            // a sub can't start before the node it belongs to
+            let spacing = this.borrow().offset - position;
+            if this.borrow().parent_data.is_some() && spacing < change {
+                return Err(SpacingError::SpacingNotLargeEnough { position, change, spacing });
+            }
             this.borrow_mut().offset -= change;
             return Ok(());
         }
         let result = Self::shallow_at_or_before(this.clone(), position).unwrap();
-        this.borrow_mut().try_decrease_spacing(result.index, position, change)?;
-        if let Some(sub) = this.borrow_mut().sub(result.index) {
+        // the sub is handled first, so that nothing has been changed yet if it fails
+        if let Some(sub) = this.borrow().sub(result.index) {
             let position_in_sub = position - result.position;
             if position_in_sub < sub.borrow().last_position() {
                 Self::try_decrease_spacing_after(sub, position_in_sub, change)?;
+            } else {
+                // only the space between the end of the sub and the next node shrinks
+                let spacing = this.borrow().link(result.index) - sub.borrow().last_position();
+                if spacing < change {
+                    return Err(SpacingError::SpacingNotLargeEnough { position, change, spacing });
+                }
             }
         }
+        this.borrow_mut().try_decrease_spacing(result.index, position, change)?;
         Ok(())
     }
 
@@ -121,21 +376,168 @@ impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
             return Ok(());
         }
         if position <= this.borrow().offset {
+            // a sub can't start before the node it belongs to
+            let spacing = this.borrow().offset;
+            if this.borrow().parent_data.is_some() && spacing < change {
+                return Err(SpacingError::SpacingNotLargeEnough { position, change, spacing });
+            }
             this.borrow_mut().offset -= change;
             return Ok(());
         }
         let result = Self::shallow_before(this.clone(), position).unwrap();
-        this.borrow_mut().try_decrease_spacing(result.index, position, change)?;
-        if let Some(sub) = this.borrow_mut().sub(result.index) {
+        // the sub is handled first, so that nothing has been changed yet if it fails
+        if let Some(sub) = this.borrow().sub(result.index) {
             let position_in_sub = position - result.position;
             if position_in_sub <= sub.borrow().last_position() {
                 Self::try_decrease_spacing_before(sub, position_in_sub, change)?;
+            } else {
+                // only the space between the end of the sub and the next node shrinks
+                let spacing = this.borrow().link(result.index) - sub.borrow().last_position();
+                if spacing < change {
+                    return Err(SpacingError::SpacingNotLargeEnough { position, change, spacing });
+                }
             }
         }
+        this.borrow_mut().try_decrease_spacing(result.index, position, change)?;
         Ok(())
     }
 
 
+
+    pub fn apply_spacing_edits(this: Rc<RefCell<Self>>, edits: impl IntoIterator<Item=SpacingEdit<S>>) {
+        display_unwrap!(Self::try_apply_spacing_edits(this, edits));
+    }
+
+    /// Applies the given edits, which must be sorted by their position, in a single sweep over
+    /// this skeleton, from the last edit to the first. The sweep keeps the skeletons that the
+    /// previous edit descended through, so an edit only traverses the skeletons that the previous
+    /// one didn't reach. Skeletons that receive many edits for their size aren't traversed at all,
+    /// but swept through linearly (see [`SweepLevel`]).
+    ///
+    /// As the edits are applied from the last to the first, an edit doesn't move the positions
+    /// that the edits before it refer to, unless it decreases a spacing to below them. If one of
+    /// the edits fails, none of them are applied, and the error of the one that failed is
+    /// returned.
+    pub fn try_apply_spacing_edits(this: Rc<RefCell<Self>>, edits: impl IntoIterator<Item=SpacingEdit<S>>)
+                                   -> Result<(), SpacingError<S>> {
+        let edits = edits.into_iter().collect::<Vec<_>>();
+        for pair in edits.windows(2) {
+            if pair[1].position() < pair[0].position() {
+                return Err(SpacingError::EditsNotSorted { position: pair[1].position() });
+            }
+        }
+        for edit in &edits {
+            this.borrow().check_bounds(edit)?;
+        }
+
+        let mut sweep = Sweep {
+            levels: vec![SweepLevel::new(this.clone(), zero(), edits.len())],
+            changes: vec![],
+        };
+        let mut path = vec![];
+        let mut result = Ok(());
+        for (before, edit) in edits.iter().enumerate().rev() {
+            let edit = edit.normalized();
+            if edit.change() == zero() {
+                continue;
+            }
+            while sweep.levels.len() > 1 && !sweep.levels.last().unwrap().reaches(&edit) {
+                sweep.leave();
+            }
+            if let Err(error) = Self::sweep_spacing_edit(&mut sweep.levels, &edits[..=before], &edit, &mut path) {
+                result = Err(error);
+                break;
+            }
+            for &(depth, index) in &path {
+                sweep.change(depth, index, edit.is_increase(), edit.change());
+            }
+            let start = path[0].0;
+            sweep.levels[start].record(&edit);
+        }
+        while sweep.levels.len() > 1 {
+            sweep.leave();
+        }
+        sweep.levels.pop().unwrap().finish();
+
+        if result.is_err() {
+            for (skeleton, index, increase, change) in sweep.changes.into_iter().rev() {
+                skeleton.borrow_mut().change_link_or_offset(index, !increase, change);
+            }
+        }
+        result
+    }
+
+    fn check_bounds(&self, edit: &SpacingEdit<S>) -> Result<(), SpacingError<S>> {
+        let position = edit.position();
+        if edit.is_after() && position >= self.last_position() {
+            Err(SpacingError::PositionAtOrAfterList { position })
+        } else if !edit.is_after() && position > self.last_position() {
+            Err(SpacingError::PositionAfterList { position })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Descends from the last skeleton of the sweep, which the given edit must reach, to the
+    /// links and offsets that the edit (whose change must be positive) changes, and checks that
+    /// it can change them, the same way the function of the same name as the edit does. Sets
+    /// `path` to the depths in the sweep and indices of the links to change (or `None` for an
+    /// offset). `edits` are the edits that haven't been applied yet, including this one.
+    fn sweep_spacing_edit(levels: &mut Vec<SweepLevel<Kind, S, T>>, edits: &[SpacingEdit<S>], edit: &SpacingEdit<S>,
+                          path: &mut Vec<(usize, Option<usize>)>) -> Result<(), SpacingError<S>> {
+        path.clear();
+        let (position, change, after, increase) = (edit.position(), edit.change(), edit.is_after(), edit.is_increase());
+        let not_large_enough = |spacing| SpacingError::SpacingNotLargeEnough { position, change, spacing };
+        loop {
+            let depth = levels.len() - 1;
+            let level = &mut levels[depth];
+            let position_here = position - level.origin;
+            let offset = level.skeleton.borrow().offset;
+            if after && position_here < offset || !after && position_here <= offset {
+                let spacing = if after { offset - position_here } else { offset };
+                // a sub can't start before the node it belongs to
+                if !increase && level.skeleton.borrow().parent_data.is_some() && spacing < change {
+                    return Err(not_large_enough(spacing));
+                }
+                path.push((depth, None));
+                return Ok(());
+            }
+            let (index, index_position) = level.find(position_here, after);
+            let link = level.link(index);
+            path.push((depth, Some(index)));
+            let sub = level.skeleton.borrow().sub(index);
+            let Some(sub) = sub else {
+                if !increase && link < change {
+                    return Err(not_large_enough(link));
+                }
+                return Ok(());
+            };
+            let position_in_sub = position_here - index_position;
+            let sub_end = sub.borrow().last_position();
+            if after && position_in_sub < sub_end || !after && position_in_sub <= sub_end {
+                let origin = level.origin + index_position;
+                let edits_in_sub = edits.len() - edits.partition_point(|edit| edit.position() < origin);
+                levels.push(SweepLevel::new(sub, origin, edits_in_sub));
+            } else {
+                // only the space between the end of the sub and the next node changes
+                if !increase && link - sub_end < change {
+                    return Err(not_large_enough(link - sub_end));
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    fn change_link_or_offset(&mut self, index: Option<usize>, increase: bool, change: S) {
+        match (index, increase) {
+            (Some(index), true) => self.increase_spacing_unchecked(index, change),
+            (Some(index), false) => self.decrease_spacing_unchecked(index, change),
+            (None, true) => self.offset += change,
+            (None, false) => self.offset -= change,
+        }
+    }
+
+
     pub(super) fn increase_spacing_after_index(&mut self, index: usize, change: S) {
         if self.link_index_is_in_bounds(index) {
             self.increase_spacing(index, change);
@@ -201,9 +603,7 @@ impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
             }
             Ordering::Equal => {},
             Ordering::Greater => {
-                for degree in 0..self.depth {
-                    assert!(!(index >> degree & 1 == 0 && self.link(get_link_index(index, degree)) < change));
-                }
+                assert!(self.link(index) >= change, "Spacing not large enough");
                 self.decrease_spacing_unchecked(index, change);
             }
         }
@@ -219,14 +619,14 @@ impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
                 Ok(())
             },
             Ordering::Greater => {
-                for degree in 0..self.depth {
-                    if index >> degree & 1 == 0 && self.link(get_link_index(index, degree)) < change {
-                        return Err(SpacingError::SpacingNotLargeEnough {
-                            position,
-                            change,
-                            spacing: self.link(index)
-                        });
-                    }
+                // every link that contains this one is at least as large, so this check suffices
+                let spacing = self.link(index);
+                if spacing < change {
+                    return Err(SpacingError::SpacingNotLargeEnough {
+                        position,
+                        change,
+                        spacing,
+                    });
                 }
                 self.decrease_spacing_unchecked(index, change);
                 Ok(())
@@ -244,12 +644,360 @@ impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
     }
 }
 
+/// The spacings between consecutive indices, each along with the index before it.
+type Gaps<Kind, S, T> = Vec<(S, EphemeralPosition<Kind, S, T>)>;
+
+/// A link, by its skeleton and index, and the sub at that index that an element is the last one of.
+type LinkAfter<Kind, S, T> = (Rc<RefCell<Skeleton<Kind, S, T>>>, usize, Option<Rc<RefCell<Skeleton<Kind, S, T>>>>);
+
+// Changing the spacing next to an element instead of at a position tells apart elements at the same
+// position: everything after the element in the list moves, even elements at its own position.
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Returns the spacing between the given element (or bound) and the next one.
+    pub(crate) fn spacing_after_element(element: &EphemeralPosition<Kind, S, T>) -> Result<S, SpacingError<S>> {
+        match element.clone().into_next_element() {
+            Some(next) => Ok(next.position - element.position),
+            None => Err(SpacingError::PositionAtOrAfterList { position: element.position }),
+        }
+    }
+
+    /// Returns the spacing between the given element (or bound) and the previous one, or its
+    /// position if it is the first one.
+    pub(crate) fn spacing_before_element(element: &EphemeralPosition<Kind, S, T>) -> S {
+        match element.clone().into_previous_element() {
+            Some(previous) => element.position - previous.position,
+            None => element.position,
+        }
+    }
+
+    /// Returns the first link after the given element (or bound) on its path to the root, along
+    /// with the sub at the index of that link that the element is the last one of, if the link
+    /// isn't in the skeleton of the element itself. Elements at the end of the last sub of a hyper
+    /// are also at the end of the hyper.
+    fn link_after_element(element: &EphemeralPosition<Kind, S, T>) -> Option<LinkAfter<Kind, S, T>> {
+        if element.index < element.skeleton.borrow().links.len() {
+            return Some((element.skeleton.clone(), element.index, None));
+        }
+        let mut skeleton = element.skeleton.clone();
+        loop {
+            let (parent, index_in_parent) = skeleton.borrow().parent_data.as_ref()
+                .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent))?;
+            if !(parent.borrow().hyper && index_in_parent + 1 == parent.borrow().links.len()) {
+                return Some((parent, index_in_parent, Some(skeleton)));
+            }
+            skeleton = parent;
+        }
+    }
+
+    /// Increases or decreases the spacing between the given element (or bound) and the next one,
+    /// moving all elements after it. Only the links on the path from the element to the root
+    /// change, so this doesn't need a traversal.
+    pub(crate) fn try_change_spacing_after_element(element: &EphemeralPosition<Kind, S, T>, increase: bool, change: S)
+                                                   -> Result<(), SpacingError<S>> {
+        // removed elements can leave links behind the last element, but there is nothing to move
+        if element.clone().into_next_element().is_none() {
+            return Err(SpacingError::PositionAtOrAfterList { position: element.position });
+        }
+        if change == zero() {
+            return Ok(());
+        }
+        if increase {
+            Self::change_spacing_after_index_unchecked(element, true, change);
+            return Ok(());
+        }
+        let Some(gaps) = Self::gaps_to_next_element(element) else {
+            return Err(SpacingError::PositionAtOrAfterList { position: element.position });
+        };
+        let spacing = gaps.iter().fold(zero(), |spacing: S, (gap, _)| spacing + *gap);
+        if spacing < change {
+            return Err(SpacingError::SpacingNotLargeEnough { position: element.position, change, spacing });
+        }
+        Self::close_gaps(gaps, change);
+        Ok(())
+    }
+
+    /// Returns the spacings between the given index and each index after it up to the next one
+    /// that holds an element, along with the index before each spacing, or `None` if there is no
+    /// such index. Removed elements leave their indices behind, and each link between them can only
+    /// shrink until the index after it meets the one before it.
+    fn gaps_to_next_element(index: &EphemeralPosition<Kind, S, T>) -> Option<Gaps<Kind, S, T>> {
+        let mut gaps = vec![];
+        let mut index = index.clone();
+        loop {
+            let next = index.clone().into_next()?;
+            let holds_element = next.holds_element();
+            gaps.push((next.position - index.position, index));
+            if holds_element {
+                return Some(gaps);
+            }
+            index = next;
+        }
+    }
+
+    /// Shrinks the given gaps (see [`Self::gaps_to_next_element`]) by the given change in total,
+    /// from the first one onwards, and returns the part of the change they couldn't take.
+    fn close_gaps(gaps: Gaps<Kind, S, T>, change: S) -> S {
+        let mut remaining = change;
+        for (gap, index) in gaps {
+            let change = if gap < remaining { gap } else { remaining };
+            if change > zero() {
+                Self::change_spacing_after_index_unchecked(&index, false, change);
+                remaining -= change;
+            }
+        }
+        remaining
+    }
+
+    /// Changes the first link after the given index on its path to the root, along with every
+    /// link on the way to the root, which must leave the indices after it at or after it.
+    fn change_spacing_after_index_unchecked(index: &EphemeralPosition<Kind, S, T>, increase: bool, change: S) {
+        let Some((skeleton, index, sub)) = Self::link_after_element(index) else {
+            return;
+        };
+        match (sub, increase) {
+            (None, true) => skeleton.borrow_mut().increase_spacing_after_index(index, change),
+            (None, false) => skeleton.borrow_mut().decrease_spacing_after_index(index, change),
+            (Some(_), _) => skeleton.borrow_mut().change_link_or_offset(Some(index), increase, change),
+        }
+        // every skeleton on the way to the root gets longer or shorter by the change
+        let mut skeleton = skeleton;
+        loop {
+            let parent_data = skeleton.borrow().parent_data.as_ref()
+                .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent));
+            let Some((parent, index_in_parent)) = parent_data else {
+                return;
+            };
+            parent.borrow_mut().change_link_or_offset(Some(index_in_parent), increase, change);
+            skeleton = parent;
+        }
+    }
+
+    /// Increases or decreases the spacing between the given element (or bound) and the previous
+    /// one, moving it and all elements after it. If it is the first element, all elements move,
+    /// but the spacing before it is its position, which can't be decreased below zero.
+    pub(crate) fn try_change_spacing_before_element(root: &Rc<RefCell<Self>>, element: &EphemeralPosition<Kind, S, T>,
+                                                    increase: bool, change: S) -> Result<(), SpacingError<S>> {
+        if !increase && change > element.position && element.clone().into_previous_element().is_none() {
+            return Err(SpacingError::SpacingNotLargeEnough { position: element.position, change, spacing: element.position });
+        }
+        Self::try_change_spacing_before_element_unbounded(root, element, increase, change)
+    }
+
+    /// Like [`Self::try_change_spacing_before_element`], but moves the first element (and with it,
+    /// the whole list) by any change, even below zero.
+    pub(crate) fn try_change_spacing_before_element_unbounded(root: &Rc<RefCell<Self>>, element: &EphemeralPosition<Kind, S, T>,
+                                                              increase: bool, change: S) -> Result<(), SpacingError<S>> {
+        match element.clone().into_previous_element() {
+            Some(previous) => Self::try_change_spacing_after_element(&previous, increase, change),
+            None => {
+                let start = EphemeralPosition::at_start(root.clone());
+                // the indices of removed elements before the first element close up before the
+                // list itself moves
+                let change = if increase || start.holds_element() {
+                    change
+                } else {
+                    Self::gaps_to_next_element(&start).map_or(change, |gaps| Self::close_gaps(gaps, change))
+                };
+                root.borrow_mut().change_link_or_offset(None, increase, change);
+                Ok(())
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     // use std::collections::HashMap;
 
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+
+    use crate::{RangeSpacedList, SpacedList, SpacingEdit, SpacingError};
     use crate::skeleton::{get_link_index, relative_depth};
 
+    /// Returns random edits, which only decrease spacings if `decrease` is true (and then most
+    /// likely fail).
+    fn random_edits(rng: &mut StdRng, count: usize, end: i64, decrease: bool) -> Vec<SpacingEdit<i64>> {
+        let mut edits = (0..count)
+            .map(|_| {
+                let position = rng.gen_range(0..end);
+                let change = rng.gen_range(if decrease { -2 } else { 0 }..=4);
+                match rng.gen_range(0..if decrease { 4 } else { 2 }) {
+                    0 => SpacingEdit::IncreaseAfter { position, change },
+                    1 => SpacingEdit::IncreaseBefore { position, change },
+                    2 => SpacingEdit::DecreaseAfter { position, change: change.abs() },
+                    _ => SpacingEdit::DecreaseBefore { position, change: change.abs() },
+                }
+            })
+            .collect_vec();
+        edits.sort_by_key(SpacingEdit::position);
+        edits
+    }
+
+    /// The functions that change a single spacing report the position of an error relative to the
+    /// sub it occurred in, so only the rest of the errors can be compared.
+    fn without_position(error: SpacingError<i64>) -> (i64, i64) {
+        match error {
+            SpacingError::SpacingNotLargeEnough { change, spacing, .. } => (change, spacing),
+            error => panic!("{}", error),
+        }
+    }
+
+    /// Applies the edits one at a time, from the last to the first, or none of them if one fails.
+    macro_rules! apply_one_at_a_time {
+        ($list:expr, $untouched:expr, $edits:expr) => {{
+            let mut result = Ok(());
+            for &edit in $edits.iter().rev() {
+                result = match edit {
+                    SpacingEdit::IncreaseAfter { position, change } => $list.try_increase_spacing_after(position, change),
+                    SpacingEdit::IncreaseBefore { position, change } => $list.try_increase_spacing_before(position, change),
+                    SpacingEdit::DecreaseAfter { position, change } => $list.try_decrease_spacing_after(position, change),
+                    SpacingEdit::DecreaseBefore { position, change } => $list.try_decrease_spacing_before(position, change),
+                };
+                if result.is_err() {
+                    $list = $untouched;
+                    break;
+                }
+            }
+            result
+        }};
+    }
+
+    #[test]
+    fn batch_nodes() {
+        for seed in 0..8 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            // enough elements for the root to become a hyper, inserted in random order to create subs
+            let mut positions = (0..20_000).map(|index| index * 10 + rng.gen_range(0..5)).collect_vec();
+            positions.shuffle(&mut rng);
+            let build = || {
+                let mut list: SpacedList<i64, i64> = SpacedList::new();
+                for &position in &positions {
+                    list.insert(position, position);
+                }
+                list
+            };
+            let mut a = build();
+            let mut b = build();
+            let edits = random_edits(&mut rng, 500, a.end(), seed % 2 == 1);
+            let result = a.try_apply_spacing_edits(edits.clone());
+            let expected = apply_one_at_a_time!(b, build(), edits);
+            assert_eq!(result.map_err(without_position), expected.map_err(without_position));
+            assert_eq!(a.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec(),
+                       b.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec());
+        }
+    }
+
+    #[test]
+    fn batch_ranges() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut ranges = (0..2000).map(|index| (index * 10, index * 10 + rng.gen_range(0..5))).collect_vec();
+            ranges.shuffle(&mut rng);
+            let build = || {
+                let mut list: RangeSpacedList<i64, i64> = RangeSpacedList::new();
+                for &(start, end) in &ranges {
+                    list.insert(start, end, start);
+                }
+                list
+            };
+            let mut a = build();
+            let mut b = build();
+            let edits = random_edits(&mut rng, 200, a.end(), seed % 2 == 1);
+            let result = a.try_apply_spacing_edits(edits.clone());
+            let expected = apply_one_at_a_time!(b, build(), edits);
+            assert_eq!(result.map_err(without_position), expected.map_err(without_position));
+            assert_eq!(a.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec(),
+                       b.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec());
+        }
+    }
+
+    #[test]
+    fn batch_errors() {
+        let mut list: SpacedList<i32, char> = SpacedList::new();
+        list.insert(0, 'a');
+        list.insert(10, 'b');
+        list.insert(20, 'c');
+        list.insert(30, 'd');
+        let positions = |list: &SpacedList<i32, char>| list.iter().map(|position| position.position()).collect_vec();
+        assert!(matches!(list.try_apply_spacing_edits([
+            SpacingEdit::IncreaseAfter { position: 5, change: 3 },
+            SpacingEdit::IncreaseBefore { position: 25, change: 3 },
+            SpacingEdit::DecreaseAfter { position: 12, change: 20 },
+        ]), Err(SpacingError::EditsNotSorted { position: 12 })));
+        assert!(matches!(list.try_apply_spacing_edits([
+            SpacingEdit::IncreaseAfter { position: 5, change: 3 },
+            SpacingEdit::DecreaseBefore { position: 15, change: 20 },
+            SpacingEdit::IncreaseBefore { position: 25, change: 3 },
+        ]), Err(SpacingError::SpacingNotLargeEnough { position: 15, change: 20, spacing: 10 })));
+        assert!(matches!(list.try_apply_spacing_edits([SpacingEdit::IncreaseAfter { position: 30, change: 3 }]),
+                         Err(SpacingError::PositionAtOrAfterList { position: 30 })));
+        assert_eq!(positions(&list), [0, 10, 20, 30]);
+        list.apply_spacing_edits([
+            SpacingEdit::IncreaseAfter { position: 3, change: 3 },
+            SpacingEdit::DecreaseBefore { position: 10, change: 5 },
+            SpacingEdit::IncreaseBefore { position: 20, change: -2 },
+            SpacingEdit::IncreaseAfter { position: 20, change: 4 },
+        ]);
+        assert_eq!(positions(&list), [0, 8, 16, 30]);
+    }
+
+    #[test]
+    fn element_spacing() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: SpacedList<i64, usize> = SpacedList::new();
+            // few distinct positions, so that many elements share theirs
+            let indices = (0..3_000).map(|element| list.insert(rng.gen_range(0..1_000) * 3, element).index()).collect_vec();
+            let mut removed = vec![false; indices.len()];
+            for _ in 0..500 {
+                let element = rng.gen_range(0..indices.len());
+                if !removed[element] {
+                    list.remove(indices[element].clone());
+                    removed[element] = true;
+                }
+            }
+            // elements at the same position are in the order of the list, which the changes keep
+            let mut expected = list.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec();
+            for _ in 0..1_000 {
+                let at = rng.gen_range(0..expected.len());
+                let index = &indices[expected[at].1];
+                let change = rng.gen_range(0..10);
+                let after = rng.gen_bool(0.5);
+                let moved = if after { at + 1 } else { at };
+                let spacing = match (after, moved) {
+                    (true, moved) if moved == expected.len() => None,
+                    (false, 0) => Some(expected[0].0),
+                    (_, moved) => Some(expected[moved].0 - expected[moved - 1].0),
+                };
+                let (result, new_spacing) = match rng.gen_range(0..3) {
+                    0 if after => (list.try_increase_spacing_after_element(index, change), spacing.map(|spacing| spacing + change)),
+                    0 => (list.try_increase_spacing_before_element(index, change), spacing.map(|spacing| spacing + change)),
+                    1 if after => (list.try_decrease_spacing_after_element(index, change), spacing.map(|spacing| spacing - change)),
+                    1 => (list.try_decrease_spacing_before_element(index, change), spacing.map(|spacing| spacing - change)),
+                    _ if after => (list.try_set_spacing_after(index, change), Some(change)),
+                    _ => (list.try_set_spacing_before(index, change), Some(change)),
+                };
+                match (spacing, new_spacing) {
+                    (Some(spacing), Some(new_spacing)) if new_spacing >= 0 || moved == 0 => {
+                        result.unwrap();
+                        for (position, _) in &mut expected[moved..] {
+                            *position += new_spacing - spacing;
+                        }
+                    }
+                    (None, _) => assert!(matches!(result, Err(SpacingError::PositionAtOrAfterList { .. }))),
+                    _ => assert!(matches!(result, Err(SpacingError::SpacingNotLargeEnough { .. }))),
+                }
+            }
+            assert_eq!(list.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec(),
+                       expected);
+            list.remove(indices[expected[0].1].clone());
+            assert!(matches!(list.try_set_spacing_after(&indices[expected[0].1], 1), Err(SpacingError::StaleIndex(_))));
+        }
+    }
+
     #[test]
     fn test() {
         // let mut approaches = HashMap::new();
@@ -0,0 +1,360 @@
+//! Removing elements leaves empty element slots behind, and inserting elements between existing
+//! ones creates subs, so a skeleton that has seen a lot of changes can end up much bigger and
+//! deeper than needed for the elements it contains. Compacting it rebuilds it from scratch by
+//! pushing all of its elements onto a new skeleton in order, which creates no empty slots and only
+//! the subs that are really needed: hypers for big lists, and for nested ranges, one sub for the
+//! inner ranges of each range that has any.
+//!
+//! Every element that is moved into the new skeleton is recorded as moved (see [`index`]), so
+//! persistent indices keep referring to the same element. The old skeletons are emptied, so that
+//! indices that are still around only keep the bookkeeping needed to find their element alive.
+//!
+//! Besides whole spaced lists, single subs are compacted as well, when insertions have made them
+//! too deep (see [`balancing`]).
+//!
+//! [`index`]: super::index
+//! [`balancing`]: super::balancing
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use num_traits::zero;
+
+use crate::{BoundType, EphemeralIndex, EphemeralPosition, Index, NestedRange, Node, ParentData, Range, Skeleton, Spacing};
+use crate::skeleton::index::NO_GENERATION;
+
+/// How much smaller compacting a spaced list has made it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Compaction {
+    /// The number of subs that are no longer needed.
+    pub reclaimed_subs: usize,
+    /// The number of element slots that are no longer needed, counting those of subs.
+    pub reclaimed_slots: usize,
+}
+
+/// A bound of an element that has been taken out of a skeleton that is being compacted (or split),
+/// in the order in which they appear in the skeleton.
+pub(super) enum Bound<Kind, S: Spacing, T> {
+    /// The start of a range or a node, with its index (none for elements that haven't been placed
+    /// before), position, span (zero for nodes) and element.
+    Start(Option<Index<Kind, S, T>>, S, S, T),
+    /// The end of the innermost range that has started, but not ended yet.
+    End,
+}
+
+/// A skeleton that elements are pushed onto while compacting.
+struct Level<Kind, S: Spacing, T> {
+    /// None for the sub of a range that has no inner ranges yet, as it is only created once the
+    /// first one is pushed.
+    skeleton: Option<Rc<RefCell<Skeleton<Kind, S, T>>>>,
+    /// The start index of the range whose inner ranges are pushed onto this level, if any.
+    start: Option<EphemeralIndex<Kind, S, T>>,
+    position: S,
+}
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Returns the number of subs below this skeleton and the number of element slots in this
+    /// skeleton and all of them.
+    fn count_subs_and_slots(this: Rc<RefCell<Self>>) -> (usize, usize) {
+        let mut subs = 0;
+        let mut slots = 0;
+        let mut skeletons = vec![this];
+        while let Some(skeleton) = skeletons.pop() {
+            let skeleton = skeleton.borrow();
+            slots += skeleton.elements.len();
+            for sub in skeleton.subs.iter().flatten() {
+                subs += 1;
+                skeletons.push(sub.clone());
+            }
+        }
+        (subs, slots)
+    }
+
+    /// Takes all elements out of this skeleton and its subs, in order. Their positions are relative
+    /// to the parent of this skeleton, like its offset.
+    pub(super) fn take_bounds(this: Rc<RefCell<Self>>, links_per_element: usize) -> Vec<Bound<Kind, S, T>> {
+        let mut bounds = vec![];
+        let offset = this.borrow().offset;
+        // the indices still to be visited, with their position; the contents of the sub of an
+        // index come between that index and the next one
+        let mut stack = vec![(this, 0, offset)];
+        while let Some((skeleton, index, position)) = stack.pop() {
+            if skeleton.borrow().generation(index) != NO_GENERATION {
+                if links_per_element == 1 || BoundType::of(index) == BoundType::Start {
+                    let persistent = EphemeralIndex::new(skeleton.clone(), index).persistent();
+                    let mut skeleton = skeleton.borrow_mut();
+                    let span = if links_per_element == 1 { zero() } else { skeleton.link(index) };
+                    // cannot fail, as there is an element at every index that has a generation
+                    let element = skeleton.elements[index / links_per_element].take().unwrap();
+                    bounds.push(Bound::Start(Some(persistent), position, span, element));
+                } else {
+                    bounds.push(Bound::End);
+                }
+            }
+            let skeleton_ref = skeleton.borrow();
+            if index < skeleton_ref.links.len() {
+                stack.push((skeleton.clone(), index + 1, position + skeleton_ref.link(index)));
+            }
+            if let Some(sub) = skeleton_ref.sub(index) {
+                let sub_position = position + sub.borrow().offset;
+                stack.push((sub, 0, sub_position));
+            }
+        }
+        bounds
+    }
+
+    /// Empties this skeleton and all of its subs, keeping only what indices into them need to find
+    /// where their elements have been moved.
+    pub(super) fn retire(this: Rc<RefCell<Self>>) {
+        let mut skeletons = vec![this];
+        while let Some(skeleton) = skeletons.pop() {
+            let mut skeleton = skeleton.borrow_mut();
+            skeletons.extend(skeleton.subs.iter().flatten().cloned());
+            skeleton.clear();
+            skeleton.hyper = false;
+            skeleton.parent_data = None;
+        }
+    }
+
+    /// Rebuilds this skeleton by pushing its elements onto a new one, using the given push
+    /// function, and returns the new skeleton. For nested ranges, the inner ranges of a range are
+    /// pushed onto the sub at its start index. If this skeleton is a sub, the new skeleton takes
+    /// its place in the parent.
+    fn compact_with(
+        this: Rc<RefCell<Self>>,
+        links_per_element: usize,
+        push: impl Fn(Rc<RefCell<Self>>, S, S, T) -> EphemeralPosition<Kind, S, T>,
+    ) -> (Rc<RefCell<Self>>, Compaction) {
+        let (old_subs, old_slots) = Self::count_subs_and_slots(this.clone());
+        let parent_data = this.borrow().parent_data.as_ref()
+            .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent));
+        let count = this.borrow().count();
+        let bounds = Self::take_bounds(this.clone(), links_per_element);
+        Self::retire(this);
+
+        let skeleton = Self::new(parent_data.as_ref().map(|(parent, index_in_parent)| ParentData {
+            parent: Rc::downgrade(parent),
+            index_in_parent: *index_in_parent,
+        }));
+        if let Some((parent, index_in_parent)) = &parent_data {
+            let mut parent = parent.borrow_mut();
+            parent.subs[*index_in_parent] = Some(skeleton.clone());
+            // the elements are counted again as they are pushed onto the new skeleton
+            parent.decrease_count(*index_in_parent, count);
+        }
+        let skeleton = Self::push_bounds(skeleton, bounds, links_per_element, push);
+
+        let (new_subs, new_slots) = Self::count_subs_and_slots(skeleton.clone());
+        (skeleton, Compaction {
+            reclaimed_subs: old_subs.saturating_sub(new_subs),
+            reclaimed_slots: old_slots.saturating_sub(new_slots),
+        })
+    }
+
+    /// Pushes the given bounds onto the given empty skeleton, using the given push function, and
+    /// returns it, or the hyper that has taken its place. The positions of the bounds are relative
+    /// to the parent of the skeleton, and the elements that already had an index are recorded as
+    /// moved.
+    pub(super) fn push_bounds(
+        skeleton: Rc<RefCell<Self>>,
+        bounds: Vec<Bound<Kind, S, T>>,
+        links_per_element: usize,
+        push: impl Fn(Rc<RefCell<Self>>, S, S, T) -> EphemeralPosition<Kind, S, T>,
+    ) -> Rc<RefCell<Self>> {
+        let mut levels = vec![Level { skeleton: Some(skeleton), start: None, position: zero() }];
+        for bound in bounds {
+            let Bound::Start(index, position, span, element) = bound else {
+                levels.pop();
+                continue;
+            };
+            let level = levels.last_mut().unwrap();
+            let skeleton = level.skeleton.take().unwrap_or_else(|| {
+                let start = level.start.as_ref().unwrap();
+                Self::ensure_sub(start.skeleton.clone(), start.index)
+            });
+            // pushing onto a full skeleton turns it into a hyper, which takes its place
+            let skeleton = Self::make_room(skeleton);
+            level.skeleton = Some(skeleton.clone());
+            let distance = if skeleton.borrow().elements.is_empty() {
+                position - level.position
+            } else {
+                position - level.position - skeleton.borrow().last_position()
+            };
+            let pushed = push(skeleton, distance, span, element).into_index();
+            if let Some(index) = index {
+                Self::record_move(index, pushed.clone(), links_per_element);
+            }
+            if links_per_element == 2 {
+                levels.push(Level { skeleton: None, start: Some(pushed), position });
+            }
+        }
+        levels.swap_remove(0).skeleton.unwrap()
+    }
+
+    /// Frees the memory that this skeleton and its subs have allocated, but don't use.
+    pub(crate) fn shrink_to_fit(this: Rc<RefCell<Self>>) {
+        let mut skeletons = vec![this];
+        while let Some(skeleton) = skeletons.pop() {
+            let mut skeleton = skeleton.borrow_mut();
+            skeletons.extend(skeleton.subs.iter().flatten().cloned());
+            skeleton.links.shrink_to_fit();
+            skeleton.elements.shrink_to_fit();
+            skeleton.subs.shrink_to_fit();
+            skeleton.generations.shrink_to_fit();
+            skeleton.moved.shrink_to_fit();
+            skeleton.origins.shrink_to_fit();
+            skeleton.occupancy.shrink_to_fit();
+            skeleton.counts.shrink_to_fit();
+        }
+    }
+}
+
+impl<S: Spacing, T> Skeleton<Node, S, T> {
+    pub(crate) fn compact(this: Rc<RefCell<Self>>) -> (Rc<RefCell<Self>>, Compaction) {
+        // cannot fail, as the elements are pushed in order
+        Self::compact_with(this, 1, |skeleton, distance, _, element|
+            Self::try_push(skeleton, distance, element).unwrap())
+    }
+}
+
+impl<S: Spacing, T> Skeleton<Range, S, T> {
+    pub(crate) fn compact(this: Rc<RefCell<Self>>) -> (Rc<RefCell<Self>>, Compaction) {
+        // cannot fail, as the ranges are pushed in order
+        Self::compact_with(this, 2, |skeleton, distance, span, element|
+            Self::try_push(skeleton, distance, span, element).unwrap())
+    }
+}
+
+impl<S: Spacing, T> Skeleton<NestedRange, S, T> {
+    pub(crate) fn compact(this: Rc<RefCell<Self>>) -> (Rc<RefCell<Self>>, Compaction) {
+        // cannot fail, as the ranges are pushed in order
+        Self::compact_with(this, 2, |skeleton, distance, span, element|
+            Self::try_push(skeleton, distance, span, element).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{NestedRangeSpacedList, RangeSpacedList, Skeleton, SpacedList};
+
+    #[test]
+    fn nodes() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut list: SpacedList<i64, i64> = SpacedList::new();
+        let mut indices = vec![];
+        for _ in 0..2_000 {
+            let position = rng.gen_range(-100_000..100_000);
+            if list.at(position).is_none() {
+                indices.push((position, list.insert(position, position).index()));
+            }
+        }
+        let (live, removed): (Vec<_>, Vec<_>) = indices.into_iter().partition(|_| rng.gen_bool(0.3));
+        for (_, index) in &removed {
+            list.remove(index.clone());
+        }
+        let contents = list.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec();
+        let (subs, slots) = Skeleton::count_subs_and_slots(list.skeleton.clone());
+        assert!(subs > 0);
+
+        let compaction = list.compact();
+        assert_eq!(compaction.reclaimed_subs, subs);
+        assert_eq!(compaction.reclaimed_slots, slots - live.len());
+        assert_eq!(Skeleton::count_subs_and_slots(list.skeleton.clone()), (0, live.len()));
+        assert_eq!(list.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec(), contents);
+        assert_eq!(list.size(), live.len());
+        for (position, index) in &live {
+            assert_eq!(index.element().unwrap(), *position);
+            assert_eq!(index.position().position(), *position);
+        }
+        for (_, index) in &removed {
+            assert!(!index.is_valid());
+        }
+
+        // compacting again changes nothing, and the compacted list works as usual
+        assert_eq!(list.shrink_to_fit(), Default::default());
+        for (position, index) in live {
+            assert_eq!(list.remove(index), position);
+        }
+        assert!(list.is_empty());
+        list.insert(5, 5);
+        assert_eq!(list.iter().map(|pos| pos.position()).collect_vec(), vec![5]);
+    }
+
+    #[test]
+    fn big_nodes() {
+        let mut list: SpacedList<u64, u64> = SpacedList::new();
+        let indices = (0..50_000).map(|i| list.push(2, i).index()).collect_vec();
+        for index in indices.iter().step_by(2) {
+            list.remove(index.clone());
+        }
+        let compaction = list.compact();
+        assert!(compaction.reclaimed_slots >= 25_000);
+        assert!(list.skeleton.borrow().hyper);
+        for (i, index) in indices.iter().enumerate().skip(1).step_by(2) {
+            assert_eq!(index.element().unwrap(), i as u64);
+            assert_eq!(index.position().position(), 2 * (i as u64 + 1));
+        }
+        assert_eq!(list.iter_backwards().map(|pos| pos.element().unwrap()).collect_vec(),
+                   (0..25_000).rev().map(|i| 2 * i + 1).collect_vec());
+    }
+
+    #[test]
+    fn ranges() {
+        let mut list: RangeSpacedList<i64, i64> = RangeSpacedList::new();
+        let mut indices = vec![];
+        for i in (0..500).rev() {
+            indices.push((i, list.insert(10 * i, 10 * i + 5, i).index()));
+        }
+        for (_, index) in indices.iter().step_by(3) {
+            list.remove(index.clone());
+        }
+        let contents = list.iter_ranges()
+            .map(|(start, end)| (start.position(), end.position(), start.element().unwrap()))
+            .collect_vec();
+        let compaction = list.compact();
+        assert!(compaction.reclaimed_subs > 0);
+        assert_eq!(Skeleton::count_subs_and_slots(list.skeleton.clone()), (0, contents.len()));
+        assert_eq!(list.iter_ranges()
+                       .map(|(start, end)| (start.position(), end.position(), start.element().unwrap()))
+                       .collect_vec(), contents);
+        for (i, index) in indices.into_iter().skip(1).filter(|(i, _)| (499 - i) % 3 != 0) {
+            assert_eq!(index.element().unwrap(), i);
+            let (start, end) = index.into_range();
+            assert_eq!((start.position().position(), end.position().position()), (10 * i, 10 * i + 5));
+        }
+    }
+
+    #[test]
+    fn nested_ranges() {
+        let mut list: NestedRangeSpacedList<i64, &str> = NestedRangeSpacedList::new();
+        let outer = list.insert(0, 20, "outer");
+        let inner = list.insert(5, 8, "inner");
+        let ghost = list.insert(10, 18, "ghost");
+        let innermost = list.insert(12, 15, "innermost");
+        let empty = list.insert(13, 13, "empty");
+        let after = list.insert(30, 40, "after");
+        list.remove(ghost.index());
+        let bounds = list.iter()
+            .map(|bound| (bound.position(), bound.bound_type(), bound.element().unwrap()))
+            .collect_vec();
+        let compaction = list.compact();
+        assert_eq!(compaction.reclaimed_slots, 1);
+        assert_eq!(list.iter()
+                       .map(|bound| (bound.position(), bound.bound_type(), bound.element().unwrap()))
+                       .collect_vec(), bounds);
+        // one sub each for the inner ranges of the outer range and of the innermost one
+        assert_eq!(Skeleton::count_subs_and_slots(list.skeleton.clone()), (2, 5));
+        for (range, element) in [(outer, "outer"), (inner, "inner"), (innermost, "innermost"),
+                                 (empty, "empty"), (after, "after")] {
+            assert_eq!(range.index().element().unwrap(), element);
+        }
+        assert!(!ghost.index().is_valid());
+        assert!(list.try_insert(14, 19, "intersecting").is_err());
+        list.insert(16, 19, "new");
+        assert_eq!(list.starting_after(15).unwrap().position(), 16);
+    }
+}
@@ -0,0 +1,390 @@
+//! Pushing elements one at a time updates the links of a skeleton for every single element. When
+//! all elements are known up front and sorted already, a skeleton can instead be built in one go:
+//! its links are computed from the spacings between its indices in a single pass (see
+//! [`Skeleton::rebuild_links`]), which takes linear time overall.
+//!
+//! Elements that don't fit into a single skeleton (see [`hyper`]) are split among skeletons of
+//! equal size, which are then put into hypers, just like pushing them would. For nested ranges,
+//! the inner ranges of a range are built into a skeleton of their own first, which then becomes
+//! the sub at the start index of the range.
+//!
+//! [`hyper`]: super::hyper
+
+use std::cell::RefCell;
+use std::iter::{repeat_n, repeat_with};
+use std::mem::size_of;
+use std::rc::Rc;
+
+use itertools::Itertools;
+use num_traits::zero;
+use thiserror::Error;
+
+use crate::{NestedRange, Node, ParentData, Range, Skeleton, Spacing};
+use crate::skeleton::hyper::HYPER_THRESHOLD;
+
+#[derive(Error, Debug)]
+pub enum FromSortedIterError {
+    #[error("Element {index} is positioned before the element before it.")]
+    Unsorted { index: usize },
+    #[error("Range {index} has a negative span.")]
+    NegativeSpan { index: usize },
+    #[error("Range {index} intersects a range before it.")]
+    RangeIntersectsExistingRange { index: usize },
+}
+
+/// An element to build a skeleton with, and the skeleton holding its inner ranges, if it is a
+/// nested range that has any.
+type Part<Kind, S, T> = (T, Option<Rc<RefCell<Skeleton<Kind, S, T>>>>);
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Builds a skeleton with the given elements, whose indices are at the given positions. Each
+    /// element has `links_per_element` indices, so there are that many positions per element.
+    fn build(positions: Vec<S>, elements: Vec<Part<Kind, S, T>>, links_per_element: usize) -> Rc<RefCell<Self>> {
+        if elements.is_empty() {
+            return Self::new(None);
+        }
+        // the number of links that makes a skeleton full, so it is built with fewer
+        let capacity = HYPER_THRESHOLD.div_ceil(size_of::<S>().max(1));
+        let elements_per_skeleton = (capacity - 1) / links_per_element;
+        let mut elements = elements.into_iter();
+        let mut skeletons = positions.chunks(elements_per_skeleton * links_per_element)
+            .map(|positions| {
+                let elements = elements.by_ref().take(positions.len() / links_per_element);
+                Self::build_single(positions, elements, links_per_element)
+            })
+            .collect_vec();
+        while skeletons.len() > 1 {
+            skeletons = skeletons.into_iter()
+                .chunks(capacity - 1)
+                .into_iter()
+                .map(|subs| Self::build_hyper(subs.collect()))
+                .collect();
+        }
+        skeletons.pop().unwrap()
+    }
+
+    /// Builds a single skeleton (no hyper) with the given elements, whose indices are at the given
+    /// positions.
+    fn build_single(positions: &[S], elements: impl Iterator<Item=Part<Kind, S, T>>, links_per_element: usize)
+                    -> Rc<RefCell<Self>> {
+        let this = Self::new(None);
+        {
+            let mut skeleton = this.borrow_mut();
+            skeleton.offset = positions[0];
+            skeleton.rebuild_links(positions.iter().tuple_windows().map(|(&a, &b)| b - a).collect());
+            let links = skeleton.links.len();
+            skeleton.subs.extend(repeat_n(None, links));
+        }
+        for (number, (element, inner)) in elements.enumerate() {
+            let index = number * links_per_element;
+            let mut skeleton = this.borrow_mut();
+            skeleton.elements.push(Some(element));
+            skeleton.new_generation(index, links_per_element);
+            if let Some(inner) = inner {
+                inner.borrow_mut().parent_data = Some(ParentData {
+                    parent: Rc::downgrade(&this),
+                    index_in_parent: index,
+                });
+                let count = inner.borrow().count();
+                skeleton.subs[index] = Some(inner);
+                skeleton.update_occupancy(index);
+                skeleton.increase_count(index, count);
+            }
+        }
+        this
+    }
+
+    /// Builds a hyper holding the given skeletons, which must not overlap, and are positioned by
+    /// their offset.
+    pub(super) fn build_hyper(subs: Vec<Rc<RefCell<Self>>>) -> Rc<RefCell<Self>> {
+        let this = Self::new(None);
+        {
+            let mut hyper = this.borrow_mut();
+            hyper.hyper = true;
+            hyper.offset = subs[0].borrow().offset;
+            // the last link spans exactly the last sub, whose offset will be zero
+            let mut spacings = subs.iter()
+                .tuple_windows()
+                .map(|(a, b)| b.borrow().offset - a.borrow().offset)
+                .collect_vec();
+            spacings.push(subs.last().unwrap().borrow().length);
+            hyper.rebuild_links(spacings);
+            hyper.elements.extend(repeat_with(|| None).take(subs.len() + 1));
+        }
+        for (index, sub) in subs.into_iter().enumerate() {
+            {
+                let mut sub = sub.borrow_mut();
+                sub.offset = zero();
+                sub.parent_data = Some(ParentData {
+                    parent: Rc::downgrade(&this),
+                    index_in_parent: index,
+                });
+            }
+            let mut hyper = this.borrow_mut();
+            let count = sub.borrow().count();
+            hyper.subs.push(Some(sub));
+            hyper.update_occupancy(index);
+            hyper.increase_count(index, count);
+        }
+        this
+    }
+}
+
+impl<S: Spacing, T> Skeleton<Node, S, T> {
+    /// Builds a skeleton from elements at the given positions, which must be sorted.
+    pub(crate) fn from_sorted(elements: impl IntoIterator<Item=(S, T)>)
+                              -> Result<Rc<RefCell<Self>>, FromSortedIterError> {
+        let mut positions: Vec<S> = vec![];
+        let mut parts = vec![];
+        for (index, (position, element)) in elements.into_iter().enumerate() {
+            if positions.last().is_some_and(|&last| position < last) {
+                return Err(FromSortedIterError::Unsorted { index });
+            }
+            positions.push(position);
+            parts.push((element, None));
+        }
+        Ok(Self::build(positions, parts, 1))
+    }
+}
+
+impl<S: Spacing, T> Skeleton<Range, S, T> {
+    /// Builds a skeleton from ranges with the given starts and spans, which must be sorted and
+    /// must not overlap.
+    pub(crate) fn from_sorted(ranges: impl IntoIterator<Item=(S, S, T)>)
+                              -> Result<Rc<RefCell<Self>>, FromSortedIterError> {
+        let mut positions: Vec<S> = vec![];
+        let mut parts = vec![];
+        for (index, (start, span, element)) in ranges.into_iter().enumerate() {
+            if span < zero() {
+                return Err(FromSortedIterError::NegativeSpan { index });
+            }
+            if let [.., previous_start, previous_end] = positions[..] {
+                if start < previous_start {
+                    return Err(FromSortedIterError::Unsorted { index });
+                }
+                if start < previous_end {
+                    return Err(FromSortedIterError::RangeIntersectsExistingRange { index });
+                }
+            }
+            positions.extend([start, start + span]);
+            parts.push((element, None));
+        }
+        Ok(Self::build(positions, parts, 2))
+    }
+}
+
+/// The ranges inside of a range (or at the top level, if there is none) while building a skeleton
+/// of nested ranges.
+struct Level<S: Spacing, T> {
+    /// The bounds of the range these ranges are inside of, if any.
+    outer: Option<(S, S)>,
+    positions: Vec<S>,
+    parts: Vec<Part<NestedRange, S, T>>,
+}
+
+impl<S: Spacing, T> Level<S, T> {
+    /// Builds the skeleton of these ranges, positioned relative to the start of their outer range.
+    fn build(self) -> Rc<RefCell<Skeleton<NestedRange, S, T>>> {
+        let start = self.outer.map_or(zero(), |(start, _)| start);
+        let positions = self.positions.into_iter().map(|position| position - start).collect();
+        Skeleton::build(positions, self.parts, 2)
+    }
+}
+
+impl<S: Spacing, T> Skeleton<NestedRange, S, T> {
+    /// Builds a skeleton from ranges with the given starts and spans, which must be sorted by
+    /// their start, with outer ranges before the ranges inside of them, and must not intersect
+    /// unless one contains the other.
+    pub(crate) fn from_sorted(ranges: impl IntoIterator<Item=(S, S, T)>)
+                              -> Result<Rc<RefCell<Self>>, FromSortedIterError> {
+        let mut levels = vec![Level { outer: None, positions: vec![], parts: vec![] }];
+        let mut previous_start = None;
+        for (index, (start, span, element)) in ranges.into_iter().enumerate() {
+            if span < zero() {
+                return Err(FromSortedIterError::NegativeSpan { index });
+            }
+            if previous_start.is_some_and(|previous_start| start < previous_start) {
+                return Err(FromSortedIterError::Unsorted { index });
+            }
+            previous_start = Some(start);
+            let end = start + span;
+            // the ranges that end at or before the start of this one are complete
+            while levels.last().unwrap().outer.is_some_and(|(_, outer_end)| start >= outer_end) {
+                let inner = levels.pop().unwrap().build();
+                levels.last_mut().unwrap().parts.last_mut().unwrap().1 = Some(inner);
+            }
+            let level = levels.last().unwrap();
+            if let [.., previous_start, previous_end] = level.positions[..] {
+                if start < previous_end {
+                    levels.push(Level { outer: Some((previous_start, previous_end)), positions: vec![], parts: vec![] });
+                }
+            }
+            let level = levels.last_mut().unwrap();
+            if level.outer.is_some_and(|(_, outer_end)| end > outer_end) {
+                return Err(FromSortedIterError::RangeIntersectsExistingRange { index });
+            }
+            level.positions.extend([start, end]);
+            level.parts.push((element, None));
+        }
+        while levels.len() > 1 {
+            let inner = levels.pop().unwrap().build();
+            levels.last_mut().unwrap().parts.last_mut().unwrap().1 = Some(inner);
+        }
+        Ok(levels.pop().unwrap().build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{BoundType, FromSortedIterError, HollowRangeSpacedList, HollowSpacedList, NestedRangeSpacedList,
+                RangeSpacedList, SpacedList};
+
+    #[test]
+    fn nodes() {
+        let positions = (0..50_000u64).map(|i| 3 + 2 * i - i % 2).collect_vec();
+        let mut list = SpacedList::from_sorted_iter(positions.iter().map(|&position| (position, position))).unwrap();
+        assert!(list.skeleton.borrow().hyper);
+        assert_eq!(list.size(), positions.len());
+        assert_eq!(list.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec(),
+                   positions.iter().map(|&position| (position, position)).collect_vec());
+        for &position in positions.iter().step_by(997) {
+            assert_eq!(list.at(position).unwrap().element().unwrap(), position);
+            assert_eq!(list.before(position).map(|pos| pos.position()), positions.iter().rev().find(|&&p| p < position).copied());
+        }
+        let index = list.at(positions[12_345]).unwrap().index();
+        list.insert(10, 10);
+        list.push(1, 0);
+        assert_eq!(index.element().unwrap(), positions[12_345]);
+        assert_eq!(list.remove(index), positions[12_345]);
+        assert_eq!(list.size(), positions.len() + 1);
+
+        let hollow = HollowSpacedList::from_spacings([2, 0, 3, 1]).unwrap();
+        assert_eq!(hollow.iter().map(|pos| pos.position()).collect_vec(), [2, 2, 5, 6]);
+        assert!(HollowSpacedList::<i32>::from_sorted_iter([]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn ranges() {
+        let ranges = (0..20_000i64).map(|i| (10 * i, 10 * i + i % 7, i)).collect_vec();
+        let list = RangeSpacedList::from_sorted_iter(ranges.iter().copied()).unwrap();
+        assert!(list.skeleton.borrow().hyper);
+        let mut inserted = RangeSpacedList::new();
+        for &(start, end, element) in ranges.iter().rev() {
+            inserted.insert(start, end, element);
+        }
+        assert_eq!(list.iter_ranges().map(|(start, end)| (start.position(), end.position(), start.element().unwrap()))
+                       .collect_vec(),
+                   ranges);
+        for position in (0..200_000).step_by(1_231) {
+            assert_eq!(list.starting_at_or_before(position).map(|pos| pos.element().unwrap()),
+                       inserted.starting_at_or_before(position).map(|pos| pos.element().unwrap()));
+            assert_eq!(list.ending_after(position).map(|pos| pos.element().unwrap()),
+                       inserted.ending_after(position).map(|pos| pos.element().unwrap()));
+        }
+
+        let hollow = HollowRangeSpacedList::from_spacings([(1, 2), (0, 0), (3, 1)]).unwrap();
+        assert_eq!(hollow.iter().map(|pos| pos.position()).collect_vec(), [1, 3, 3, 3, 6, 7]);
+        let hollow = HollowRangeSpacedList::from_sorted_iter_with_span([(1, 2), (3, 0), (6, 1)]).unwrap();
+        assert_eq!(hollow.iter().map(|pos| pos.position()).collect_vec(), [1, 3, 3, 3, 6, 7]);
+    }
+
+    #[test]
+    fn nested_ranges() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut inserted = NestedRangeSpacedList::new();
+            for element in 0..300 {
+                let start = rng.gen_range(0..1_000);
+                let _ = inserted.try_insert(start, start + rng.gen_range(0..100), element);
+            }
+            // the ranges in the order of their starts, which puts outer ranges first
+            let mut ranges = vec![];
+            let mut open = vec![];
+            for bound in inserted.iter() {
+                match bound.bound_type() {
+                    BoundType::Start => {
+                        open.push(ranges.len());
+                        ranges.push((bound.position(), bound.position(), bound.element().unwrap()));
+                    }
+                    BoundType::End => ranges[open.pop().unwrap()].1 = bound.position(),
+                }
+            }
+            let mut list = NestedRangeSpacedList::from_sorted_iter(ranges.iter().copied()).unwrap();
+            assert_eq!(list.size(), ranges.len());
+            // the built list works like any other
+            for element in 300..400 {
+                let start = rng.gen_range(0..1_000);
+                let end = start + rng.gen_range(0..100);
+                assert_eq!(list.try_insert(start, end, element).is_ok(), inserted.try_insert(start, end, element).is_ok());
+            }
+            assert_eq!(list.iter().map(|pos| (pos.position(), pos.element().unwrap(), pos.bound_type())).collect_vec(),
+                       inserted.iter().map(|pos| (pos.position(), pos.element().unwrap(), pos.bound_type())).collect_vec());
+            for position in (0..1_100).step_by(7) {
+                assert_eq!(list.starting_at_or_after(position).map(|pos| pos.element().unwrap()),
+                           inserted.starting_at_or_after(position).map(|pos| pos.element().unwrap()));
+                assert_eq!(list.ending_before(position).map(|pos| pos.element().unwrap()),
+                           inserted.ending_before(position).map(|pos| pos.element().unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(SpacedList::from_sorted_iter([(0, 'a'), (2, 'b'), (1, 'c')]),
+                         Err(FromSortedIterError::Unsorted { index: 2 })));
+        assert!(matches!(HollowSpacedList::from_spacings([1, 1, -1]),
+                         Err(FromSortedIterError::Unsorted { index: 2 })));
+        assert!(matches!(RangeSpacedList::from_sorted_iter([(0, 2, 'a'), (3, 2, 'b')]),
+                         Err(FromSortedIterError::NegativeSpan { index: 1 })));
+        assert!(matches!(RangeSpacedList::from_sorted_iter([(0, 2, 'a'), (1, 3, 'b')]),
+                         Err(FromSortedIterError::RangeIntersectsExistingRange { index: 1 })));
+        assert!(matches!(HollowRangeSpacedList::from_sorted_iter([(5, 6), (0, 1)]),
+                         Err(FromSortedIterError::Unsorted { index: 1 })));
+        assert!(matches!(NestedRangeSpacedList::from_sorted_iter([(0, 10, 'a'), (2, 5, 'b'), (3, 11, 'c')]),
+                         Err(FromSortedIterError::RangeIntersectsExistingRange { index: 2 })));
+        assert!(matches!(NestedRangeSpacedList::from_sorted_iter([(0, 10, 'a'), (2, 5, 'b'), (1, 3, 'c')]),
+                         Err(FromSortedIterError::Unsorted { index: 2 })));
+        // ranges that start where another one ends come after it, not inside of it
+        assert!(NestedRangeSpacedList::from_sorted_iter([(0, 10, 'a'), (10, 20, 'b')]).is_ok());
+    }
+
+    #[test]
+    fn collect_and_extend() {
+        let mut list: SpacedList<i32, char> = [(0, 'a'), (5, 'b')].into_iter().collect();
+        list.extend([(3, 'c'), (5, 'd'), (8, 'e')]);
+        assert_eq!(list.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec(),
+                   [(0, 'a'), (3, 'c'), (5, 'b'), (5, 'd'), (8, 'e')]);
+        assert_eq!(list.size(), 5);
+
+        let mut list: NestedRangeSpacedList<i32, char> = [(0, 10, 'a'), (2, 4, 'b')].into_iter().collect();
+        list.extend([(5, 6, 'c'), (10, 12, 'd')]);
+        assert_eq!(list.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec(),
+                   [(0, 'a'), (2, 'b'), (4, 'b'), (5, 'c'), (6, 'c'), (10, 'a'), (10, 'd'), (12, 'd')]);
+
+        let mut list: HollowRangeSpacedList<i32> = HollowRangeSpacedList::new();
+        list.extend([(0, 1), (4, 6), (2, 3)]);
+        assert_eq!(list.iter().map(|pos| pos.position()).collect_vec(), [0, 1, 2, 3, 4, 6]);
+    }
+
+    #[test]
+    fn extend_by_sorted_runs() {
+        let mut list: SpacedList<u32, u32> = [(0, 0), (5, 1)].into_iter().collect();
+        // two sorted runs after the end of the list, with an element before it in between
+        let elements = (10..1_000).chain([3]).chain(2_000..3_000).map(|position| (position, position)).collect_vec();
+        list.extend(elements.iter().copied());
+        // the runs are built on their own and appended as a whole, instead of pushed one by one
+        assert!(list.skeleton.borrow().hyper);
+        let expected = [(0, 0), (5, 1)].into_iter().chain(elements).sorted_by_key(|&(position, _)| position).collect_vec();
+        assert_eq!(list.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec(), expected);
+        assert_eq!(list.size(), expected.len());
+
+        let mut list: RangeSpacedList<u32, u32> = RangeSpacedList::new();
+        list.extend([(0, 2, 0), (2, 4, 1), (6, 8, 2), (4, 5, 3), (10, 12, 4)]);
+        assert_eq!(list.iter_ranges().map(|(start, end)| (start.position(), end.position())).collect_vec(),
+                   [(0, 2), (2, 4), (4, 5), (6, 8), (10, 12)]);
+    }
+}
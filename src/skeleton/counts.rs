@@ -0,0 +1,383 @@
+//! To find the position of an element among all elements (its rank), or the element at a given
+//! position among them (the nth element), without counting them one at a time, every skeleton
+//! keeps track of how many elements each of its indices stands for: one if an element is placed
+//! there (for ranges, only counting the start index), plus the number of elements in its sub, if
+//! it has one. These counts are stored in a Fenwick tree laid out like the links of a skeleton,
+//! so both the number of elements before an index and the index holding the nth element of a
+//! skeleton can be found in logarithmic time.
+//!
+//! Like occupancy (see [`occupancy`]), the counts change whenever an element gets a new generation
+//! or its generation ends, and every change is passed on to the indices of the ancestors of the
+//! skeleton that lead to it. Subs that are moved to another index or skeleton as a whole take
+//! their count along with them.
+//!
+//! [`occupancy`]: super::occupancy
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use num_traits::zero;
+
+use crate::{EphemeralPosition, ParentData, Skeleton, Spacing};
+use crate::skeleton::index::NO_GENERATION;
+
+#[derive(Default)]
+pub(crate) struct Counts {
+    /// Entry i holds the sum of the counts of the indices from `i & (i + 1)` up to i, like the
+    /// links of a skeleton hold the sum of the spacings between those indices.
+    tree: Vec<usize>,
+    total: usize,
+}
+
+impl Counts {
+    pub(crate) fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns the sum of the counts of the indices before the given one.
+    pub(crate) fn before(&self, index: usize) -> usize {
+        let mut index = index.min(self.tree.len());
+        let mut count = 0;
+        while index > 0 {
+            count += self.tree[index - 1];
+            index &= index - 1;
+        }
+        count
+    }
+
+    pub(crate) fn get(&self, index: usize) -> usize {
+        self.before(index + 1) - self.before(index)
+    }
+
+    pub(crate) fn increase(&mut self, index: usize, by: usize) {
+        self.grow(index + 1);
+        self.total += by;
+        let mut index = index;
+        while index < self.tree.len() {
+            self.tree[index] += by;
+            index |= index + 1;
+        }
+    }
+
+    pub(crate) fn decrease(&mut self, index: usize, by: usize) {
+        self.total -= by;
+        let mut index = index;
+        while index < self.tree.len() {
+            self.tree[index] -= by;
+            index |= index + 1;
+        }
+    }
+
+    /// Adds indices with a count of zero until there are at least `len` of them.
+    fn grow(&mut self, len: usize) {
+        while self.tree.len() < len {
+            let index = self.tree.len();
+            let mut count = 0;
+            for degree in 0..index.trailing_ones() {
+                count += self.tree[index - (1 << degree)];
+            }
+            self.tree.push(count);
+        }
+    }
+
+    /// Replaces all counts by the given ones.
+    pub(crate) fn rebuild(&mut self, counts: impl IntoIterator<Item=usize>) {
+        self.tree.clear();
+        self.total = 0;
+        for (index, count) in counts.into_iter().enumerate() {
+            let mut entry = count;
+            for degree in 0..index.trailing_ones() {
+                entry += self.tree[index - (1 << degree)];
+            }
+            self.tree.push(entry);
+            self.total += count;
+        }
+    }
+
+    /// Returns the index whose count includes the element with the given number (counting from
+    /// zero), which must be less than the total, and the sum of the counts before that index.
+    pub(crate) fn find(&self, number: usize) -> (usize, usize) {
+        let mut index = 0;
+        let mut before = 0;
+        let mut step = self.tree.len().checked_ilog2().map_or(0, |log| 1 << log);
+        while step > 0 {
+            if index + step <= self.tree.len() && before + self.tree[index + step - 1] <= number {
+                index += step;
+                before += self.tree[index - 1];
+            }
+            step >>= 1;
+        }
+        (index, before)
+    }
+
+    /// Drops all indices from the given one onwards, which must have a count of zero.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.tree.truncate(len);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.tree.clear();
+        self.total = 0;
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.tree.shrink_to_fit();
+    }
+}
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Returns the number of elements in this skeleton and its subs.
+    pub(crate) fn count(&self) -> usize {
+        self.counts.total()
+    }
+
+    /// Increases the count of the given index, and of the indices in the ancestors of this
+    /// skeleton that lead to it.
+    pub(super) fn increase_count(&mut self, index: usize, by: usize) {
+        self.change_count(index, by, Counts::increase);
+    }
+
+    /// Decreases the count of the given index, and of the indices in the ancestors of this
+    /// skeleton that lead to it.
+    pub(super) fn decrease_count(&mut self, index: usize, by: usize) {
+        self.change_count(index, by, Counts::decrease);
+    }
+
+    fn change_count(&mut self, index: usize, by: usize, change: fn(&mut Counts, usize, usize)) {
+        if by == 0 {
+            return;
+        }
+        change(&mut self.counts, index, by);
+        let mut parent_data = self.parent_data.as_ref()
+            .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent));
+        while let Some((parent, index)) = parent_data {
+            let mut parent = parent.borrow_mut();
+            change(&mut parent.counts, index, by);
+            parent_data = parent.parent_data.as_ref()
+                .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent));
+        }
+    }
+
+    /// Recomputes the counts of all indices of this skeleton, after its elements and subs have
+    /// moved to other indices. Each element has `links_per_element` indices, and only counts at
+    /// the first one.
+    pub(super) fn rebuild_counts(&mut self, links_per_element: usize) {
+        let counts = (0..=self.links.len())
+            .map(|index| {
+                let element = index % links_per_element == 0 && self.generation(index) != NO_GENERATION;
+                element as usize + self.sub(index).map_or(0, |sub| sub.borrow().count())
+            })
+            .collect::<Vec<_>>();
+        self.counts.rebuild(counts);
+    }
+
+    /// Returns the number of elements in this skeleton and its subs that are positioned before
+    /// the given position, which is relative to the parent of this skeleton, like its offset.
+    /// Ranges are positioned at their start.
+    pub(crate) fn count_before(this: Rc<RefCell<Self>>, position: S) -> usize {
+        let mut count = 0;
+        let mut skeleton = this;
+        let mut position = position;
+        loop {
+            let Some(before) = Self::shallow_before(skeleton.clone(), position) else {
+                return count;
+            };
+            let sub = {
+                let skeleton = skeleton.borrow();
+                count += skeleton.counts.before(before.index + 1);
+                skeleton.sub(before.index)
+            };
+            // the elements of the sub are counted, but only those before the position should be
+            let Some(sub) = sub else {
+                return count;
+            };
+            count -= sub.borrow().count();
+            position -= before.position;
+            skeleton = sub;
+        }
+    }
+
+    /// Returns the element with the given number (counting from zero) among the elements in this
+    /// skeleton and its subs, in the order in which they are iterated over. For ranges, this is
+    /// the position of their start.
+    pub(crate) fn nth(this: Rc<RefCell<Self>>, number: usize) -> Option<EphemeralPosition<Kind, S, T>> {
+        if number >= this.borrow().count() {
+            return None;
+        }
+        let mut skeleton = this;
+        let mut number = number;
+        let mut position = zero();
+        loop {
+            let (index, sub) = {
+                let skeleton = skeleton.borrow();
+                let (index, before) = skeleton.counts.find(number);
+                number -= before;
+                position += skeleton.shallow_position_of(index);
+                (index, skeleton.sub(index))
+            };
+            let sub_count = sub.as_ref().map_or(0, |sub| sub.borrow().count());
+            // the element at an index comes before the elements in its sub
+            let element = skeleton.borrow().counts.get(index) - sub_count;
+            if number < element {
+                return Some(EphemeralPosition::new(skeleton, index, position));
+            }
+            number -= element;
+            // cannot fail, as the count of the index is more than the number
+            skeleton = sub.unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{BoundType, HollowNestedRangeSpacedList, NestedRangeSpacedList, RangeSpacedList, SpacedList};
+
+    use super::Counts;
+
+    #[test]
+    fn before_and_find() {
+        let mut rng = StdRng::seed_from_u64(14);
+        let mut counts = Counts::default();
+        let mut expected = vec![0; 1_000];
+        for _ in 0..3_000 {
+            let index = rng.gen_range(0..expected.len());
+            if expected[index] > 0 && rng.gen_bool(0.4) {
+                counts.decrease(index, 1);
+                expected[index] -= 1;
+            } else {
+                counts.increase(index, 2);
+                expected[index] += 2;
+            }
+        }
+        assert_eq!(counts.total(), expected.iter().sum::<usize>());
+        for index in 0..=expected.len() {
+            assert_eq!(counts.before(index), expected[..index].iter().sum::<usize>());
+        }
+        let mut number = 0;
+        for (index, &count) in expected.iter().enumerate() {
+            for _ in 0..count {
+                assert_eq!(counts.find(number), (index, expected[..index].iter().sum()));
+                number += 1;
+            }
+        }
+        let mut rebuilt = Counts::default();
+        rebuilt.rebuild(expected.iter().copied());
+        assert_eq!(rebuilt.tree, counts.tree);
+    }
+
+    /// Asserts that ranking and selecting in the given list agrees with iterating over it, where
+    /// `$positions` are the positions that elements are ranked by, in iteration order.
+    macro_rules! assert_ranks {
+        ($list:expr, $positions:expr) => {
+            let positions: Vec<i64> = $positions;
+            assert_eq!($list.size(), positions.len());
+            for (number, &position) in positions.iter().enumerate() {
+                assert_eq!($list.nth(number).unwrap().position(), position);
+            }
+            assert!($list.nth(positions.len()).is_none());
+            let end = positions.last().map_or(0, |&last| last + 2);
+            for position in -1..=end {
+                let expected = positions.iter().filter(|&&other| other < position).count();
+                assert_eq!($list.rank(position), expected, "rank {position}");
+                assert_eq!($list.count_between(position - 3, position),
+                           positions.iter().filter(|&&other| position - 3 <= other && other < position).count());
+            }
+        };
+    }
+
+    #[test]
+    fn nodes() {
+        for seed in 0..6 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: SpacedList<i64, u32> = SpacedList::new();
+            let mut indices = vec![];
+            for element in 0..2_000 {
+                indices.push(list.insert(rng.gen_range(0..1_000), element).index());
+            }
+            assert_ranks!(list, list.iter().map(|pos| pos.position()).collect_vec());
+            for index in indices.into_iter().filter(|_| rng.gen_bool(0.5)) {
+                list.remove(index);
+            }
+            assert_ranks!(list, list.iter().map(|pos| pos.position()).collect_vec());
+            for _ in 0..100 {
+                list.push(rng.gen_range(0..3), 0);
+            }
+            if seed % 2 == 0 {
+                list.compact();
+            }
+            assert_ranks!(list, list.iter().map(|pos| pos.position()).collect_vec());
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        for seed in 0..6 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: RangeSpacedList<i64, u32> = RangeSpacedList::new();
+            let mut indices = vec![];
+            for element in 0..2_000 {
+                let start = rng.gen_range(0..10_000);
+                if let Ok(position) = list.try_insert(start, start + rng.gen_range(0..4), element) {
+                    indices.push(position.index());
+                }
+            }
+            assert_ranks!(list, list.iter_ranges().map(|(start, _)| start.position()).collect_vec());
+            for index in indices.into_iter().filter(|_| rng.gen_bool(0.5)) {
+                list.remove(index);
+            }
+            if seed % 2 == 0 {
+                list.compact();
+            }
+            assert_ranks!(list, list.iter_ranges().map(|(start, _)| start.position()).collect_vec());
+        }
+    }
+
+    #[test]
+    fn nested_ranges() {
+        for seed in 0..6 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: NestedRangeSpacedList<i64, u32> = NestedRangeSpacedList::new();
+            let mut indices = vec![];
+            for element in 0..2_000 {
+                let start = rng.gen_range(0..3_000);
+                if let Ok(position) = list.try_insert(start, start + rng.gen_range(0..50), element) {
+                    indices.push(position.index());
+                }
+            }
+            let starts = |list: &NestedRangeSpacedList<i64, u32>| list.iter()
+                .filter(|pos| pos.bound_type() == BoundType::Start)
+                .map(|pos| pos.position())
+                .collect_vec();
+            assert_ranks!(list, starts(&list));
+            for index in indices.into_iter().filter(|_| rng.gen_bool(0.5)) {
+                list.remove(index);
+            }
+            if seed % 2 == 0 {
+                list.compact();
+            }
+            assert_ranks!(list, starts(&list));
+        }
+    }
+
+    #[test]
+    fn failed_insertions() {
+        let mut list: HollowNestedRangeSpacedList<i64> = HollowNestedRangeSpacedList::new();
+        list.insert(0, 10);
+        list.insert(20, 30);
+        assert!(list.try_insert(5, 25).is_err());
+        assert!(list.try_push(-5, 1).is_err());
+        assert_eq!(list.size(), 2);
+        let inner = list.insert(2, 4);
+        assert_eq!(list.rank(3), 2);
+        assert_eq!(list.nth(1).unwrap().position(), 2);
+        list.remove(inner.index());
+        assert_eq!(list.size(), 2);
+        assert_eq!(list.nth(1).unwrap().position(), 20);
+        assert_eq!(list.count_between(0, 20), 1);
+        assert_eq!(list.count_between(20, 0), 0);
+    }
+}
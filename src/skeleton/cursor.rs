@@ -0,0 +1,327 @@
+//! A cursor points at an element of a spaced list (or at no element, past the ends of the list,
+//! like the cursors of [`std::collections::LinkedList`]) and keeps where that element is in the
+//! skeleton, so that moving to the next or previous element only takes a step from there (see
+//! [`EphemeralPosition::into_next_element`]) instead of a traversal from the root.
+//!
+//! [`CursorMut`] can also change the list next to its element. Insertions start at the skeleton of
+//! the element, or the closest of its ancestors that the new element fits into (see
+//! [`insertion`]), and spacing changes only update the links on the path from the element to the
+//! root. As insertions and removals can move elements between skeletons, the
+//! cursor finds its element again through its persistent index afterwards, which doesn't need a
+//! traversal either.
+//!
+//! [`insertion`]: super::insertion
+
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::rc::Rc;
+
+use num_traits::zero;
+
+use crate::{display_unwrap, ElementRef, ElementRefMut, EphemeralPosition, Node, Position, RelativeInsertionError, Skeleton,
+            Spacing, SpacingError};
+use crate::skeleton::shortcuts::Shortcuts;
+
+/// Returns the first element of the given skeleton, if it has any.
+fn first<S: Spacing, T>(root: &Rc<RefCell<Skeleton<Node, S, T>>>) -> Option<EphemeralPosition<Node, S, T>> {
+    root.borrow().is_occupied().then(|| EphemeralPosition::at_start(root.clone()))?.into_element_at_or_after()
+}
+
+/// Returns the last element of the given skeleton, if it has any.
+fn last<S: Spacing, T>(root: &Rc<RefCell<Skeleton<Node, S, T>>>) -> Option<EphemeralPosition<Node, S, T>> {
+    root.borrow().is_occupied().then(|| EphemeralPosition::at_end(root.clone()))?.into_element_at_or_before()
+}
+
+macro_rules! cursor_accessors {
+    () => {
+        /// Returns the position of the current element, or `None` if the cursor is past the ends of
+        /// the list.
+        #[must_use]
+        pub fn position(&self) -> Option<S> {
+            self.current.as_ref().map(|current| current.position)
+        }
+
+        /// Returns a persistent position of the current element, which stays valid after the
+        /// cursor has moved on.
+        #[must_use]
+        pub fn current(&self) -> Option<Position<Node, S, T>> {
+            self.current.as_ref().map(EphemeralPosition::persistent)
+        }
+
+        #[must_use]
+        pub fn element(&self) -> Option<ElementRef<Node, S, T>> {
+            self.current.as_ref().map(|current| ElementRef::new_(current.skeleton.clone(), current.index))
+        }
+
+        /// Moves to the next element. If the cursor is at the last element, it moves past the
+        /// ends of the list, and from there, to the first element.
+        pub fn move_next(&mut self) {
+            self.current = match self.current.take() {
+                Some(current) => current.into_next_element(),
+                None => first(&self.root),
+            };
+        }
+
+        /// Moves to the previous element. If the cursor is at the first element, it moves past the
+        /// ends of the list, and from there, to the last element.
+        pub fn move_prev(&mut self) {
+            self.current = match self.current.take() {
+                Some(current) => current.into_previous_element(),
+                None => last(&self.root),
+            };
+        }
+    };
+}
+
+/// A cursor over a [`SpacedList`](crate::SpacedList), created with
+/// [`SpacedList::cursor_at`](crate::SpacedList::cursor_at).
+pub struct Cursor<'a, S: Spacing, T> {
+    root: &'a Rc<RefCell<Skeleton<Node, S, T>>>,
+    current: Option<EphemeralPosition<Node, S, T>>,
+}
+
+impl<'a, S: Spacing, T> Cursor<'a, S, T> {
+    pub(crate) fn new(root: &'a Rc<RefCell<Skeleton<Node, S, T>>>, current: Option<EphemeralPosition<Node, S, T>>) -> Self {
+        Self { root, current }
+    }
+
+    cursor_accessors!();
+}
+
+/// A cursor over a [`SpacedList`](crate::SpacedList) that can insert and remove elements and
+/// change spacings next to its element, created with
+/// [`SpacedList::cursor_mut`](crate::SpacedList::cursor_mut).
+pub struct CursorMut<'a, S: Spacing, T> {
+    root: &'a mut Rc<RefCell<Skeleton<Node, S, T>>>,
+    shortcuts: &'a mut Shortcuts<Node, S, T>,
+    current: Option<EphemeralPosition<Node, S, T>>,
+}
+
+impl<'a, S: Spacing, T> CursorMut<'a, S, T> {
+    pub(crate) fn new(root: &'a mut Rc<RefCell<Skeleton<Node, S, T>>>, shortcuts: &'a mut Shortcuts<Node, S, T>,
+                      current: Option<EphemeralPosition<Node, S, T>>) -> Self {
+        Self { root, shortcuts, current }
+    }
+
+    cursor_accessors!();
+
+    #[must_use]
+    pub fn element_mut(&self) -> Option<ElementRefMut<Node, S, T>> {
+        self.current.as_ref().map(|current| ElementRefMut::new_(current.skeleton.clone(), current.index))
+    }
+
+    /// Returns a read-only cursor at the current element, which borrows this one.
+    #[must_use]
+    pub fn as_cursor(&self) -> Cursor<'_, S, T> {
+        Cursor::new(self.root, self.current.clone())
+    }
+
+    fn current_or_panic(&self) -> &EphemeralPosition<Node, S, T> {
+        self.current.as_ref().expect("The cursor is not at an element.")
+    }
+
+    /// Inserts an element the given spacing before the current element, and returns its position.
+    /// The cursor stays at the current element. Panics if the cursor is past the ends of the list,
+    /// or if the element can't be inserted (see [`Self::try_insert_before`]).
+    pub fn insert_before(&mut self, spacing: S, value: T) -> Position<Node, S, T> {
+        display_unwrap!(self.try_insert_before(spacing, value))
+    }
+
+    /// Inserts an element the given spacing after the current element, and returns its position.
+    /// The cursor stays at the current element. Panics if the cursor is past the ends of the list,
+    /// or if the element can't be inserted (see [`Self::try_insert_after`]).
+    pub fn insert_after(&mut self, spacing: S, value: T) -> Position<Node, S, T> {
+        display_unwrap!(self.try_insert_after(spacing, value))
+    }
+
+    /// Inserts an element the given spacing before the current element, which must be at or after
+    /// the previous element (or at or after zero if there is none), like
+    /// [`SpacedList::try_insert_before`](crate::SpacedList::try_insert_before). Panics if the
+    /// cursor is past the ends of the list.
+    pub fn try_insert_before(&mut self, spacing: S, value: T)
+                             -> Result<Position<Node, S, T>, RelativeInsertionError<Infallible>> {
+        let position = Skeleton::relative_insertion_position(self.current_or_panic(), spacing, zero(), false)?;
+        Ok(self.insert(position, value))
+    }
+
+    /// Inserts an element the given spacing after the current element, which must be at or before
+    /// the next element, like [`SpacedList::try_insert_after`](crate::SpacedList::try_insert_after).
+    /// Panics if the cursor is past the ends of the list.
+    pub fn try_insert_after(&mut self, spacing: S, value: T)
+                            -> Result<Position<Node, S, T>, RelativeInsertionError<Infallible>> {
+        let position = Skeleton::relative_insertion_position(self.current_or_panic(), spacing, zero(), true)?;
+        Ok(self.insert(position, value))
+    }
+
+    fn insert(&mut self, position: S, value: T) -> Position<Node, S, T> {
+        let current = self.current_or_panic();
+        let (current_index, current_position) = (current.index().persistent(), current.position);
+        let (skeleton, origin) = Skeleton::relative_insertion_start(current, position, position);
+        self.shortcuts.clear();
+        let skeleton = if Rc::ptr_eq(&skeleton, self.root) {
+            *self.root = Skeleton::make_room(self.root.clone());
+            self.root.clone()
+        } else {
+            skeleton
+        };
+        let mut inserted = Skeleton::<Node, _, _>::insert(skeleton, position - origin, value);
+        inserted.position.position += origin;
+        let (root, inserted) = Skeleton::<Node, _, _>::balance(self.root.clone(), inserted);
+        *self.root = root;
+        // the insertion may have displaced the current element into a sub, or compacted it
+        let current = current_index.resolve().unwrap();
+        self.current = Some(EphemeralPosition::new(current.skeleton, current.index, current_position));
+        inserted.into()
+    }
+
+    /// Removes the current element and moves to the next one (or past the ends of the list, if it
+    /// was the last one). Returns the removed element, or `None` if the cursor is past the ends of
+    /// the list.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let current = self.current.take()?;
+        let next = current.clone().into_next_element()
+            .map(|next| (next.index().persistent(), next.position));
+        self.shortcuts.clear();
+        let element = Skeleton::<Node, _, _>::remove(current.skeleton.clone(), current.into_index());
+        // removing an element can drop skeletons that became vacant, but the next element stays
+        self.current = next.map(|(index, position)| {
+            let next = index.resolve().unwrap();
+            EphemeralPosition::new(next.skeleton, next.index, position)
+        });
+        element
+    }
+
+    /// Increases the spacing between the current element and the elements after it, moving all of
+    /// them back by the given change. Panics if the cursor is past the ends of the list, or if the
+    /// spacing can't be changed (see [`Self::try_increase_spacing_after_current`]).
+    pub fn increase_spacing_after_current(&mut self, change: S) {
+        display_unwrap!(self.try_increase_spacing_after_current(change))
+    }
+
+    /// Increases the spacing between the current element and the elements after it, moving all of
+    /// them back by the given change. Only the links on the path from the current element to the
+    /// root are changed. Fails if the current element is the last one, as there is no spacing
+    /// after it. Panics if the cursor is past the ends of the list.
+    pub fn try_increase_spacing_after_current(&mut self, change: S) -> Result<(), SpacingError<S>> {
+        let current = self.current_or_panic();
+        Skeleton::try_change_spacing_after_element(current, true, change)?;
+        self.shortcuts.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{RelativeInsertionError, SpacedList, SpacingError};
+
+    #[test]
+    fn editing_while_walking() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: SpacedList<i64, u32> = SpacedList::new();
+            let mut expected = vec![];
+            // distinct positions, as the order of elements at the same position isn't known
+            for (element, position) in (0..300).map(|_| rng.gen_range(0..3_000)).unique().enumerate() {
+                list.insert(position, element as u32);
+                expected.push((position, element as u32));
+            }
+            expected.sort();
+            let is_free = |expected: &[(i64, u32)], position|
+                expected.binary_search_by_key(&position, |&(existing, _)| existing).is_err();
+            let mut next_element = 300;
+            let mut cursor = list.cursor_mut(rng.gen_range(0..3_000));
+            let mut current = expected.iter().position(|&(position, _)| position >= cursor.position().unwrap_or(i64::MAX));
+            for _ in 0..2_000 {
+                assert_eq!(cursor.position(), current.map(|current| expected[current].0));
+                assert_eq!(cursor.element().map(|element| element.unwrap()), current.map(|current| expected[current].1));
+                let spacing = rng.gen_range(1..20);
+                match (rng.gen_range(0..6), current) {
+                    (0, _) => {
+                        cursor.move_next();
+                        current = match current {
+                            Some(current) => Some(current + 1).filter(|&next| next < expected.len()),
+                            None => (!expected.is_empty()).then_some(0),
+                        };
+                    }
+                    (1, _) => {
+                        cursor.move_prev();
+                        current = match current {
+                            Some(current) => current.checked_sub(1),
+                            None => expected.len().checked_sub(1),
+                        };
+                    }
+                    (2, Some(0)) if expected[0].0 < spacing => {
+                        assert!(matches!(cursor.try_insert_before(spacing, next_element),
+                                         Err(RelativeInsertionError::DoesNotFit)));
+                    }
+                    (2, Some(index)) if index > 0 && expected[index - 1].0 > expected[index].0 - spacing => {
+                        assert!(matches!(cursor.try_insert_before(spacing, next_element),
+                                         Err(RelativeInsertionError::DoesNotFit)));
+                    }
+                    (2, Some(index)) if is_free(&expected, expected[index].0 - spacing) => {
+                        let position = expected[index].0 - spacing;
+                        assert_eq!(cursor.insert_before(spacing, next_element).position(), position);
+                        let at = expected.partition_point(|&(existing, _)| existing < position);
+                        expected.insert(at, (position, next_element));
+                        current = Some(index + 1);
+                        next_element += 1;
+                    }
+                    (3, Some(index)) if expected.get(index + 1).is_some_and(|&(next, _)| next < expected[index].0 + spacing) => {
+                        assert!(matches!(cursor.try_insert_after(spacing, next_element),
+                                         Err(RelativeInsertionError::DoesNotFit)));
+                    }
+                    (3, Some(index)) if is_free(&expected, expected[index].0 + spacing) => {
+                        let position = expected[index].0 + spacing;
+                        assert_eq!(cursor.insert_after(spacing, next_element).position(), position);
+                        let at = expected.partition_point(|&(existing, _)| existing < position);
+                        expected.insert(at, (position, next_element));
+                        next_element += 1;
+                    }
+                    (4, Some(index)) => {
+                        assert_eq!(cursor.remove_current(), Some(expected.remove(index).1));
+                        current = Some(index).filter(|&index| index < expected.len());
+                    }
+                    (5, Some(index)) if index + 1 == expected.len() => {
+                        assert!(matches!(cursor.try_increase_spacing_after_current(spacing),
+                                         Err(SpacingError::PositionAtOrAfterList { .. })));
+                    }
+                    (5, Some(index)) => {
+                        let change = rng.gen_range(0..10);
+                        cursor.increase_spacing_after_current(change);
+                        for (position, _) in &mut expected[index + 1..] {
+                            *position += change;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            drop(cursor);
+            assert_eq!(list.size(), expected.len());
+            assert_eq!(list.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec(),
+                       expected);
+            let cursor = list.cursor_at(expected.last().map_or(0, |&(position, _)| position));
+            assert_eq!(cursor.element().map(|element| element.unwrap()), expected.last().map(|&(_, element)| element));
+        }
+    }
+
+    #[test]
+    fn checked_editing() {
+        let mut list: SpacedList<u32, char> = SpacedList::new();
+        list.insert(2, 'a');
+        list.insert(5, 'b');
+        let mut cursor = list.cursor_mut(0);
+        assert!(matches!(cursor.try_insert_before(3, 'c'), Err(RelativeInsertionError::DoesNotFit)));
+        assert!(matches!(cursor.try_insert_after(4, 'c'), Err(RelativeInsertionError::DoesNotFit)));
+        assert_eq!(cursor.insert_before(2, 'c').position(), 0);
+        cursor.move_next();
+        assert!(matches!(cursor.try_insert_before(4, 'd'), Err(RelativeInsertionError::DoesNotFit)));
+        assert!(matches!(cursor.try_increase_spacing_after_current(1), Err(SpacingError::PositionAtOrAfterList { position: 5 })));
+        drop(cursor);
+        assert_eq!(list.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec(),
+                   [(0, 'c'), (2, 'a'), (5, 'b')]);
+    }
+}
@@ -0,0 +1,276 @@
+//! Deleting a region removes every element inside of it and closes the gap it leaves, so that
+//! everything after the region moves forwards by its length. The elements are removed like single
+//! elements are, leaving their indices behind, and the gap is closed by moving each of the
+//! remaining bounds inside the region to its start, one after the other (see
+//! [`Skeleton::try_change_spacing_before_element_unbounded`]), and then everything after the region. This
+//! only changes the links on the paths from those bounds to the root.
+//!
+//! Ranges that only partly lie inside the region lose the part that does when the gap is closed,
+//! unless a [`StraddlePolicy`] says otherwise.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use num_traits::zero;
+use thiserror::Error;
+
+use crate::{BoundType, EphemeralPosition, Index, NestedRange, Node, Range, RangeKind, Skeleton, Spacing};
+
+/// What to do with ranges that start before a deleted region and end after its start, or start
+/// inside of it and end after its end.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StraddlePolicy {
+    /// Cuts the part inside the region out of the range.
+    #[default]
+    Clip,
+    /// Removes the range along with the ranges inside the region.
+    Remove,
+    /// Fails without deleting anything.
+    Error,
+}
+
+#[derive(Error, Debug)]
+pub enum DeletionError<S: Spacing> {
+    #[error("Cannot delete a region that ends at {end}, before its start at {start}.")]
+    RegionEndsBeforeStart { start: S, end: S },
+    #[error("Cannot delete a region that the range from {start} to {end} only partly lies in.")]
+    RangeStraddlesRegion { start: S, end: S },
+}
+
+/// A range to remove, by the index and position of its start and the position of its end.
+type Removal<Kind, S, T> = (Index<Kind, S, T>, S, S);
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    fn check_region(start: S, end: S) -> Result<(), DeletionError<S>> {
+        if end < start {
+            return Err(DeletionError::RegionEndsBeforeStart { start, end });
+        }
+        Ok(())
+    }
+
+    /// Returns the first element (or bound) at or after the given position.
+    fn element_at_or_after(root: &Rc<RefCell<Self>>, position: S) -> Option<EphemeralPosition<Kind, S, T>> {
+        Self::at_or_after(root.clone(), position)?.into_element_at_or_after()
+    }
+
+    /// Removes the given elements, and returns them along with their positions.
+    fn remove_all(removals: Vec<Removal<Kind, S, T>>, links_per_element: usize) -> Vec<(S, S, T)> {
+        removals.into_iter()
+            .map(|(index, start, end)| {
+                let index = index.resolve().unwrap();
+                let element = Self::remove_element(index.skeleton, index.index / links_per_element, links_per_element);
+                (start, end, element.unwrap())
+            })
+            .collect()
+    }
+
+    /// Moves the elements (or bounds) after the start of the region and up to its end to its
+    /// start, and all elements after the region forwards by its length. Nothing but empty element
+    /// slots may be between them and the start of the region.
+    fn close_region(root: &Rc<RefCell<Self>>, start: S, end: S) {
+        let length = end - start;
+        // how far the elements after the current one have already moved
+        let mut moved = zero();
+        let mut element = Self::at_or_after(root.clone(), start)
+            .and_then(|position| position.into_element_at_or_after())
+            .and_then(|element| if element.position > start { Some(element) } else { element.into_next_element() });
+        while let Some(mut current) = element {
+            let original = current.position + moved;
+            let target = if original <= end { start } else { original - length };
+            let change = current.position - target;
+            // the previous element is at or before the start of the region by now
+            Self::try_change_spacing_before_element_unbounded(root, &current, false, change).unwrap();
+            if original > end {
+                return;
+            }
+            moved += change;
+            current.position = target;
+            element = current.into_next_element();
+        }
+    }
+
+    /// Deletes the ranges in the given region, after `around`, the starts of the ranges that
+    /// contain the whole region, have been found.
+    fn delete_ranges(root: &Rc<RefCell<Self>>, start: S, end: S, policy: StraddlePolicy,
+                     around: Vec<EphemeralPosition<Kind, S, T>>) -> Result<Vec<(S, S, T)>, DeletionError<S>>
+        where Kind: RangeKind {
+        if start == end {
+            return Ok(vec![]);
+        }
+        let mut inside = vec![];
+        let mut straddling = around.into_iter().map(EphemeralPosition::into_range).collect::<Vec<_>>();
+        let mut bound = Self::element_at_or_after(root, start);
+        while let Some(current) = bound.filter(|bound| bound.position <= end) {
+            bound = current.clone().into_next_element();
+            let (range_start, range_end) = current.clone().into_range();
+            match current.bound_type() {
+                BoundType::Start if current.position < end => {
+                    if range_end.position <= end {
+                        inside.push((range_start, range_end));
+                    } else {
+                        straddling.push((range_start, range_end));
+                    }
+                }
+                // ranges that start inside the region have been dealt with at their start
+                BoundType::End if range_start.position < start && current.position > start => {
+                    straddling.push((range_start, range_end));
+                }
+                _ => {}
+            }
+        }
+        if let Some((range_start, range_end)) = straddling.first() {
+            match policy {
+                StraddlePolicy::Clip => {}
+                StraddlePolicy::Remove => inside.append(&mut straddling),
+                StraddlePolicy::Error => return Err(DeletionError::RangeStraddlesRegion {
+                    start: range_start.position,
+                    end: range_end.position,
+                }),
+            }
+        }
+        inside.sort_by_key(|(range_start, _)| range_start.position);
+        let removals = inside.into_iter()
+            .map(|(range_start, range_end)| (range_start.index().persistent(), range_start.position, range_end.position))
+            .collect();
+        let removed = Self::remove_all(removals, 2);
+        Self::close_region(root, start, end);
+        Ok(removed)
+    }
+}
+
+impl<S: Spacing, T> Skeleton<Node, S, T> {
+    /// Removes all elements at or after `start` and before `end`, and moves all elements after
+    /// them forwards by `end - start`. Returns the removed elements and their positions.
+    pub(crate) fn delete_region(root: &Rc<RefCell<Self>>, start: S, end: S) -> Result<Vec<(S, T)>, DeletionError<S>> {
+        Self::check_region(start, end)?;
+        if start == end {
+            return Ok(vec![]);
+        }
+        let mut inside = vec![];
+        let mut element = Self::element_at_or_after(root, start);
+        while let Some(current) = element.filter(|element| element.position < end) {
+            inside.push((current.index().persistent(), current.position, current.position));
+            element = current.into_next_element();
+        }
+        let removed = Self::remove_all(inside, 1);
+        Self::close_region(root, start, end);
+        Ok(removed.into_iter().map(|(position, _, element)| (position, element)).collect())
+    }
+}
+
+impl<S: Spacing, T> Skeleton<Range, S, T> {
+    /// Removes all ranges between `start` and `end`, and moves all ranges after them forwards by
+    /// `end - start`. Ranges that start before `start` and end after it, or start before `end` and
+    /// end after it, are dealt with according to the given policy. Returns the removed ranges and
+    /// their positions.
+    pub(crate) fn delete_region(root: &Rc<RefCell<Self>>, start: S, end: S, policy: StraddlePolicy)
+                                -> Result<Vec<(S, S, T)>, DeletionError<S>> {
+        Self::check_region(start, end)?;
+        // as ranges don't overlap, only the last range starting before the region can contain it
+        let around = Self::conditional_starting_before(root.clone(), start, |slot| slot.is_some())
+            .filter(|range_start| range_start.clone().into_range().1.position > end);
+        Self::delete_ranges(root, start, end, policy, around.into_iter().collect())
+    }
+}
+
+impl<S: Spacing, T> Skeleton<NestedRange, S, T> {
+    /// Removes all ranges between `start` and `end`, like the function of the same name for
+    /// [`Range`] skeletons does.
+    pub(crate) fn delete_region(root: &Rc<RefCell<Self>>, start: S, end: S, policy: StraddlePolicy)
+                                -> Result<Vec<(S, S, T)>, DeletionError<S>> {
+        Self::check_region(start, end)?;
+        // the ranges that contain the whole region contain the last range starting before it
+        let mut around = vec![];
+        let mut range = Self::conditional_starting_before(root.clone(), start, |slot| slot.is_some());
+        while let Some(range_start) = range {
+            if range_start.holds_element() && range_start.clone().into_range().1.position > end {
+                around.push(range_start.clone());
+            }
+            range = Self::parent_range(range_start);
+        }
+        around.reverse();
+        Self::delete_ranges(root, start, end, policy, around)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{NestedRangeSpacedList, RangeOrder, SpacedList, StraddlePolicy};
+
+    #[test]
+    fn delete_nodes() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: SpacedList<u64, u32> = SpacedList::new();
+            let mut expected = vec![];
+            for element in 0..2_000 {
+                let position = rng.gen_range(0..4_000);
+                list.insert(position, element);
+                expected.push((position, element));
+            }
+            // elements at the same position are in the order of the list
+            expected.sort_by_key(|&(position, _)| position);
+            let order = list.iter().map(|position| position.element().unwrap()).collect_vec();
+            expected.sort_by_key(|(_, element)| order.iter().position(|other| other == element));
+            for _ in 0..100 {
+                let start = rng.gen_range(0..list.end().max(1));
+                let end = start + rng.gen_range(0..50);
+                let removed = list.delete_region(start, end);
+                let (inside, outside): (Vec<_>, Vec<_>) = expected.iter()
+                    .partition(|&&(position, _)| start <= position && position < end);
+                assert_eq!(removed, inside);
+                expected = outside.into_iter()
+                    .map(|(position, element)| (if position >= end { position - (end - start) } else { position }, element))
+                    .collect();
+                assert_eq!(list.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec(),
+                           expected);
+                assert_eq!(list.size(), expected.len());
+            }
+        }
+    }
+
+    #[test]
+    fn delete_nested_ranges() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: NestedRangeSpacedList<i64, u32> = NestedRangeSpacedList::new();
+            for element in 0..1_000 {
+                let start = rng.gen_range(0..10_000);
+                let _ = list.try_insert(start, start + rng.gen_range(0..100), element);
+            }
+            let ranges = |list: &NestedRangeSpacedList<i64, u32>| list.iter_ranges(RangeOrder::PreOrder)
+                .map(|(start, end, _)| (start.position(), end.position(), start.element().unwrap()))
+                .sorted_by_key(|&(_, _, element)| element)
+                .collect_vec();
+            let mut expected = ranges(&list);
+            for _ in 0..100 {
+                let start = rng.gen_range(0..list.end().max(1));
+                let end = start + rng.gen_range(0..200);
+                let policy = [StraddlePolicy::Clip, StraddlePolicy::Remove, StraddlePolicy::Error][rng.gen_range(0..3)];
+                let straddles = |&(range_start, range_end, _): &(i64, i64, u32)|
+                    range_start < start && range_end > start || range_start < end && range_end > end;
+                let inside = |&(range_start, range_end, _): &(i64, i64, u32)| start <= range_start && range_end <= end
+                    && range_start < end;
+                let result = list.try_delete_region(start, end, policy);
+                if policy == StraddlePolicy::Error && start < end && expected.iter().any(straddles) {
+                    assert!(result.is_err());
+                    continue;
+                }
+                let removed = result.unwrap();
+                let is_removed = |range: &(i64, i64, u32)| inside(range) || policy == StraddlePolicy::Remove && straddles(range);
+                assert_eq!(removed.iter().map(|&(_, _, element)| element).sorted().collect_vec(),
+                           expected.iter().filter(|range| is_removed(range)).map(|&(_, _, element)| element).collect_vec());
+                let moved = |position: i64| if position <= start { position } else { start.max(position - (end - start)) };
+                expected = expected.into_iter()
+                    .filter(|range| !is_removed(range))
+                    .map(|(range_start, range_end, element)| (moved(range_start), moved(range_end), element))
+                    .collect();
+                assert_eq!(ranges(&list), expected);
+            }
+        }
+    }
+}
@@ -4,6 +4,7 @@ use maybe_owned::MaybeOwned;
 
 use crate::{ParentData, BoundType, EphemeralPosition, Index, Node, RangeKind, Skeleton, Spacing};
 use crate::skeleton::ElementSlot;
+use crate::skeleton::index::WeakIndex;
 
 pub(crate) struct EphemeralIndex<Kind, S: Spacing, T> {
     pub(crate) skeleton: Rc<RefCell<Skeleton<Kind, S, T>>>,
@@ -36,7 +37,7 @@ impl<Kind, S: Spacing, T> EphemeralIndex<Kind, S, T> {
     }
 
     pub(crate) fn at_end(skeleton: Rc<RefCell<Skeleton<Kind, S, T>>>) -> Self {
-        let index = skeleton.borrow().elements.len() - 1;
+        let index = skeleton.borrow().links.len();
         Self {
             skeleton,
             index,
@@ -48,8 +49,10 @@ impl<Kind, S: Spacing, T> EphemeralIndex<Kind, S, T> {
     }
 
     pub(crate) fn persistent(&self) -> Index<Kind, S, T> {
-        self.skeleton.borrow().into_persistent.get(&self.index).cloned()
-            .unwrap_or(Index::new(self.skeleton.clone(), self.index as isize))
+        let skeleton = self.skeleton.borrow();
+        // if the element was moved here, persistent indices refer to where it was originally placed
+        skeleton.origins.get(&self.index).and_then(WeakIndex::upgrade)
+            .unwrap_or_else(|| Index::new(self.skeleton.clone(), self.index, skeleton.generation(self.index)))
     }
 
     pub(crate) fn into_next(self) -> Option<Self> {
@@ -107,24 +110,24 @@ impl<Kind, S: Spacing, T> EphemeralIndex<Kind, S, T> {
 }
 
 impl <S: Spacing, T> EphemeralIndex<Node, S, T> {
-    pub(crate) fn element(&self) -> Ref<ElementSlot<T>> {
+    pub(crate) fn element(&self) -> Ref<'_, ElementSlot<T>> {
         Ref::map(RefCell::borrow(&self.skeleton),
                  |skeleton| &skeleton.elements[self.index])
     }
 
-    pub(crate) fn element_mut(&self) -> RefMut<ElementSlot<T>> {
+    pub(crate) fn element_mut(&self) -> RefMut<'_, ElementSlot<T>> {
         RefMut::map(RefCell::borrow_mut(&self.skeleton),
                     |skeleton| &mut skeleton.elements[self.index])
     }
 }
 
 impl<Kind: RangeKind, S: Spacing, T> EphemeralIndex<Kind, S, T> {
-    pub(crate) fn element(&self) -> Ref<ElementSlot<T>> {
+    pub(crate) fn element(&self) -> Ref<'_, ElementSlot<T>> {
         Ref::map(RefCell::borrow(&self.skeleton),
                  |skeleton| &skeleton.elements[self.index / 2])
     }
 
-    pub(crate) fn element_mut(&self) -> RefMut<ElementSlot<T>> {
+    pub(crate) fn element_mut(&self) -> RefMut<'_, ElementSlot<T>> {
         RefMut::map(RefCell::borrow_mut(&self.skeleton),
                     |skeleton| &mut skeleton.elements[self.index / 2])
     }
@@ -154,7 +157,7 @@ impl<Kind: RangeKind, S: Spacing, T> EphemeralIndex<Kind, S, T> {
         }
     }
 
-    pub(crate) fn range(&self) -> (MaybeOwned<Self>, MaybeOwned<Self>) {
+    pub(crate) fn range(&self) -> (MaybeOwned<'_, Self>, MaybeOwned<'_, Self>) {
         match self.bound_type() {
             BoundType::Start => {
                 let end = Self::new(
@@ -41,7 +41,7 @@ impl<Kind, S: Spacing, T> EphemeralPosition<Kind, S, T> {
     }
 
     pub(crate) fn at_end(skeleton: Rc<RefCell<Skeleton<Kind, S, T>>>) -> Self {
-        let index = skeleton.borrow().elements.len() - 1;
+        let index = skeleton.borrow().links.len();
         let position = skeleton.borrow().last_position();
         Self {
             skeleton,
@@ -59,11 +59,8 @@ impl<Kind, S: Spacing, T> EphemeralPosition<Kind, S, T> {
     }
 
     pub(crate) fn persistent(&self) -> Position<Kind, S, T> {
-        self.skeleton.borrow().into_persistent.get(&self.index).cloned()
-            .map_or(
-                Position::new(self.skeleton.clone(), self.index as isize, self.position),
-                |index| Position::new(index.skeleton, index.index, self.position),
-            )
+        let index = self.index().persistent();
+        Position::new(index.skeleton, index.index, index.generation, self.position)
     }
 
     pub(crate) fn into_next(self) -> Option<Self> {
@@ -137,24 +134,24 @@ impl<Kind, S: Spacing, T> EphemeralPosition<Kind, S, T> {
 }
 
 impl<S: Spacing, T> EphemeralPosition<Node, S, T> {
-    pub(crate) fn element(&self) -> Ref<ElementSlot<T>> {
+    pub(crate) fn element(&self) -> Ref<'_, ElementSlot<T>> {
         Ref::map(RefCell::borrow(&self.skeleton),
                  |skeleton| &skeleton.elements[self.index])
     }
 
-    pub(crate) fn element_mut(&self) -> RefMut<ElementSlot<T>> {
+    pub(crate) fn element_mut(&self) -> RefMut<'_, ElementSlot<T>> {
         RefMut::map(RefCell::borrow_mut(&self.skeleton),
                     |skeleton| &mut skeleton.elements[self.index])
     }
 }
 
 impl<Kind: RangeKind, S: Spacing, T> EphemeralPosition<Kind, S, T> {
-    pub(crate) fn element(&self) -> Ref<ElementSlot<T>> {
+    pub(crate) fn element(&self) -> Ref<'_, ElementSlot<T>> {
         Ref::map(RefCell::borrow(&self.skeleton),
                  |skeleton| &skeleton.elements[self.index / 2])
     }
 
-    pub(crate) fn element_mut(&self) -> RefMut<ElementSlot<T>> {
+    pub(crate) fn element_mut(&self) -> RefMut<'_, ElementSlot<T>> {
         RefMut::map(RefCell::borrow_mut(&self.skeleton),
                     |skeleton| &mut skeleton.elements[self.index / 2])
     }
@@ -186,7 +183,7 @@ impl<Kind: RangeKind, S: Spacing, T> EphemeralPosition<Kind, S, T> {
         }
     }
 
-    pub(crate) fn range(&self) -> (MaybeOwned<Self>, MaybeOwned<Self>) {
+    pub(crate) fn range(&self) -> (MaybeOwned<'_, Self>, MaybeOwned<'_, Self>) {
         match self.bound_type() {
             BoundType::Start => {
                 let end = Self::new(
@@ -0,0 +1,221 @@
+//! All links, elements and subs of a skeleton are stored in a single Vec each. This causes two
+//! problems for big lists: when a power of two in size is crossed, allocating the larger Vec can
+//! cause long freezes, and traversal starts with extremely large jumps through the links, which is
+//! bad news for the cache.
+//!
+//! To avoid this, a skeleton whose links have reached [`HYPER_THRESHOLD`] bytes does not grow any
+//! further when pushing onto it. Instead, it is replaced by a new skeleton, called a "hyper", in
+//! whatever position it was (root skeleton of a spaced list, or sub in a parent skeleton). The
+//! full skeleton is added to the hyper as the sub at index zero, and the element is pushed onto a
+//! new sub at index one. When a skeleton that is the last sub of a hyper overflows, another sub is
+//! added to the hyper; no need to create a new hyper. Full hypers are put into hypers themselves.
+//!
+//! Hypers have no elements (all of their element slots are empty), and a sub at every index except
+//! for the last one. All subs of a hyper have an offset of zero, so the position of index i in a
+//! hyper is the position of the first element of sub i. The last index of a hyper marks its end,
+//! which means that the last link always spans exactly the last sub.
+//!
+//! The existence of hypers is invisible to everyone using this crate, similarly to the existence
+//! of subs. As their element slots are empty, traversal and iteration never yield an index in a
+//! hyper itself, only one in a sub.
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::mem;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use num_traits::zero;
+
+use crate::{ElementSlot, ParentData, Skeleton, Spacing};
+
+/// The size of the links of a skeleton, in bytes, at which pushing onto it makes it overflow into
+/// a hyper.
+pub(crate) const HYPER_THRESHOLD: usize = 1 << 16;
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    pub(crate) fn is_full(&self) -> bool {
+        self.links.len() * size_of::<S>().max(1) >= HYPER_THRESHOLD
+    }
+
+    /// Returns whether an element can be pushed onto this skeleton without it overflowing.
+    pub(crate) fn has_room(&self) -> bool {
+        !self.is_full() || self.hyper && self.last_sub().borrow().has_room()
+    }
+
+    pub(super) fn last_sub(&self) -> Rc<RefCell<Self>> {
+        // hypers have a sub at every index except for the last one, and subs has one entry less
+        // than there are indices
+        self.subs.last().cloned().flatten().unwrap()
+    }
+
+    /// Root skeletons can't be replaced by a hyper when they overflow, as they are owned by a
+    /// spaced list instead of a parent skeleton. Spaced lists therefore call this function before
+    /// every insertion and store the returned skeleton as their new root.
+    pub(crate) fn make_room(this: Rc<RefCell<Self>>) -> Rc<RefCell<Self>> {
+        if this.borrow().has_room() {
+            this
+        } else {
+            Self::into_hyper(this)
+        }
+    }
+
+    /// Replaces this skeleton with a new hyper that contains it as its first sub, and returns the
+    /// hyper.
+    pub(crate) fn into_hyper(this: Rc<RefCell<Self>>) -> Rc<RefCell<Self>> {
+        let parent_data = this.borrow_mut().parent_data.take();
+        let hyper = Self::new(None);
+        {
+            let mut hyper_ref = hyper.borrow_mut();
+            let mut skeleton = this.borrow_mut();
+            hyper_ref.hyper = true;
+            hyper_ref.offset = mem::replace(&mut skeleton.offset, zero());
+            hyper_ref.push_link();
+            hyper_ref.increase_spacing(0, skeleton.length);
+            hyper_ref.elements.push(ElementSlot::None);
+            hyper_ref.elements.push(ElementSlot::None);
+            hyper_ref.subs[0] = Some(this.clone());
+            hyper_ref.counts.increase(0, skeleton.count());
+            skeleton.parent_data = Some(ParentData {
+                parent: Rc::downgrade(&hyper),
+                index_in_parent: 0,
+            });
+        }
+        if let Some(ParentData { parent, index_in_parent }) = &parent_data {
+            parent.upgrade().unwrap().borrow_mut().subs[*index_in_parent] = Some(hyper.clone());
+        }
+        hyper.borrow_mut().parent_data = parent_data;
+        hyper.borrow_mut().update_occupancy(0);
+        hyper
+    }
+
+    /// Prepares this hyper for a push at the given distance from its end. Returns the sub that the
+    /// element should be pushed onto, the position of that sub, and the distance from the end of
+    /// the sub at which the element should be pushed.
+    ///
+    /// After pushing onto the sub, [`Skeleton::sync_last_link`] needs to be called.
+    pub(crate) fn hyper_push_target(this: Rc<RefCell<Self>>, distance: S) -> (Rc<RefCell<Self>>, S, S) {
+        let last_sub = this.borrow().last_sub();
+        let mut hyper = this.borrow_mut();
+        let last_index = hyper.links.len() - 1;
+        if last_sub.borrow().has_room() {
+            let sub_position = hyper.last_position() - hyper.link(last_index);
+            (last_sub, sub_position, distance)
+        } else {
+            hyper.increase_spacing(last_index, distance);
+            let index = hyper.push_link();
+            hyper.elements.push(ElementSlot::None);
+            let sub = Self::new(Some(ParentData {
+                parent: Rc::downgrade(&this),
+                index_in_parent: index,
+            }));
+            hyper.subs[index] = Some(sub.clone());
+            (sub, hyper.last_position(), zero())
+        }
+    }
+
+    /// Restores the invariant that the last link of this hyper spans exactly its last sub.
+    pub(crate) fn sync_last_link(&mut self) {
+        let last_index = self.links.len() - 1;
+        let sub_end = self.last_sub().borrow().last_position();
+        let link = self.link(last_index);
+        match sub_end.cmp(&link) {
+            Ordering::Greater => self.increase_spacing(last_index, sub_end - link),
+            Ordering::Less => self.decrease_spacing(last_index, link - sub_end),
+            Ordering::Equal => {}
+        }
+    }
+
+    /// Moves the start of this hyper to the given position, which must lie before its current
+    /// start. The elements of the first sub stay where they are, so that an element can then be
+    /// inserted at the front of the first sub.
+    pub(crate) fn move_hyper_start(this: Rc<RefCell<Self>>, position: S) {
+        let mut hyper = this.borrow_mut();
+        let shift = hyper.offset - position;
+        hyper.offset = position;
+        hyper.increase_spacing(0, shift);
+        hyper.sub(0).unwrap().borrow_mut().offset += shift;
+    }
+
+    /// Returns the sub of this hyper that an element at the given position needs to be inserted
+    /// into, the position of that sub, and the position of the next sub (or of the end of this
+    /// hyper, if there is no next sub). The position must not be before the start of this hyper.
+    pub(crate) fn hyper_sub_at_or_before(this: Rc<RefCell<Self>>, position: S) -> (Rc<RefCell<Self>>, S, S) {
+        let result = Self::shallow_at_or_before(this.clone(), position).unwrap();
+        let hyper = this.borrow();
+        (hyper.sub(result.index).unwrap(), result.position, result.position + hyper.link(result.index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::{RangeSpacedList, SpacedList};
+
+    #[test]
+    fn push() {
+        let mut list: SpacedList<u64, u64> = SpacedList::new();
+        let first = list.push(3, 0);
+        for i in 1..50_000 {
+            list.push(2, i);
+        }
+        assert!(first.skeleton.borrow().parent_data.as_ref().unwrap().parent.upgrade().unwrap().borrow().hyper);
+        assert_eq!(list.iter().map(|pos| pos.position()).collect_vec(), (0..50_000).map(|i| 3 + 2 * i).collect_vec());
+        assert_eq!(list.iter_backwards().map(|pos| pos.element().unwrap()).collect_vec(), (0..50_000).rev().collect_vec());
+        assert_eq!(first.element().unwrap(), 0);
+        assert_eq!(list.first().unwrap().position(), 3);
+        assert_eq!(list.last().unwrap().position(), 3 + 2 * 49_999);
+        assert_eq!(list.length(), 2 * 49_999);
+        for i in [0, 8191, 8192, 8193, 16_385, 49_999] {
+            let position = 3 + 2 * i;
+            assert_eq!(list.at(position).unwrap().element().unwrap(), i);
+            assert_eq!(list.at_or_before(position + 1).unwrap().element().unwrap(), i);
+            assert_eq!(list.at_or_after(position - 1).unwrap().element().unwrap(), i);
+            if i > 0 {
+                assert_eq!(list.before(position).unwrap().element().unwrap(), i - 1);
+            }
+            if i < 49_999 {
+                assert_eq!(list.after(position).unwrap().element().unwrap(), i + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn insert_and_change_spacing() {
+        let mut list: SpacedList<u64, u64> = SpacedList::new();
+        for i in 0..30_000 {
+            list.push(10, 10 * (i + 1));
+        }
+        list.insert(5, 5);
+        list.insert(81_925, 81_925);
+        list.insert(1, 1);
+        list.increase_spacing_after(81_920, 3);
+        list.decrease_spacing_after(81_920, 2);
+        assert!(list.try_decrease_spacing_after(81_920, 20).is_err());
+        let expected = [1, 5].into_iter()
+            .chain((1..=30_000).map(|i| 10 * i))
+            .chain([81_925])
+            .sorted()
+            .map(|element| (if element > 81_920 { element + 1 } else { element }, element))
+            .collect_vec();
+        assert_eq!(list.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec(), expected);
+    }
+
+    #[test]
+    fn ranges() {
+        let mut list: RangeSpacedList<u64, u64> = RangeSpacedList::new();
+        let first = list.push(10, 2, 0);
+        for i in 1..20_000 {
+            list.push(3, 2, i);
+        }
+        assert!(first.skeleton.borrow().parent_data.as_ref().unwrap().parent.upgrade().unwrap().borrow().hyper);
+        assert_eq!(list.iter_ranges().map(|(start, end)| (start.position(), end.position())).collect_vec(),
+                   (0..20_000).map(|i| (10 + 5 * i, 12 + 5 * i)).collect_vec());
+        // the gap between two ranges at the boundary of two subs
+        assert!(list.try_insert(10 + 5 * 4095 + 2, 10 + 5 * 4096, 20_000).is_ok());
+        assert!(list.try_insert(10 + 5 * 4096 - 1, 10 + 5 * 4096 + 1, 20_001).is_err());
+        assert_eq!(list.starting_at(10 + 5 * 4096).unwrap().element().unwrap(), 4096);
+        assert_eq!(list.ending_at_or_before(10 + 5 * 4096).unwrap().element().unwrap(), 20_000);
+    }
+}
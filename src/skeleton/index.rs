@@ -1,15 +1,143 @@
+//! Persistent indices keep referring to the same element while other elements are inserted and
+//! removed around it. An index consists of the skeleton and index that the element was originally
+//! placed at, and the generation of the element, that is, the number of elements that had been
+//! placed into that skeleton before it. Generations are never reused, so an index whose element has
+//! been removed can't accidentally refer to a newer element placed at the same index.
+//!
+//! Elements move when an element is inserted before the first element of a skeleton (the new
+//! element takes its place, and the previous first element is reinserted further back), when an
+//! element is spliced in before them (see [`splicing`]), and when a skeleton is compacted. The
+//! skeleton that a moved element was originally placed in remembers where it went, and the element
+//! remembers where it originally was. When a moved element is removed, both entries are kept, so
+//! that its indices can still tell whether its slot has been reused, until its slot gets a new
+//! element or is dropped. So there is at most one such pair of entries per element slot.
+//!
+//! [`splicing`]: super::splicing
+
 use std::cell::RefCell;
-use std::rc::Rc;
+use std::ptr;
+use std::rc::{Rc, Weak};
 
 use maybe_owned::MaybeOwned;
+use thiserror::Error;
 
-use crate::{BoundType, ElementRef, ElementRefMut, EphemeralIndex, HollowPosition, Position, 
+use crate::{BoundType, ElementRef, ElementRefMut, EphemeralIndex, HollowPosition, Position,
             RangeKind, Skeleton, Spacing};
 
-// TODO rework persistent indices to store not an isize, but a usize with a "generation" counter,
-//  basically the "n" in "This index refers to the nth element ever placed at index i."
-//  then, rework persistent index handling in before-offset insertion of elements and removal of
-//  elements (-> take care that first_persistent_index is always correct!)
+#[derive(Error, Debug)]
+pub enum StaleIndexError {
+    #[error("The element this index refers to has been removed.")]
+    ElementRemoved,
+    #[error("The element this index refers to has been removed, and a newer element has taken its place.")]
+    SlotReused,
+}
+
+/// The generation of indices that don't hold an element.
+pub(crate) const NO_GENERATION: usize = usize::MAX;
+
+/// Like an [`Index`], but doesn't keep its skeleton alive.
+pub(crate) struct WeakIndex<Kind, S: Spacing, T> {
+    pub(crate) skeleton: Weak<RefCell<Skeleton<Kind, S, T>>>,
+    pub(crate) index: usize,
+    pub(crate) generation: usize,
+}
+
+impl<Kind, S: Spacing, T> Clone for WeakIndex<Kind, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            skeleton: self.skeleton.clone(),
+            index: self.index,
+            generation: self.generation,
+        }
+    }
+}
+
+impl<Kind, S: Spacing, T> WeakIndex<Kind, S, T> {
+    pub(crate) fn upgrade(&self) -> Option<Index<Kind, S, T>> {
+        Some(Index::new(self.skeleton.upgrade()?, self.index, self.generation))
+    }
+}
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    pub(crate) fn generation(&self, index: usize) -> usize {
+        self.generations.get(index).copied().unwrap_or(NO_GENERATION)
+    }
+
+    /// Gives the element that was just placed at the given index a new generation.
+    pub(super) fn new_generation(&mut self, first_index: usize, links_per_element: usize) {
+        let generation = self.next_generation;
+        self.next_generation += 1;
+        let end = first_index + links_per_element;
+        if self.generations.len() < end {
+            self.generations.resize(end, NO_GENERATION);
+        }
+        // an element that takes the place of another one (which is then placed somewhere else)
+        // doesn't change the number of elements
+        if self.generations[first_index] == NO_GENERATION {
+            self.increase_count(first_index, 1);
+        }
+        for index in first_index..end {
+            self.generations[index] = generation;
+            self.forget_origin(index);
+            self.update_occupancy(index);
+        }
+    }
+
+    /// Marks the element at the given index as removed, so that no index refers to it anymore.
+    pub(super) fn end_generation(this: &Rc<RefCell<Self>>, first_index: usize, links_per_element: usize) {
+        let mut skeleton = this.borrow_mut();
+        skeleton.decrease_count(first_index, 1);
+        for index in first_index..first_index + links_per_element {
+            // if the element was moved here, its origin is kept until the slot is reused or dropped
+            skeleton.generations[index] = NO_GENERATION;
+            skeleton.update_occupancy(index);
+        }
+    }
+
+    /// Drops the record of where the element at the given index was originally placed, along with
+    /// the entry in that skeleton that refers to the index.
+    pub(super) fn forget_origin(&mut self, index: usize) {
+        let Some(origin) = self.origins.remove(&index) else {
+            return;
+        };
+        let Some(skeleton) = origin.skeleton.upgrade() else {
+            return;
+        };
+        let key = (origin.index, origin.generation);
+        let this: *const Self = self;
+        // the entry may already refer to where the element has been moved since
+        let refers_here = |moved: &WeakIndex<Kind, S, T>| moved.index == index
+            && moved.skeleton.upgrade().is_some_and(|skeleton| ptr::eq(skeleton.as_ptr(), this));
+        // the origin may be this skeleton itself, which is already borrowed
+        if ptr::eq(skeleton.as_ptr(), this) {
+            if self.moved.get(&key).is_some_and(refers_here) {
+                self.moved.remove(&key);
+            }
+        } else {
+            let mut skeleton = skeleton.borrow_mut();
+            if skeleton.moved.get(&key).is_some_and(refers_here) {
+                skeleton.moved.remove(&key);
+            }
+        }
+    }
+
+    /// Records that the element that `from` refers to has been moved to `to`.
+    pub(super) fn record_move(from: Index<Kind, S, T>, to: EphemeralIndex<Kind, S, T>, links_per_element: usize) {
+        for bound in 0..links_per_element {
+            let generation = to.skeleton.borrow().generation(to.index + bound);
+            from.skeleton.borrow_mut().moved.insert((from.index + bound, from.generation), WeakIndex {
+                skeleton: Rc::downgrade(&to.skeleton),
+                index: to.index + bound,
+                generation,
+            });
+            to.skeleton.borrow_mut().origins.insert(to.index + bound, WeakIndex {
+                skeleton: Rc::downgrade(&from.skeleton),
+                index: from.index + bound,
+                generation: from.generation,
+            });
+        }
+    }
+}
 
 macro_rules! index {
     ($name:ident; <Kind, S: Spacing$(, $T:ident)?>; $type:ty; $skeleton:ty) => {
@@ -75,30 +203,36 @@ macro_rules! index {
             pub fn into_range(self) -> (Self, Self) {
                 match self.bound_type() {
                     BoundType::Start => {
-                        let end = self.;
+                        let end = Self::new(
+                            self.skeleton.clone(),
+                            self.index + 1,
+                            self.generation);
                         (self, end)
                     }
                     BoundType::End => {
                         let start = Self::new(
                             self.skeleton.clone(),
-                            self.index - 1);
+                            self.index - 1,
+                            self.generation);
                         (start, self)
                     }
                 }
             }
 
-            pub fn range(&self) -> (MaybeOwned<Self>, MaybeOwned<Self>) {
+            pub fn range(&self) -> (MaybeOwned<'_, Self>, MaybeOwned<'_, Self>) {
                 match self.bound_type() {
                     BoundType::Start => {
                         let end = Self::new(
                             self.skeleton.clone(),
-                            self.index + 1);
+                            self.index + 1,
+                            self.generation);
                         (self.into(), end.into())
                     }
                     BoundType::End => {
                         let start = Self::new(
                             self.skeleton.clone(),
-                            self.index - 1);
+                            self.index - 1,
+                            self.generation);
                         (start.into(), self.into())
                     }
                 }
@@ -120,9 +254,45 @@ impl<Kind, S: Spacing, T> Index<Kind, S, T> {
         ElementRefMut::new_(ephemeral.skeleton.clone(), ephemeral.index)
     }
 
+    /// Returns where the element this index refers to is now, or why it can't be found anymore.
+    pub(crate) fn try_resolve(&self) -> Result<EphemeralIndex<Kind, S, T>, StaleIndexError> {
+        let moved = self.skeleton.borrow().moved.get(&(self.index, self.generation)).cloned();
+        let index = match moved {
+            // the element can only have been dropped along with the spaced list it was in
+            Some(moved) => moved.upgrade().ok_or(StaleIndexError::ElementRemoved)?,
+            None => self.clone(),
+        };
+        let generation = index.skeleton.borrow().generation(index.index);
+        if index.generation == NO_GENERATION || generation == NO_GENERATION {
+            Err(StaleIndexError::ElementRemoved)
+        } else if generation != index.generation {
+            Err(StaleIndexError::SlotReused)
+        } else {
+            Ok(EphemeralIndex::new(index.skeleton, index.index))
+        }
+    }
+
+    pub(crate) fn resolve(&self) -> Option<EphemeralIndex<Kind, S, T>> {
+        self.try_resolve().ok()
+    }
+
+    /// Returns whether the element this index refers to is still in its spaced list.
+    pub fn is_valid(&self) -> bool {
+        self.try_resolve().is_ok()
+    }
+
+    pub fn try_element(&self) -> Result<ElementRef<Kind, S, T>, StaleIndexError> {
+        let ephemeral = self.try_resolve()?;
+        Ok(ElementRef::new_(ephemeral.skeleton, ephemeral.index))
+    }
+
+    pub fn try_position(&self) -> Result<Position<Kind, S, T>, StaleIndexError> {
+        Ok(self.try_resolve()?.position().persistent())
+    }
+
     pub(crate) fn ephemeral(&self) -> EphemeralIndex<Kind, S, T> {
-        self.skeleton.borrow().from_persistent.get().cloned()
-            .unwrap_or(EphemeralIndex::new(self.skeleton.clone(), self.index))
+        // indices of removed elements keep referring to the index they were created for
+        self.resolve().unwrap_or_else(|| EphemeralIndex::new(self.skeleton.clone(), self.index))
     }
 
     pub fn position(&self) -> Position<Kind, S, T> {
@@ -139,6 +309,30 @@ impl<Kind, S: Spacing, T> From<EphemeralIndex<Kind, S, T>> for Index<Kind, S, T>
 index!(HollowIndex; <Kind, S: Spacing>; HollowIndex<Kind, S>; Skeleton<Kind, S, ()>);
 
 impl<Kind, S: Spacing> HollowIndex<Kind, S> {
+    pub(crate) fn try_resolve(&self) -> Result<EphemeralIndex<Kind, S, ()>, StaleIndexError> {
+        let index: Index<Kind, S, ()> = self.clone().into();
+        index.try_resolve()
+    }
+
+    pub(crate) fn resolve(&self) -> Option<EphemeralIndex<Kind, S, ()>> {
+        self.try_resolve().ok()
+    }
+
+    /// Returns whether the element this index refers to is still in its spaced list.
+    pub fn is_valid(&self) -> bool {
+        self.try_resolve().is_ok()
+    }
+
+    /// Hollow spaced lists don't store elements, so this only checks that the element this index
+    /// refers to is still in its spaced list.
+    pub fn try_element(&self) -> Result<(), StaleIndexError> {
+        self.try_resolve().map(|_| ())
+    }
+
+    pub fn try_position(&self) -> Result<HollowPosition<Kind, S>, StaleIndexError> {
+        Ok(self.try_resolve()?.position().persistent().into())
+    }
+
     pub(crate) fn ephemeral(&self) -> EphemeralIndex<Kind, S, ()> {
         let index: Index<Kind, S, ()> = self.clone().into();
         index.ephemeral()
@@ -151,12 +345,70 @@ impl<Kind, S: Spacing> HollowIndex<Kind, S> {
 
 impl<Kind, S: Spacing> From<Index<Kind, S, ()>> for HollowIndex<Kind, S> {
     fn from(index: Index<Kind, S, ()>) -> Self {
-        Self::new(index.skeleton, index.index)
+        Self::new(index.skeleton, index.index, index.generation)
     }
 }
 
 impl<Kind, S: Spacing> From<HollowIndex<Kind, S>> for Index<Kind, S, ()> {
     fn from(index: HollowIndex<Kind, S>) -> Self {
-        Self::new(index.skeleton, index.index)
+        Self::new(index.skeleton, index.index, index.generation)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    use crate::{RangeSpacedList, Skeleton, SpacedList, Spacing};
+
+    fn bookkeeping_entries<Kind, S: Spacing, T>(skeleton: &Rc<RefCell<Skeleton<Kind, S, T>>>) -> usize {
+        let skeleton = skeleton.borrow();
+        skeleton.moved.len() + skeleton.origins.len()
+            + skeleton.subs.iter().flatten().map(bookkeeping_entries).sum::<usize>()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn front_insertion_churn() {
+        let mut list: SpacedList<i64, i64> = SpacedList::new();
+        let mut live = VecDeque::new();
+        let mut removed = vec![];
+        for position in (-10_000..0).rev() {
+            live.push_back((position, list.insert(position, position).index()));
+            if live.len() > 20 {
+                let (_, index) = live.pop_front().unwrap();
+                list.remove(index.clone());
+                removed.push(index);
+            }
+        }
+        for (position, index) in &live {
+            assert_eq!(index.element().unwrap(), *position);
+            assert_eq!(index.position().position(), *position);
+        }
+        for index in removed {
+            assert!(list.try_remove(index).is_err());
+        }
+        assert_eq!(list.size(), live.len());
+        // every live element needs at most one entry in its original skeleton and one where it is
+        assert!(bookkeeping_entries(&list.skeleton) <= 2 * live.len());
+    }
+
+    #[test]
+    fn range_front_insertion() {
+        let mut list: RangeSpacedList<i64, i64> = RangeSpacedList::new();
+        let mut live = VecDeque::new();
+        for position in (0..1_000).map(|i| -3 * i) {
+            live.push_back((position, list.insert(position, position + 2, position).index()));
+            if live.len() > 15 {
+                let (_, index) = live.pop_front().unwrap();
+                list.remove(index);
+            }
+        }
+        for (position, index) in live {
+            assert_eq!(index.element().unwrap(), position);
+            let (start, end) = index.into_range();
+            assert_eq!(start.position().position(), position);
+            assert_eq!(end.position().position(), position + 2);
+        }
+    }
+}
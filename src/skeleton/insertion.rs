@@ -0,0 +1,318 @@
+//! Inserting an element can take many steps: it may need to be inserted into a sub, into a sub of
+//! that sub, and so on, and inserting it before the first element of a skeleton displaces that
+//! element, which then needs to be reinserted further back (possibly displacing yet another
+//! element). Doing all of that recursively can overflow the stack for deep skeletons, so each kind
+//! of skeleton only implements a single step, and [`Skeleton::insert_iteratively`] repeats it
+//! until all elements have been placed.
+//!
+//! Inserting an element next to one whose index is already known doesn't start at the root
+//! skeleton, but at the skeleton of that element, or the closest of its ancestors that the new
+//! element fits into (see [`Skeleton::relative_insertion_start`]). When a sorted batch of elements
+//! is inserted, the elements of the list are walked once alongside the batch, and each element of
+//! the batch is inserted next to the last element of the list at or before it in the same way,
+//! instead of traversing the skeleton from the root for every element.
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use num_traits::zero;
+use thiserror::Error;
+
+use crate::{EphemeralIndex, EphemeralPosition, Index, ParentData, Skeleton, Spacing, StaleIndexError};
+
+#[derive(Error, Debug)]
+pub enum BatchInsertionError<E: Error + 'static> {
+    #[error("Element {index} of the batch is positioned before the element before it.")]
+    Unsorted { index: usize },
+    #[error("Cannot insert element {index} of the batch: {error}")]
+    Insertion {
+        index: usize,
+        #[source]
+        error: E,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum RelativeInsertionError<E: Error + 'static> {
+    #[error("Cannot insert next to an element that is no longer in the list: {0}")]
+    StaleIndex(#[from] StaleIndexError),
+    #[error("Cannot insert next to an element of another list.")]
+    ElementOfOtherList,
+    #[error("Cannot insert at a negative spacing from another element.")]
+    NegativeSpacing,
+    #[error("Cannot insert an element that doesn't fit between another element and its neighbour.")]
+    DoesNotFit,
+    #[error("Cannot insert the element: {error}")]
+    Insertion {
+        #[source]
+        error: E,
+    },
+}
+
+/// The previous first element of a skeleton that has been displaced by an insertion, and needs to
+/// be reinserted into that skeleton.
+pub(super) struct Displaced<Kind, S: Spacing, T, E> {
+    pub(super) index: Index<Kind, S, T>,
+    pub(super) position: S,
+    /// The element itself, and for ranges its span.
+    pub(super) element: E,
+}
+
+/// Where an element has been inserted, and the skeleton that the last element it displaced has
+/// been placed in (or the element itself, if it displaced none), which is the deepest skeleton that
+/// the insertion has made deeper.
+pub(crate) struct Inserted<Kind, S: Spacing, T> {
+    pub(crate) position: EphemeralPosition<Kind, S, T>,
+    pub(crate) deepest: Rc<RefCell<Skeleton<Kind, S, T>>>,
+}
+
+pub(super) enum InsertionStep<Kind, S: Spacing, T, E> {
+    /// The element needs to be inserted into the given sub, at the given position relative to it.
+    IntoSub(Rc<RefCell<Skeleton<Kind, S, T>>>, S, E),
+    /// The element has been placed at the given index.
+    Placed(EphemeralIndex<Kind, S, T>),
+    /// The element has been placed at the first index of a skeleton, displacing its previous
+    /// first element.
+    Displaced(EphemeralIndex<Kind, S, T>, Displaced<Kind, S, T, E>),
+}
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Inserts an element by repeating the given insertion step until it and all elements it
+    /// displaced have been placed. Only the insertion of the element itself can fail, as displaced
+    /// elements are reinserted where they were before.
+    pub(super) fn insert_iteratively<E, Error>(
+        this: Rc<RefCell<Self>>,
+        position: S,
+        mut element: E,
+        links_per_element: usize,
+        step: impl Fn(Rc<RefCell<Self>>, S, E) -> Result<InsertionStep<Kind, S, T, E>, Error>,
+    ) -> Result<Inserted<Kind, S, T>, Error> {
+        let mut skeleton = this;
+        let mut relative_position = position;
+        let mut inserted = None;
+        let mut displaced_index = None;
+        loop {
+            let (placed, displaced) = match step(skeleton, relative_position, element)? {
+                InsertionStep::IntoSub(sub, position, sub_element) => {
+                    skeleton = sub;
+                    relative_position = position;
+                    element = sub_element;
+                    continue;
+                }
+                InsertionStep::Placed(placed) => (placed, None),
+                InsertionStep::Displaced(placed, displaced) => (placed, Some(displaced)),
+            };
+            match displaced_index.take() {
+                Some(displaced_index) => Self::record_move(displaced_index, placed.clone(), links_per_element),
+                None => inserted = Some(placed.clone()),
+            }
+            match displaced {
+                Some(displaced) => {
+                    skeleton = placed.skeleton;
+                    relative_position = displaced.position;
+                    element = displaced.element;
+                    displaced_index = Some(displaced.index);
+                }
+                None => {
+                    // the element itself is always the first one to be placed
+                    let inserted = inserted.unwrap();
+                    return Ok(Inserted {
+                        position: EphemeralPosition::new(inserted.skeleton, inserted.index, position),
+                        deepest: placed.skeleton,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns where to insert an element that starts at `start` and ends at `end` (the same for
+    /// nodes) near the given skeleton: the given skeleton, or the closest of its ancestors whose
+    /// parent would insert the element into it, along with the position that the positions in the
+    /// returned skeleton are relative to. `origin` is that position for the given skeleton, and all
+    /// positions are relative to the root.
+    fn insertion_start(this: Rc<RefCell<Self>>, origin: S, start: S, end: S) -> (Rc<RefCell<Self>>, S) {
+        let mut skeleton = this;
+        let mut origin = origin;
+        loop {
+            let parent_data = skeleton.borrow().parent_data.as_ref()
+                .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent));
+            let Some((parent, index_in_parent)) = parent_data else {
+                return (skeleton, origin);
+            };
+            // a sub holds the elements between the position of its index and the next one
+            let (parent_origin, next_position) = {
+                let parent = parent.borrow();
+                (origin - parent.shallow_position_of(index_in_parent), origin + parent.link(index_in_parent))
+            };
+            if start >= origin && start < next_position && end <= next_position {
+                return (skeleton, origin);
+            }
+            skeleton = parent;
+            origin = parent_origin;
+        }
+    }
+
+    /// Returns where to insert an element that starts at `start` and ends at `end` (the same for
+    /// nodes) next to the element (or bound) at the given position (see
+    /// [`Self::insertion_start`]).
+    pub(crate) fn relative_insertion_start(next_to: &EphemeralPosition<Kind, S, T>, start: S, end: S)
+                                           -> (Rc<RefCell<Self>>, S) {
+        let origin = next_to.position - next_to.skeleton.borrow().shallow_position_of(next_to.index);
+        Self::insertion_start(next_to.skeleton.clone(), origin, start, end)
+    }
+
+    /// Returns the start of an element with the given span (zero for nodes) that is to be inserted
+    /// the given spacing after (or before) the element (or bound) at the given position. The new
+    /// element must end at or before the next element (or start at or after the previous one, or
+    /// at or after zero if there is none).
+    pub(crate) fn relative_insertion_position<E: Error>(next_to: &EphemeralPosition<Kind, S, T>, spacing: S, span: S,
+                                                        after: bool) -> Result<S, RelativeInsertionError<E>> {
+        if spacing < zero() {
+            return Err(RelativeInsertionError::NegativeSpacing);
+        }
+        if after {
+            let start = next_to.position + spacing;
+            if next_to.clone().into_next_element().is_some_and(|next| start + span > next.position) {
+                return Err(RelativeInsertionError::DoesNotFit);
+            }
+            return Ok(start);
+        }
+        let room = match next_to.clone().into_previous_element() {
+            Some(previous) => next_to.position - previous.position,
+            // the spacing before the first element is its position, which can't go below zero
+            None => next_to.position,
+        };
+        if spacing + span > room {
+            return Err(RelativeInsertionError::DoesNotFit);
+        }
+        Ok(next_to.position - spacing - span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{BatchInsertionError, HollowSpacedList, NestedRangeSpacedList, RangeInsertionError, RangeSpacedList,
+                SpacedList};
+
+    #[test]
+    fn nodes() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: SpacedList<i64, i64> = SpacedList::new();
+            let mut expected: SpacedList<i64, i64> = SpacedList::new();
+            let mut indices = vec![];
+            for _ in 0..500 {
+                let position = rng.gen_range(0..10_000) * 2;
+                indices.push((list.insert(position, position).index(), expected.insert(position, position).index()));
+            }
+            // the walk alongside the batch has to skip the slots of removed elements
+            for (index, expected_index) in indices.into_iter().filter(|_| rng.gen_bool(0.3)) {
+                list.remove(index);
+                expected.remove(expected_index);
+            }
+            // odd positions, so that they are distinct from the ones already in the list
+            let batch = (0..500).map(|_| rng.gen_range(-100..10_100) * 2 + 1).sorted().dedup().collect_vec();
+            let positions = list.insert_sorted_batch(batch.iter().map(|&position| (position, position)));
+            for &position in &batch {
+                expected.insert(position, position);
+            }
+            assert_eq!(positions.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec(),
+                       batch.iter().map(|&position| (position, position)).collect_vec());
+            assert_eq!(list.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec(),
+                       expected.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec());
+            assert_eq!(list.size(), expected.size());
+            for position in positions {
+                assert_eq!(position.index().position().position(), position.position());
+            }
+        }
+
+        let mut list: HollowSpacedList<i32> = HollowSpacedList::new();
+        list.insert_sorted_batch([1, 1, 5]);
+        list.insert_sorted_batch([0, 1, 7]);
+        assert_eq!(list.iter().map(|pos| pos.position()).collect_vec(), [0, 1, 1, 1, 5, 7]);
+    }
+
+    #[test]
+    fn ranges() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: RangeSpacedList<i64, i64> = RangeSpacedList::new();
+            for element in 0..300 {
+                let start = rng.gen_range(0..1_000) * 10;
+                let _ = list.try_insert(start, start + 5, element);
+            }
+            let mut expected: RangeSpacedList<i64, i64> = list.iter_ranges()
+                .map(|(start, end)| (start.position(), end.position(), start.element().unwrap()))
+                .collect();
+            // fills some of the gaps between the ranges
+            let batch = (0..1_000)
+                .map(|start| (rng.gen_bool(0.3), start * 10 + 5, start * 10 + 5 + rng.gen_range(0..6), -start))
+                .filter_map(|(chosen, start, end, element)| chosen.then_some((start, end, element)))
+                .collect_vec();
+            let positions = list.insert_sorted_batch(batch.iter().copied());
+            for &(start, end, element) in &batch {
+                expected.insert(start, end, element);
+            }
+            assert_eq!(positions.iter().map(|pos| (pos.position(), pos.span(), pos.element().unwrap())).collect_vec(),
+                       batch.iter().map(|&(start, end, element)| (start, end - start, element)).collect_vec());
+            assert_eq!(list.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec(),
+                       expected.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec());
+        }
+    }
+
+    #[test]
+    fn nested_ranges() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: NestedRangeSpacedList<i64, i64> = NestedRangeSpacedList::new();
+            let mut expected: NestedRangeSpacedList<i64, i64> = NestedRangeSpacedList::new();
+            for element in 0..200 {
+                let start = rng.gen_range(0..1_000);
+                let end = start + rng.gen_range(0..50);
+                if list.try_insert(start, end, element).is_ok() {
+                    expected.insert(start, end, element);
+                }
+            }
+            let mut batch = vec![];
+            // distinct starts, as ranges with the same start can't be reordered without changing
+            // how they are nested
+            for start in 0..1_000 {
+                let end = start + rng.gen_range(0..50);
+                if rng.gen_bool(0.2) && expected.try_insert(start, end, -start).is_ok() {
+                    batch.push((start, end, -start));
+                }
+            }
+            list.insert_sorted_batch(batch);
+            assert_eq!(list.iter().map(|pos| (pos.position(), pos.element().unwrap(), pos.bound_type())).collect_vec(),
+                       expected.iter().map(|pos| (pos.position(), pos.element().unwrap(), pos.bound_type())).collect_vec());
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut list: RangeSpacedList<i32, char> = RangeSpacedList::new();
+        list.insert(10, 20, 'a');
+        list.insert(30, 40, 'b');
+        let contents = |list: &RangeSpacedList<i32, char>|
+            list.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec();
+        let before = contents(&list);
+
+        assert!(matches!(list.try_insert_sorted_batch([(0, 5, 'c'), (22, 25, 'd'), (21, 22, 'e')]),
+                         Err(BatchInsertionError::Unsorted { index: 2 })));
+        assert_eq!(contents(&list), before);
+        assert!(matches!(list.try_insert_sorted_batch([(0, 5, 'c'), (22, 25, 'd'), (35, 36, 'e'), (50, 60, 'f')]),
+                         Err(BatchInsertionError::Insertion { index: 2, error: RangeInsertionError::RangeStartsInsideExistingRange })));
+        assert_eq!(contents(&list), before);
+        assert_eq!(list.size(), 2);
+
+        let mut list: SpacedList<i32, char> = SpacedList::new();
+        assert!(matches!(list.try_insert_sorted_batch([(2, 'a'), (1, 'b')]),
+                         Err(BatchInsertionError::Unsorted { index: 1 })));
+        assert!(list.is_empty());
+    }
+}
@@ -1,12 +1,15 @@
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
 
-use nohash_hasher::{IntMap, IntSet};
+use nohash_hasher::IntMap;
 use num_traits::zero;
 
-use crate::{Spacing, EphemeralIndex, Index};
+use crate::Spacing;
+use crate::skeleton::counts::Counts;
+use crate::skeleton::index::WeakIndex;
+use crate::skeleton::occupancy::Occupancy;
 
 pub struct Node;
 
@@ -30,42 +33,9 @@ pub(crate) struct ParentData<Parent> {
     pub(crate) index_in_parent: usize,
 }
 
-// TODO major optimization opportunity for big lists:
-//  source of problem: all links, elements and subs are stored in a singular Vec
-//  problem 1: When a power of two in size is crossed, allocating the larger array can take a long time, causing up to
-//             seconds-long freezes.
-//  problem 2: Traversal always starts by accessing a link in the second half of the list, and then moves through the
-//             skeleton in jumps of exponentially decaying distances. At the beginning, these distances are extremely
-//             large. This is bad news for the cache.
-//  solution: When a certain threshold in link Vec size is crossed (maybe 2^16 bytes) when pushing an element onto the
-//            skeleton, *don't* append it to this skeleton. Instead, create a new skeleton. Replace the old, "full"
-//            one with this new one in whatever position it was ("root" skeleton of a spaced list, or sub in a parent
-//            skeleton). Add the old skeleton as a sub at index zero to the new one. Create another sub at index one,
-//            and push the element onto that one. Make sure the spacing's all right.
-//            Let's call the new skeleton "hyperskeleton", or "hyper" for short. Hypers have no elements, and a sub at
-//            every index. Traversal through a hyper may never yield an index in the hyper itself, only one in a sub.
-//            This means that all subs of a hyper should have an offset of zero. Iteration through a hyper must
-//            behave like the conjoined iteration through each of its consecutive subs. In short, the existence of the
-//            hyper should be invisible to everyone using this crate, similarly to the existence of subs.
-//            It seems logical to introduce "Hyper" as a new skeleton Kind.
-//            When a skeleton "overflows" (as described above) that is already a sub in a hyper, add a sublist at its
-//            next free index; no need to create a new hyper. Using estimates of 2^12 to 2^16 bytes in a full skeleton,
-//            one hyper can be filled with 2^24 (16 million) to 2^32 (4 billion) bytes of links, and a hyper of
-//            hypers (also possible!) can be filled with 2^36 (69 billion) to 2^48 (281 trillion) link bytes. Safe to
-//            say, hypers don't need to be nested deeply at these sizes.
-//            This mechanism guarantees an upper bound not only on allocated array size, but also on jump distances,
-//            except when entering a sub. In this way, most of the really bad cache misses can be avoided.
-// TODO optimization opportunity: store a small list of recently/often-accessed indices with their respective positions
-//  for quick access (in other words: a shortcuts cache)
-// TODO optimization opportunity: instead of introducing a sub, actually splice the element into the vec and recalculate
-//  spacings accordingly (only when it's faster, so for small skeletons)
 // TODO double-check that subs never have a negative offset
-// TODO optimization opportunity: in empty element slots, store information that makes it O(log n) to skip many empty
-//  slots; this can be achieved by using a similar tree structure as for the spacings (boolean tree with AND and element
-//  access)
 // TODO integrate subs into element slots?
-// TODO insert functions that (also) take an index as a parameter (?)
-// the last element slot must always be full!
+// the last element slot must always be full! (except in hypers, which have no elements at all)
 pub(crate) struct Skeleton<Kind, S: Spacing, T> {
     links: Vec<S>,
     elements: Vec<ElementSlot<T>>,
@@ -74,17 +44,26 @@ pub(crate) struct Skeleton<Kind, S: Spacing, T> {
     offset: S,
     length: S,
     depth: usize,
-    first_persistent_index: isize,
-
-    // TODO how to store persistent indices _correctly_?
-    //  (with support for hypers, in the future)
-    //  => rewrite necessary?
-    // dangling_persistent_indices: HashSet<>,
-
-    /// When an element is removed, its persistent index is inserted into this set.
-    dangling_persistent_indices: IntSet<isize>,
-    from_persistent: IntMap<isize, EphemeralIndex<Kind, S, T>>,
-    into_persistent: IntMap<usize, Index<Kind, S, T>>,
+    /// Hypers are skeletons that only serve to hold other skeletons, so that no single skeleton
+    /// grows beyond [`hyper::HYPER_THRESHOLD`]. See the [`hyper`] module for details.
+    hyper: bool,
+    /// The generation of the element at each index (both bounds of a range share one), or
+    /// [`index::NO_GENERATION`] if there is no element at that index. See [`index`] for details.
+    generations: Vec<usize>,
+    /// The number of elements ever placed into this skeleton, which is the generation of the next
+    /// element placed into it.
+    next_generation: usize,
+    /// Where the elements that were moved out of this skeleton are now, by the index and
+    /// generation that they had in this skeleton.
+    moved: HashMap<(usize, usize), WeakIndex<Kind, S, T>>,
+    /// For the elements that were moved into this skeleton, by their index in this skeleton: the
+    /// index they were originally placed at, which is the one that persistent indices refer to.
+    origins: IntMap<usize, WeakIndex<Kind, S, T>>,
+    /// Which indices hold an element or have a sub that holds one. See [`occupancy`] for details.
+    occupancy: Occupancy,
+    /// How many elements each index stands for, counting those in its sub. See [`counts`] for
+    /// details.
+    counts: Counts,
     _kind: PhantomData<Kind>,
 }
 
@@ -110,10 +89,13 @@ impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
             offset: zero(),
             length: zero(),
             depth: 0,
-            first_persistent_index: 0,
-            dangling_persistent_indices: IntSet::default(),
-            from_persistent: IntMap::default(),
-            into_persistent: IntMap::default(),
+            hyper: false,
+            generations: vec![],
+            next_generation: 0,
+            moved: HashMap::new(),
+            origins: IntMap::default(),
+            occupancy: Occupancy::default(),
+            counts: Counts::default(),
             _kind: PhantomData::<Kind>,
         }))
     }
@@ -171,12 +153,40 @@ impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
                     }))).clone()
         }
     }
+
+    /// Returns the root skeleton of the spaced list that this skeleton belongs to.
+    pub(crate) fn root(this: Rc<RefCell<Self>>) -> Rc<RefCell<Self>> {
+        let mut skeleton = this;
+        loop {
+            let parent = skeleton.borrow().parent_data.as_ref().map(|ParentData { parent, .. }| parent.upgrade().unwrap());
+            match parent {
+                Some(parent) => skeleton = parent,
+                None => return skeleton,
+            }
+        }
+    }
+}
+
+impl<Kind, S: Spacing, T> Drop for Skeleton<Kind, S, T> {
+    fn drop(&mut self) {
+        // dropping a sub drops its subs, which drop their subs, and so on, which can overflow the
+        // stack for deep chains of subs, so subs are dropped one at a time instead
+        let mut subs = self.subs.drain(..).flatten().collect::<Vec<_>>();
+        while let Some(sub) = subs.pop() {
+            // subs that are still referenced elsewhere (by an index, for example) are dropped
+            // later, in the same way
+            if let Ok(sub) = Rc::try_unwrap(sub) {
+                subs.extend(sub.into_inner().subs.drain(..).flatten());
+            }
+        }
+    }
 }
 
 pub mod change_spacing;
 pub mod node;
 pub mod range;
 pub mod nested_range;
+pub mod nesting;
 pub mod traversal;
 pub mod index;
 pub mod ephemeral_index;
@@ -184,3 +194,17 @@ pub mod position;
 pub mod ephemeral_position;
 pub mod bound_type;
 pub mod element_ref;
+pub mod hyper;
+pub mod removal;
+pub mod insertion;
+pub mod occupancy;
+pub mod counts;
+pub mod compaction;
+pub mod balancing;
+pub mod splicing;
+pub mod shortcuts;
+pub mod construction;
+pub mod values;
+pub mod cursor;
+pub mod deletion;
+pub mod splitting;
@@ -1,12 +1,12 @@
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::mem;
 use std::rc::Rc;
 use num_traits::zero;
 use thiserror::Error;
 
-use crate::{BoundType, EphemeralIndex, EphemeralPosition, Index, NestedRange, Skeleton, Spacing};
+use crate::{BoundType, EphemeralIndex, EphemeralPosition, NestedRange, ParentData, Skeleton, Spacing};
 use crate::skeleton::ElementSlot;
+use crate::skeleton::insertion::{Displaced, Inserted, InsertionStep};
 
 #[derive(Error, Debug)]
 pub enum NestedRangePushError {
@@ -26,6 +26,11 @@ pub enum NestedRangeInsertionError {
     NegativeSpan,
 }
 
+/// The span and element of a range that is being inserted, and the sub containing its inner ranges.
+/// Only ranges that have been displaced by an insertion have inner ranges, which they take with
+/// them.
+type RangeWithInnerRanges<S, T> = (S, T, Option<Rc<RefCell<Skeleton<NestedRange, S, T>>>>);
+
 impl<S: Spacing, T> Skeleton<NestedRange, S, T> {
     pub(crate) fn try_push(this: Rc<RefCell<Self>>, distance: S, span: S, element: T)
                            -> Result<EphemeralPosition<NestedRange, S, T>, NestedRangePushError> {
@@ -37,9 +42,22 @@ impl<S: Spacing, T> Skeleton<NestedRange, S, T> {
             // cannot fail because we would have returned with an Err already if span were < 0
             this.borrow_mut().increase_spacing(0, span);
             this.borrow_mut().elements.push(ElementSlot::Some(element));
+            this.borrow_mut().new_generation(0, 2);
             Ok(EphemeralPosition::new(this, 0, distance))
         } else if distance < zero() {
             Err(NestedRangePushError::NegativeDistanceInNonEmptyList)
+        } else if !this.borrow().has_room() && this.borrow().parent_data.is_some() {
+            let hyper = Self::into_hyper(this);
+            Self::try_push(hyper, distance, span, element)
+        } else if this.borrow().hyper {
+            let (sub, sub_position, distance) = Self::hyper_push_target(this.clone(), distance);
+            // cannot fail, because distance and span are non-negative
+            let result = Self::try_push(sub, distance, span, element).unwrap();
+            this.borrow_mut().sync_last_link();
+            Ok(EphemeralPosition {
+                position: sub_position + result.position,
+                ..result
+            })
         } else {
             // last element slot must always be full => no need to handle the case that it's empty
 
@@ -51,29 +69,101 @@ impl<S: Spacing, T> Skeleton<NestedRange, S, T> {
             // cannot fail because we would have returned with an Err already if span were < 0
             this.borrow_mut().increase_spacing(span_index, span);
             this.borrow_mut().elements.push(ElementSlot::Some(element));
+            this.borrow_mut().new_generation(span_index, 2);
             Ok(EphemeralPosition::new(this, span_index, start_position))
         }
     }
 
     pub(crate) fn try_insert(this: Rc<RefCell<Self>>, position: S, span: S, element: T)
-                             -> Result<EphemeralPosition<NestedRange, S, T>, NestedRangeInsertionError> {
+                             -> Result<Inserted<NestedRange, S, T>, NestedRangeInsertionError> {
+        Self::insert_iteratively(this, position, (span, element, None), 2, |skeleton, position, (span, element, inner)|
+            Self::insertion_step(skeleton, position, span, element, inner))
+    }
+
+    /// Places the given sub, which contains the inner ranges of the range that was just placed at
+    /// the given start index, at that index.
+    fn attach_inner_ranges(this: &Rc<RefCell<Self>>, start: usize, inner: Option<Rc<RefCell<Self>>>) {
+        if let Some(inner) = inner {
+            inner.borrow_mut().parent_data = Some(ParentData {
+                parent: Rc::downgrade(this),
+                index_in_parent: start,
+            });
+            let count = inner.borrow().count();
+            let mut skeleton = this.borrow_mut();
+            skeleton.subs[start] = Some(inner);
+            skeleton.increase_count(start, count);
+        }
+    }
+
+    /// Like [`Self::try_push`], but also places the given sub of inner ranges at the new range.
+    fn push_with_inner_ranges(this: Rc<RefCell<Self>>, distance: S, span: S, element: T,
+                              inner: Option<Rc<RefCell<Self>>>) -> EphemeralIndex<NestedRange, S, T> {
+        // cannot fail, as all callers make sure that the distance and span are non-negative (or
+        // that the skeleton is empty)
+        let index = Self::try_push(this, distance, span, element).unwrap().into_index();
+        Self::attach_inner_ranges(&index.skeleton, index.index, inner);
+        index
+    }
+
+    fn insertion_step(this: Rc<RefCell<Self>>, position: S, span: S, element: T, inner: Option<Rc<RefCell<Self>>>)
+                      -> Result<InsertionStep<NestedRange, S, T, RangeWithInnerRanges<S, T>>, NestedRangeInsertionError> {
         if span < zero() {
             Err(NestedRangeInsertionError::NegativeSpan)
         } else if this.borrow().elements.is_empty() {
             // we checked that span is non-negative, so NegativeSpan can't occur, and
             // we checked that the list is empty, so NegativeDistanceInNonEmptyList can't occur
-            // so this cannot fail
-            Ok(Self::try_push(this, position, span, element).unwrap())
+            Ok(InsertionStep::Placed(Self::push_with_inner_ranges(this, position, span, element, inner)))
+        } else if position >= this.borrow().last_position() {
+            let distance = position - this.borrow().last_position();
+            // we checked that span is non-negative, so NegativeSpan can't occur, and
+            // distance cannot be negative either (see its definition and the line above)
+            Ok(InsertionStep::Placed(Self::push_with_inner_ranges(this, distance, span, element, inner)))
+        } else if this.borrow().hyper {
+            if position < this.borrow().offset {
+                if position + span > this.borrow().offset {
+                    return Err(NestedRangeInsertionError::RangeIntersectsExistingRange);
+                }
+                Self::move_hyper_start(this.clone(), position);
+            }
+            let (sub, sub_position, next_sub_position) = Self::hyper_sub_at_or_before(this, position);
+            if position + span > next_sub_position {
+                return Err(NestedRangeInsertionError::RangeIntersectsExistingRange);
+            }
+            Ok(InsertionStep::IntoSub(sub, position - sub_position, (span, element, inner)))
         } else if position < this.borrow().offset {
-            let previous_first_position = this.borrow().offset;
-            let previous_first_span = this.borrow().links[0];
-
-            if position + span > previous_first_position {
+            let offset = this.borrow().offset;
+            if position + span > offset && !this.borrow_mut().move_empty_ranges(0, offset, position + span) {
                 return Err(NestedRangeInsertionError::RangeIntersectsExistingRange);
             }
 
+            let first_sub = this.borrow().sub(0);
+            if this.borrow().elements[0].is_none() {
+                if let Some(sub) = first_sub {
+                    // the first range is empty, but still holds inner ranges, so instead of taking
+                    // its place, the range becomes one of them, after extending the empty range
+                    // to start at it
+                    let mut skeleton = this.borrow_mut();
+                    let shift = offset - position;
+                    skeleton.offset = position;
+                    skeleton.increase_spacing(0, shift);
+                    sub.borrow_mut().offset += shift;
+                    return Ok(InsertionStep::IntoSub(sub, zero(), (span, element, inner)));
+                }
+            }
+
+            let previous_first_index = EphemeralIndex::new(this.clone(), 0).persistent();
+            let previous_first_position = this.borrow().offset;
+            let previous_first_span = this.borrow().links[0];
+
             let previous_first_element_slot =
-                mem::replace(&mut this.borrow_mut().elements[0], ElementSlot::Some(element));
+                this.borrow_mut().elements[0].replace(element);
+            let previous_first_inner = this.borrow_mut().subs[0].take();
+            if let Some(inner) = &previous_first_inner {
+                // the inner ranges are counted again when they are attached to the displaced range
+                let count = inner.borrow().count();
+                this.borrow_mut().decrease_count(0, count);
+            }
+            this.borrow_mut().new_generation(0, 2);
 
             this.borrow_mut().offset = position;
             match span.cmp(&previous_first_span) {
@@ -109,68 +199,65 @@ impl<S: Spacing, T> Skeleton<NestedRange, S, T> {
             therefore, this cannot fail
              */
             this.borrow_mut().increase_spacing_after_index(1, (previous_first_position + previous_first_span) - (position + span));
+            Self::attach_inner_ranges(&this, 0, inner);
 
-            if let ElementSlot::Some(previous_first_element) = previous_first_element_slot {
-                // reinsert old element
-
-                // cannot fail, because we made enough space
-                let insertion_index = Self::try_insert(
-                    this.clone(),
-                    previous_first_position,
-                    previous_first_span,
-                    previous_first_element,
-                ).unwrap().into_index();
-
-                let first_persistent_index = this.borrow().first_persistent_index;
-                this.borrow_mut().from_persistent.insert(first_persistent_index, insertion_index.clone());
-                this.borrow_mut().from_persistent.insert(first_persistent_index + 1, insertion_index.into_range().1);
+            let first_index = EphemeralIndex::new(this, 0);
+            match previous_first_element_slot {
+                // the old element needs to be reinserted, which cannot fail, because we made enough
+                // space
+                ElementSlot::Some(previous_first_element) => Ok(InsertionStep::Displaced(first_index, Displaced {
+                    index: previous_first_index,
+                    position: previous_first_position,
+                    element: (previous_first_span, previous_first_element, previous_first_inner),
+                })),
+                ElementSlot::None => Ok(InsertionStep::Placed(first_index)),
             }
-
-            this.borrow_mut().first_persistent_index -= 2;
-            let first_persistent_index = this.borrow().first_persistent_index;
-
-            let first_index = EphemeralIndex::new(this.clone(), 0);
-            this.borrow_mut().from_persistent.insert(first_persistent_index, first_index.clone());
-            this.borrow_mut().from_persistent.insert(first_persistent_index + 1, first_index.into_range().1);
-
-            let first_index = Index::new(this.clone(), first_persistent_index);
-            this.borrow_mut().into_persistent.insert(0, first_index.clone());
-            this.borrow_mut().into_persistent.insert(1, first_index.into_range().1);
-
-            return Ok(EphemeralPosition::new(this, 0, position));
-        } else if position >= this.borrow().last_position() {
-            let distance = position - this.borrow().last_position();
-            // we checked that span is non-negative, so NegativeSpan can't occur, and
-            // distance cannot be negative either (see its definition and the line above)
-            // so this cannot fail
-            return Ok(Self::try_push(this, distance, span, element).unwrap());
         } else {
             let result = Self::shallow_at_or_before(this.clone(), position).unwrap();
             match BoundType::of(result.index) {
                 BoundType::Start => {
                     let outer_span = result.span();
-                    let sub = Self::ensure_sub(this, result.index);
                     if position + span > result.position + outer_span {
                         return Err(NestedRangeInsertionError::InnerRangeExceedsOuterRange)
                     }
-                    Ok(EphemeralPosition {
-                        position,
-                        ..Self::try_insert(sub, position - result.position, span, element)?
-                    })
+                    let sub = Self::ensure_sub(this, result.index);
+                    Ok(InsertionStep::IntoSub(sub, position - result.position, (span, element, inner)))
                 }
                 BoundType::End => {
+                    let reused = this.borrow_mut().reuse_empty_range(result.index, result.position, position, span);
+                    if let Some(start) = reused {
+                        this.borrow_mut().elements[start / 2] = ElementSlot::Some(element);
+                        this.borrow_mut().new_generation(start, 2);
+                        Self::attach_inner_ranges(&this, start, inner);
+                        return Ok(InsertionStep::Placed(EphemeralIndex::new(this, start)));
+                    }
+                    // a range that starts before the end of the sub after the bound has to go into
+                    // it, which can still fail, so nothing may be moved to make room for it before
+                    if this.borrow().sub(result.index)
+                        .is_some_and(|sub| position < result.position + sub.borrow().last_position()) {
+                        let sub = Self::ensure_sub(this, result.index);
+                        return Ok(InsertionStep::IntoSub(sub, position - result.position, (span, element, inner)));
+                    }
                     let space_between = this.borrow().link(result.index);
-                    let sub = Self::ensure_sub(this, result.index);
                     // I have some doubts if this should be > or >=
-                    if position + span > result.position + space_between {
+                    if position + span > result.position + space_between
+                        && !this.borrow_mut().move_empty_ranges(result.index + 1, result.position + space_between, position + span) {
                         return Err(NestedRangeInsertionError::RangeIntersectsExistingRange)
                     }
-                    Ok(EphemeralPosition {
-                        position,
-                        ..Self::try_insert(sub, position - result.position, span, element)?
-                    })
+                    if let Some(spacings) = Self::splice_spacings(&this, &result, position, span) {
+                        let start = Self::splice(&this, result.index, &spacings, element);
+                        Self::attach_inner_ranges(&this, start.index, inner);
+                        return Ok(InsertionStep::Placed(start));
+                    }
+                    let sub = Self::ensure_sub(this, result.index);
+                    Ok(InsertionStep::IntoSub(sub, position - result.position, (span, element, inner)))
                 }
             }
         }
     }
-}
\ No newline at end of file
+
+    pub(crate) fn remove(this: Rc<RefCell<Self>>, index: EphemeralIndex<NestedRange, S, T>) -> Option<T> {
+        // both bounds of a range share one element slot, so they are removed together
+        Self::remove_element(this, index.index / 2, 2)
+    }
+}
@@ -0,0 +1,359 @@
+//! The ranges of a nested range skeleton form a tree: the inner ranges of a range are in the sub
+//! at its start index, while the subs at end indices (gap subs) and the subs of hypers hold ranges
+//! on the same level as the ranges of their parent. A range whose element was removed can still
+//! hold inner ranges, which then belong to the level of that empty range.
+//!
+//! So the parent of a range is found by going up through the ancestors of its skeleton until one
+//! is entered from a start index that holds an element, and the ranges on one level are found by
+//! going through the occupied indices of a skeleton, descending into the subs that are on the same
+//! level.
+
+use std::cell::RefCell;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use num_traits::zero;
+
+use crate::{BoundType, EphemeralPosition, HollowPosition, NestedRange, ParentData, Position, Skeleton, Spacing};
+use crate::skeleton::index::NO_GENERATION;
+use crate::skeleton::traversal::iteration::Iter;
+
+impl<S: Spacing, T> Skeleton<NestedRange, S, T> {
+    /// Returns whether the given index is the start of a range that holds an element.
+    fn is_start_of_range(&self, index: usize) -> bool {
+        !self.hyper && BoundType::of(index) == BoundType::Start && self.generation(index) != NO_GENERATION
+    }
+
+    /// Returns the start of the innermost range that contains the range starting at the given
+    /// position, or `None` if that range is on the top level.
+    pub(crate) fn parent_range(start: EphemeralPosition<NestedRange, S, T>) -> Option<EphemeralPosition<NestedRange, S, T>> {
+        // the position of the index that the current skeleton is the sub of
+        let mut position = start.position - start.skeleton.borrow().shallow_position_of(start.index);
+        let mut skeleton = start.skeleton;
+        loop {
+            let (parent, index) = skeleton.borrow().parent_data.as_ref()
+                .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent))?;
+            if parent.borrow().is_start_of_range(index) {
+                return Some(EphemeralPosition::new(parent, index, position));
+            }
+            position -= parent.borrow().shallow_position_of(index);
+            skeleton = parent;
+        }
+    }
+
+    /// Returns an iterator over the starts of the ranges directly inside the range starting at
+    /// the given position.
+    pub(crate) fn inner_ranges(start: &EphemeralPosition<NestedRange, S, T>) -> LevelIter<S, T> {
+        let sub = start.skeleton.borrow().sub(start.index);
+        LevelIter {
+            stack: sub.into_iter().map(|sub| (sub, 0, start.position)).collect(),
+        }
+    }
+
+    /// Returns an iterator over the starts of the ranges on the top level of this skeleton, which
+    /// must be the root.
+    pub(crate) fn top_level_ranges(this: Rc<RefCell<Self>>) -> LevelIter<S, T> {
+        LevelIter {
+            stack: vec![(this, 0, zero())],
+        }
+    }
+}
+
+/// A skeleton being iterated over by a [`LevelIter`], with the next index to look at and the
+/// position of the index in the parent that the skeleton is the sub of.
+type Level<S, T> = (Rc<RefCell<Skeleton<NestedRange, S, T>>>, usize, S);
+
+/// Iterates over the starts of the ranges on one level, in ascending order.
+pub(crate) struct LevelIter<S: Spacing, T> {
+    /// Only the last skeleton is being iterated over, the others continue after it.
+    stack: Vec<Level<S, T>>,
+}
+
+impl<S: Spacing, T> Iterator for LevelIter<S, T> {
+    type Item = EphemeralPosition<NestedRange, S, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (skeleton, next, base) = self.stack.last_mut()?;
+            let Some(index) = skeleton.borrow().occupancy.next(*next) else {
+                self.stack.pop();
+                continue;
+            };
+            *next = index + 1;
+            let position = *base + skeleton.borrow().shallow_position_of(index);
+            if skeleton.borrow().is_start_of_range(index) {
+                // the sub at this index holds ranges of the level below, so it is skipped
+                return Some(EphemeralPosition::new(skeleton.clone(), index, position));
+            }
+            // a gap sub, the sub of a hyper, or the inner ranges of an empty range
+            let sub = skeleton.borrow().sub(index);
+            if let Some(sub) = sub {
+                self.stack.push((sub, 0, position));
+            }
+        }
+    }
+}
+
+/// The order in which the ranges of a nested range list are iterated over.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RangeOrder {
+    /// By their starts, so outer ranges come before the ranges inside of them.
+    PreOrder,
+    /// By their ends, so outer ranges come after the ranges inside of them.
+    PostOrder,
+}
+
+/// An iterator over the ranges of a nested range spaced list, as their start, their end and the
+/// number of ranges containing them, built on [`Iter`], which produces their bounds in order.
+///
+/// The depth of a range is the number of ranges that have been entered, but not left yet, from
+/// the side it is produced at, so the front and the back keep track of it separately.
+pub struct NestedRanges<S: Spacing, T, P = Position<NestedRange, S, T>> {
+    bounds: Iter<NestedRange, S, T>,
+    order: RangeOrder,
+    front_open: usize,
+    back_open: usize,
+    remaining: usize,
+    _position: PhantomData<P>,
+}
+
+/// [`NestedRanges`] of a hollow nested range spaced list.
+pub type HollowNestedRanges<S> = NestedRanges<S, (), HollowPosition<NestedRange, S>>;
+
+impl<S: Spacing, T, P> NestedRanges<S, T, P> {
+    /// Creates an iterator over the given bounds of `len` ranges.
+    pub(crate) fn new(bounds: Iter<NestedRange, S, T>, len: usize, order: RangeOrder) -> Self {
+        Self {
+            bounds,
+            order,
+            front_open: 0,
+            back_open: 0,
+            remaining: len,
+            _position: PhantomData,
+        }
+    }
+}
+
+impl<S: Spacing, T, P: From<Position<NestedRange, S, T>>> NestedRanges<S, T, P> {
+    /// Returns the range of the given bound if it is the one that ranges are produced at.
+    fn produced(&mut self, bound: Position<NestedRange, S, T>, depth: usize) -> Option<(P, P, usize)> {
+        let produced_at = match self.order {
+            RangeOrder::PreOrder => BoundType::Start,
+            RangeOrder::PostOrder => BoundType::End,
+        };
+        if bound.bound_type() != produced_at {
+            return None;
+        }
+        self.remaining -= 1;
+        let (start, end) = bound.into_range();
+        Some((start.into(), end.into(), depth))
+    }
+}
+
+impl<S: Spacing, T, P> Clone for NestedRanges<S, T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            bounds: self.bounds.clone(),
+            order: self.order,
+            front_open: self.front_open,
+            back_open: self.back_open,
+            remaining: self.remaining,
+            _position: PhantomData,
+        }
+    }
+}
+
+impl<S: Spacing, T, P: From<Position<NestedRange, S, T>>> Iterator for NestedRanges<S, T, P> {
+    type Item = (P, P, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let bound = self.bounds.next()?;
+            // from the front, ranges are entered at their start and left at their end
+            let depth = match bound.bound_type() {
+                BoundType::Start => {
+                    self.front_open += 1;
+                    self.front_open - 1
+                }
+                BoundType::End => {
+                    self.front_open -= 1;
+                    self.front_open
+                }
+            };
+            if let Some(range) = self.produced(bound, depth) {
+                return Some(range);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<S: Spacing, T, P: From<Position<NestedRange, S, T>>> DoubleEndedIterator for NestedRanges<S, T, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let bound = self.bounds.next_back()?;
+            // from the back, ranges are entered at their end and left at their start
+            let depth = match bound.bound_type() {
+                BoundType::End => {
+                    self.back_open += 1;
+                    self.back_open - 1
+                }
+                BoundType::Start => {
+                    self.back_open -= 1;
+                    self.back_open
+                }
+            };
+            if let Some(range) = self.produced(bound, depth) {
+                return Some(range);
+            }
+        }
+    }
+}
+
+impl<S: Spacing, T, P: From<Position<NestedRange, S, T>>> ExactSizeIterator for NestedRanges<S, T, P> {}
+
+impl<S: Spacing, T, P: From<Position<NestedRange, S, T>>> FusedIterator for NestedRanges<S, T, P> {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{BoundType, NestedRange, NestedRangeSpacedList, Position, RangeOrder};
+
+    fn check(list: &NestedRangeSpacedList<i64, u32>) {
+        // the parent of every range and its bounds by its element, found by going through all
+        // bounds in order
+        let mut parents = HashMap::new();
+        let mut ranges = HashMap::new();
+        let mut open = vec![];
+        for bound in list.iter() {
+            let element = bound.element().unwrap();
+            match bound.bound_type() {
+                BoundType::Start => {
+                    parents.insert(element, open.last().copied());
+                    ranges.insert(element, (bound.position(), bound.position() + bound.span()));
+                    open.push(element);
+                }
+                BoundType::End => assert_eq!(open.pop(), Some(element)),
+            }
+        }
+        let element = |position: Option<Position<NestedRange, i64, u32>>|
+            position.map(|position| position.element().unwrap());
+        let mut children: HashMap<Option<u32>, Vec<u32>> = HashMap::new();
+        for bound in list.iter().filter(|bound| bound.bound_type() == BoundType::Start) {
+            children.entry(parents[&bound.element().unwrap()]).or_default().push(bound.element().unwrap());
+        }
+        assert_eq!(list.roots().map(|root| root.element().unwrap()).collect_vec(),
+                   children.get(&None).cloned().unwrap_or_default());
+        for bound in list.iter() {
+            let this = bound.element().unwrap();
+            let parent = parents[&this];
+            assert_eq!(element(list.parent(&bound)), parent);
+            let ancestors = list.ancestors(&bound).map(|ancestor| ancestor.element().unwrap()).collect_vec();
+            assert_eq!(ancestors.first().copied(), parent);
+            assert_eq!(list.depth(&bound), ancestors.len());
+            assert_eq!(list.children(&bound).map(|child| child.element().unwrap()).collect_vec(),
+                       children.get(&Some(this)).cloned().unwrap_or_default());
+            assert_eq!(list.siblings(&bound).map(|sibling| sibling.element().unwrap()).collect_vec(),
+                       children[&parent].iter().copied().filter(|&sibling| sibling != this).collect_vec());
+        }
+        assert_eq!(list.iter_values().iter().map(|(position, &value)| (position, value)).collect_vec(),
+                   list.iter().filter(|bound| bound.bound_type() == BoundType::Start)
+                       .map(|start| (start.position(), start.element().unwrap())).collect_vec());
+        let pre_order = list.iter_ranges(RangeOrder::PreOrder)
+            .map(|(start, end, depth)| (start.element().unwrap(), end.position(), depth))
+            .collect_vec();
+        assert_eq!(pre_order.iter().map(|&(element, ..)| element).collect_vec(),
+                   list.iter().filter(|bound| bound.bound_type() == BoundType::Start)
+                       .map(|start| start.element().unwrap()).collect_vec());
+        for &(element, end, depth) in &pre_order {
+            assert_eq!(ranges[&element].1, end);
+            let mut ancestor = parents[&element];
+            let mut expected_depth = 0;
+            while let Some(element) = ancestor {
+                expected_depth += 1;
+                ancestor = parents[&element];
+            }
+            assert_eq!(depth, expected_depth);
+        }
+        let post_order = list.iter_ranges(RangeOrder::PostOrder)
+            .map(|(start, _, depth)| (start.element().unwrap(), depth))
+            .collect_vec();
+        assert_eq!(post_order.iter().map(|&(element, _)| element).collect_vec(),
+                   list.iter().filter(|bound| bound.bound_type() == BoundType::End)
+                       .map(|end| end.element().unwrap()).collect_vec());
+        assert_eq!(post_order.iter().copied().sorted().collect_vec(),
+                   pre_order.iter().map(|&(element, _, depth)| (element, depth)).sorted().collect_vec());
+        for order in [RangeOrder::PreOrder, RangeOrder::PostOrder] {
+            let forwards = list.iter_ranges(order)
+                .map(|(start, _, depth)| (start.element().unwrap(), depth))
+                .collect_vec();
+            let backwards = list.iter_ranges_backwards(order)
+                .map(|(start, _, depth)| (start.element().unwrap(), depth))
+                .collect_vec();
+            assert_eq!(forwards.iter().copied().rev().collect_vec(), backwards);
+            // both ends meeting in the middle
+            let mut iter = list.iter_ranges(order);
+            let mut front = vec![];
+            let mut back = vec![];
+            while let Some((start, _, depth)) = iter.next() {
+                front.push((start.element().unwrap(), depth));
+                if let Some((start, _, depth)) = iter.next_back() {
+                    back.push((start.element().unwrap(), depth));
+                }
+                assert_eq!(iter.len(), forwards.len() - front.len() - back.len());
+            }
+            front.extend(back.into_iter().rev());
+            assert_eq!(front, forwards);
+        }
+        for position in -1..=list.last().map_or(0, |last| last.position() + 1) {
+            let contains = |element: &u32| ranges[element].0 <= position && position <= ranges[element].1;
+            match element(list.innermost_containing(position)) {
+                None => assert!(!ranges.keys().any(contains)),
+                Some(innermost) => {
+                    assert!(contains(&innermost));
+                    // no range containing the position is inside the innermost one
+                    for other in ranges.keys().filter(|other| contains(other)) {
+                        let mut ancestor = parents[other];
+                        while let Some(element) = ancestor {
+                            assert_ne!(element, innermost, "{other} is inside {innermost} at {position}");
+                            ancestor = parents[&element];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tree_navigation() {
+        for seed in 0..6 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: NestedRangeSpacedList<i64, u32> = NestedRangeSpacedList::new();
+            let mut indices = vec![];
+            for element in 0..1_000 {
+                let start = rng.gen_range(0..2_000);
+                if let Ok(position) = list.try_insert(start, start + rng.gen_range(0..60), element) {
+                    indices.push(position.index());
+                }
+            }
+            check(&list);
+            for index in indices.into_iter().filter(|_| rng.gen_bool(0.4)) {
+                list.remove(index);
+            }
+            check(&list);
+            if seed % 2 == 0 {
+                list.compact();
+                check(&list);
+            }
+        }
+    }
+}
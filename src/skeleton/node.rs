@@ -1,10 +1,11 @@
 use std::cell::RefCell;
-use std::mem;
+use std::convert::Infallible;
 use std::rc::Rc;
 use num_traits::zero;
 use thiserror::Error;
 
-use crate::{ElementSlot, EphemeralIndex, EphemeralPosition, Index, Node, Skeleton, Spacing};
+use crate::{ElementSlot, EphemeralIndex, EphemeralPosition, Node, Skeleton, Spacing};
+use crate::skeleton::insertion::{Displaced, Inserted, InsertionStep};
 
 #[derive(Error, Debug)]
 pub enum PushError {
@@ -19,9 +20,22 @@ impl<S: Spacing, T> Skeleton<Node, S, T> {
         if this.borrow().elements.is_empty() {
             this.borrow_mut().offset = distance;
             this.borrow_mut().elements.push(ElementSlot::Some(element));
+            this.borrow_mut().new_generation(0, 1);
             Ok(EphemeralPosition::new(this, 0, distance))
         } else if distance < zero() {
             Err(PushError::NegativeDistanceInNonEmptyList)
+        } else if !this.borrow().has_room() && this.borrow().parent_data.is_some() {
+            let hyper = Self::into_hyper(this);
+            Self::try_push(hyper, distance, element)
+        } else if this.borrow().hyper {
+            let (sub, sub_position, distance) = Self::hyper_push_target(this.clone(), distance);
+            // cannot fail, because distance is non-negative
+            let result = Self::try_push(sub, distance, element).unwrap();
+            this.borrow_mut().sync_last_link();
+            Ok(EphemeralPosition {
+                position: sub_position + result.position,
+                ..result
+            })
         } else {
             // last element slot must always be full => no need to handle the case that it's empty
 
@@ -29,6 +43,7 @@ impl<S: Spacing, T> Skeleton<Node, S, T> {
             // cannot fail because we would have returned with an Err already if distance were < 0
             this.borrow_mut().increase_spacing(index, distance);
             this.borrow_mut().elements.push(ElementSlot::Some(element));
+            this.borrow_mut().new_generation(index + 1, 1);
             Ok(EphemeralPosition::at_end(this))
         }
     }
@@ -38,7 +53,7 @@ impl<S: Spacing, T> Skeleton<Node, S, T> {
     //    __3__   __2__
     //  4 | 3 | 2 | 2 | 1 |
     //    A   B   C   D   E            A is now at index 0
-    //                                 persistent index of A = 0
+    //                                 persistent index of A = (0, generation 0)
     // insert at 3:
     // make space
     //    ________9________
@@ -52,68 +67,66 @@ impl<S: Spacing, T> Skeleton<Node, S, T> {
     //    __4__   __2__
     //  3 | 4 | 2 | 2 | 1 |
     //    F   B   C   D   E            F is now at index 0 (ike)
-    // reinsert old element            persistent index of F = -1
+    // reinsert old element            persistent index of F = (0, generation 5)
     //    ________9________
     //    ____6____
     //    __4__   __2__
     //  3 | 4 | 2 | 2 | 1 |
-    //    F   B   C   D   E            counter = 1
+    //    F   B   C   D   E
     //    sub:
-    //     1 |                         persistent index of B = 1
-    //       A                         persistent index of A = 0
-    //                                 persistent index of F = -1
+    //     1 |                         persistent index of B = (1, generation 1)
+    //       A                         persistent index of A = (0, generation 0)
+    //                                 persistent index of F = (0, generation 5)
     //
-    //                                 access at 1 => self at 1
-    //                                 access at 0 => get new index with skeleton from map/vec
-    //                                 access at -1 => self at 0
+    //                                 access at (1, 1) => self at 1
+    //                                 access at (0, 0) => moved, get new index from map
+    //                                 access at (0, 5) => self at 0
     //
-    // ephemeral 0 => persistent -1
-    // persistent -1 => ephemeral 0
-    // persistent 0 => ephemeral sub/0
+    // ephemeral 0 => persistent (0, 5)
+    // ephemeral sub/0 => persistent (0, 0), as A originally was at 0
+    // persistent (0, 0) => ephemeral sub/0
     pub(crate) fn insert(this: Rc<RefCell<Self>>, position: S, element: T)
-                         -> EphemeralPosition<Node, S, T> {
-        // TODO rewrite this recursion as iteration, as the recursion can lead to stack overflows
-        //  (for example when inserting at a million random u32s into a skeleton)
+                         -> Inserted<Node, S, T> {
+        let Ok(inserted) = Self::insert_iteratively(this, position, element, 1, |skeleton, position, element|
+            Ok::<_, Infallible>(Self::insertion_step(skeleton, position, element)));
+        inserted
+    }
 
+    fn insertion_step(this: Rc<RefCell<Self>>, position: S, element: T) -> InsertionStep<Node, S, T, T> {
         if this.borrow().elements.is_empty() {
             // cannot fail, because try_push can only fail when the list is non-empty, which it can't be in this branch
-            Self::try_push(this, position, element).unwrap()
+            InsertionStep::Placed(Self::try_push(this, position, element).unwrap().into_index())
+        } else if position >= this.borrow().last_position() {
+            let distance = position - this.borrow().last_position();
+            // cannot fail, because distance cannot be non-negative (by definition and the condition of this branch)
+            InsertionStep::Placed(Self::try_push(this, distance, element).unwrap().into_index())
+        } else if this.borrow().hyper {
+            if position < this.borrow().offset {
+                Self::move_hyper_start(this.clone(), position);
+            }
+            let (sub, sub_position, _) = Self::hyper_sub_at_or_before(this, position);
+            InsertionStep::IntoSub(sub, position - sub_position, element)
         } else if position < this.borrow().offset {
+            let previous_first_index = EphemeralIndex::new(this.clone(), 0).persistent();
             let previous_first_position = this.borrow().offset;
             let previous_first_element_slot =
-                mem::replace(&mut this.borrow_mut().elements[0], ElementSlot::Some(element));
+                this.borrow_mut().elements[0].replace(element);
+            this.borrow_mut().new_generation(0, 1);
 
             // cannot fail, because we already established previous_first_position > position
             this.borrow_mut().increase_spacing_after_index(0, previous_first_position - position);
             this.borrow_mut().offset = position;
 
-            if let ElementSlot::Some(previous_first_element) = previous_first_element_slot {
-                // reinsert old element
-
-                let insertion_index = Self::insert(
-                    this.clone(),
-                    previous_first_position,
-                    previous_first_element,
-                ).into_index();
-
-                let first_persistent_index = this.borrow().first_persistent_index;
-                this.borrow_mut().from_persistent.insert(first_persistent_index, insertion_index);
+            let first_index = EphemeralIndex::new(this, 0);
+            match previous_first_element_slot {
+                // the old element needs to be reinserted
+                ElementSlot::Some(previous_first_element) => InsertionStep::Displaced(first_index, Displaced {
+                    index: previous_first_index,
+                    position: previous_first_position,
+                    element: previous_first_element,
+                }),
+                ElementSlot::None => InsertionStep::Placed(first_index),
             }
-
-            this.borrow_mut().first_persistent_index -= 1;
-            let first_persistent_index = this.borrow().first_persistent_index;
-
-            let first_index = EphemeralIndex::new(this.clone(), 0);
-            this.borrow_mut().from_persistent.insert(first_persistent_index, first_index);
-
-            let first_index = Index::new(this.clone(), first_persistent_index);
-            this.borrow_mut().into_persistent.insert(0, first_index);
-
-            EphemeralPosition::new(this, 0, position)
-        } else if position >= this.borrow().last_position() {
-            let distance = position - this.borrow().last_position();
-            // cannot fail, because distance cannot be non-negative (by definition and the condition of this branch)
-            return Self::try_push(this, distance, element).unwrap();
         } else {
             // TODO for (nested) range too
 
@@ -126,8 +139,9 @@ impl<S: Spacing, T> Skeleton<Node, S, T> {
             if at_or_before.index == at_or_after.index && at_or_before.element().is_none() {
                 // great, let's put our element there
                 *at_or_before.element_mut() = ElementSlot::Some(element);
+                this.borrow_mut().new_generation(at_or_before.index, 1);
                 // no need to adjust spacing, everything's nicely positioned already (what a coincidence!)
-                return at_or_before;
+                return InsertionStep::Placed(at_or_before.into_index());
             }
 
             // there is no slot at position
@@ -135,10 +149,7 @@ impl<S: Spacing, T> Skeleton<Node, S, T> {
             if let Some(sub) = this.borrow().sub(at_or_before.index)
                 .take_if(|sub| position <= at_or_before.position + sub.borrow().last_position()) {
                 // we *need* to insert element into this sub
-                return EphemeralPosition {
-                    position,
-                    ..Self::insert(sub, position - at_or_before.position, element)
-                };
+                return InsertionStep::IntoSub(sub, position - at_or_before.position, element);
             }
 
             // we can safely decrease the spacing between at_or_before and at_or_after so much that
@@ -147,51 +158,60 @@ impl<S: Spacing, T> Skeleton<Node, S, T> {
             if at_or_after.element().is_none() {
                 // great, let's put our element there
                 *at_or_after.element_mut() = ElementSlot::Some(element);
+                this.borrow_mut().new_generation(at_or_after.index, 1);
                 // adjust spacings
                 // basically decrease_spacing_before_index(at_or_after.index, ...)
-                // can't fail because at_or_after.position is, well, at or after position
-                this.borrow_mut().decrease_spacing(at_or_after.index - 1, at_or_after.position - position);
-                this.borrow_mut().increase_spacing_after_index(at_or_after.index, position - at_or_after.position);
-                return EphemeralPosition::new(this, at_or_after.index, position);
+                // can't fail because at_or_after.position is, well, at or after position, and if it
+                // is at position, zero-width links may put it at index 0 or before at_or_before, but
+                // then nothing needs to move
+                let change = at_or_after.position - position;
+                if change > zero() {
+                    this.borrow_mut().decrease_spacing(at_or_after.index - 1, change);
+                    this.borrow_mut().increase_spacing_after_index(at_or_after.index, change);
+                }
+                return InsertionStep::Placed(EphemeralIndex::new(this, at_or_after.index));
             }
 
             // if it isn't tho, we can check if maybe we can put it in the at_or_before slot, but
             // only if it doesn't have a sub (because we otherwise couldn't adjust the spacing well)
 
-            if this.borrow().sub(at_or_before.index).is_none() && at_or_before.element().is_none() {
+            // the start of a skeleton can't move if it is the sub of a hyper
+            if this.borrow().sub(at_or_before.index).is_none() && at_or_before.element().is_none()
+                && (at_or_before.index > 0 || !this.borrow().is_sub_of_hyper()) {
                 // great, let's put our element there
                 *at_or_before.element_mut() = ElementSlot::Some(element);
+                this.borrow_mut().new_generation(at_or_before.index, 1);
                 // adjust spacings
                 // basically increase_spacing_before_index(at_or_before.index, ...)
                 // can't fail because at_or_before.position is, well, at or before position
-                this.borrow_mut().increase_spacing(at_or_before.index - 1, at_or_before.position - position);
+                if at_or_before.index == 0 {
+                    this.borrow_mut().offset = position;
+                } else {
+                    this.borrow_mut().increase_spacing(at_or_before.index - 1, position - at_or_before.position);
+                }
                 this.borrow_mut().decrease_spacing_after_index(at_or_before.index, position - at_or_before.position);
-                return EphemeralPosition::new(this, at_or_before.index, position);
+                return InsertionStep::Placed(EphemeralIndex::new(this, at_or_before.index));
             }
 
             // we couldn't put our element in at_or_before or at_or_after, nor into the existing
-            // range of at_or_before's sub, so we'll just put it in between
+            // range of at_or_before's sub, so we'll just put it in between, right into this
+            // skeleton if it is small enough, and if it doesn't share its position with either of
+            // them, as zero-width links would make the two queries above disagree
+
+            let spacing = this.borrow().link(at_or_before.index);
+            let distance = position - at_or_before.position;
+            if this.borrow().can_splice() && distance > zero() && distance < spacing {
+                return InsertionStep::Placed(Self::splice(&this, at_or_before.index, &[distance, spacing - distance], element));
+            }
 
             let sub = Self::ensure_sub(this, at_or_before.index);
-            EphemeralPosition {
-                position,
-                ..Self::insert(sub, position - at_or_before.position, element)
-            }
+            InsertionStep::IntoSub(sub, position - at_or_before.position, element)
         }
     }
 
     pub(crate) fn remove(this: Rc<RefCell<Self>>, index: EphemeralIndex<Node, S, T>) -> Option<T> {
         // TODO check that EphemeralIndex belongs to this skeleton / take a usize index instead?
-
-        let old_persistent_index = index.persistent();
-        this.borrow_mut().dangling_persistent_indices.insert(old_persistent_index.index);
-        this.borrow_mut().from_persistent.remove(&old_persistent_index.index);
-
-        this.borrow_mut().first_persistent_index -= 1;
-        let new_persistent_index = this.borrow().first_persistent_index;
-        this.borrow_mut().into_persistent.insert(index.index, Index::new(this.clone(), new_persistent_index));
-
-        this.borrow_mut().elements[index.index].take()
+        Self::remove_element(this, index.index, 1)
     }
 }
 
@@ -200,15 +220,16 @@ mod tests {
     use std::cell::RefCell;
     use std::rc::Rc;
     use itertools::Itertools;
-    use rand::random;
-    use crate::{Node, Spacing, Skeleton, SpacedList};
+    use rand::{random, Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use crate::{HollowSpacedList, Node, ParentData, Spacing, Skeleton, SpacedList};
 
     #[test]
     fn removal() {
         let mut list: SpacedList<u32, char> = SpacedList::new();
-        let a = list.insert(1, 'a');
+        list.insert(1, 'a');
         let b = list.insert(2, 'b');
-        let c = list.insert(3, 'c');
+        list.insert(3, 'c');
 
         list.remove(b.index());
 
@@ -219,6 +240,73 @@ mod tests {
         // assert_eq!(vec!['a', 'c'], list.iter().map(|pos| pos.element().unwrap()).collect_vec());
     }
 
+    #[test]
+    fn insert_at_position_of_spliced_element() {
+        let mut list: SpacedList<i32, i32> = SpacedList::new();
+        let first = list.insert(3, 0);
+        list.push(2, 1);
+        list.insert(3, 2);
+        list.insert(4, 3);
+        list.insert(5, 4);
+        list.remove(first.index());
+        list.push(4, 5);
+        list.insert(3, 6);
+        assert_eq!(list.iter().map(|pos| pos.position()).collect_vec(), [3, 3, 4, 5, 5, 9]);
+    }
+
+    #[test]
+    fn insertions_at_shared_positions() {
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: SpacedList<i32, i32> = SpacedList::new();
+            let mut expected = vec![];
+            let mut indices = vec![];
+            for element in 0..40 {
+                match rng.gen_range(0..4) {
+                    0 if !indices.is_empty() => {
+                        let (index, position) = indices.swap_remove(rng.gen_range(0..indices.len()));
+                        list.remove(index);
+                        expected.remove(expected.iter().position(|&other| other == position).unwrap());
+                    }
+                    1 => {
+                        let spacing = if list.is_empty() { rng.gen_range(0..4) } else { rng.gen_range(0..3) };
+                        let position = list.push(spacing, element).position();
+                        indices.push((list.last().unwrap().index(), position));
+                        expected.push(position);
+                    }
+                    _ => {
+                        let position = rng.gen_range(0..8);
+                        indices.push((list.insert(position, element).index(), position));
+                        expected.push(position);
+                    }
+                }
+                expected.sort();
+                assert_eq!(list.iter().map(|pos| pos.position()).collect_vec(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn reuse_empty_slots() {
+        let mut list: SpacedList<i32, char> = SpacedList::new();
+        let b = list.push(-431, 'b');
+        let c = list.push(131, 'c');
+        let a = list.push(573, 'a');
+        list.push(102, 'd');
+        list.remove(b.index());
+        // the empty first slot is moved forwards
+        list.insert(-400, 'e');
+        list.remove(c.index());
+        // an empty slot is moved forwards
+        list.insert(-200, 'f');
+        list.remove(a.index());
+        // an empty slot is moved backwards
+        list.insert(0, 'g');
+        assert!(list.skeleton.borrow().subs.iter().all(Option::is_none));
+        assert_eq!(list.iter().map(|pos| (pos.position(), pos.element().unwrap())).collect_vec(),
+                   [(-400, 'e'), (-200, 'f'), (0, 'g'), (375, 'd')]);
+    }
+
     #[test]
     fn test() {
         let mut list: SpacedList<i32, char> = SpacedList::new();
@@ -241,6 +329,60 @@ mod tests {
         // println!("{} at {} in {:?}", a.ephemeral().element(), a.ephemeral().index, a.ephemeral().skeleton.borrow().elements);
     }
 
+    #[test]
+    fn many_random_insertions() {
+        let mut list: HollowSpacedList<u32> = HollowSpacedList::new();
+        for _ in 0..200_000 {
+            list.insert(random());
+        }
+        assert_eq!(list.size(), 200_000);
+        assert!(list.iter().map(|pos| pos.position()).is_sorted());
+        drop(list);
+    }
+
+    // takes minutes without optimizations, run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn million_random_insertions() {
+        let mut list: HollowSpacedList<u32> = HollowSpacedList::new();
+        for _ in 0..2_000_000 {
+            list.insert(random());
+        }
+        assert_eq!(list.size(), 2_000_000);
+        assert!(list.iter().map(|pos| pos.position()).is_sorted());
+        drop(list);
+    }
+
+    #[test]
+    fn deep_sub_chain() {
+        // every skeleton has elements at 0 and 2, and a sub at its first link, nested this deep
+        const DEPTH: usize = 100_000;
+        // built from the bottom up, as pushing onto a sub updates the counts of all its ancestors
+        let mut chain = Skeleton::<Node, u32, ()>::new(None);
+        for _ in 0..DEPTH {
+            let skeleton = Skeleton::new(None);
+            Skeleton::<Node, _, _>::try_push(skeleton.clone(), 0, ()).unwrap();
+            Skeleton::<Node, _, _>::try_push(skeleton.clone(), 2, ()).unwrap();
+            if chain.borrow().count() > 0 {
+                chain.borrow_mut().parent_data = Some(ParentData {
+                    parent: Rc::downgrade(&skeleton),
+                    index_in_parent: 0,
+                });
+                let count = chain.borrow().count();
+                skeleton.borrow_mut().subs[0] = Some(chain);
+                skeleton.borrow_mut().increase_count(0, count);
+            }
+            chain = skeleton;
+        }
+        assert_eq!(chain.borrow().count(), 2 * DEPTH);
+        // inserting at 1 goes into the sub at every level, until it reaches the end of the chain
+        let inserted = Skeleton::insert(chain.clone(), 1, ());
+        assert_eq!(inserted.position.position, 1);
+        assert_eq!(chain.borrow().count(), 2 * DEPTH + 1);
+        drop(inserted);
+        drop(chain);
+    }
+
     #[test]
     fn random_structure() {
         let skelly = Skeleton::<Node, u32, ()>::new(None);
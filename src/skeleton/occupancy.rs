@@ -0,0 +1,368 @@
+//! After many removals, a skeleton can consist mostly of empty element slots, and stepping through
+//! them one at a time makes iteration and traversal linear in the number of slots instead of the
+//! number of elements. To avoid this, every skeleton keeps track of which of its indices are
+//! occupied, that is, hold an element or have a sub that holds one (directly or in a sub of its
+//! own). This is stored in a tree of bits, where each bit of a level tells whether any bit in the
+//! corresponding word of the level below is set, so the next or previous occupied index can be
+//! found in logarithmic time.
+//!
+//! Whether an index holds an element is known from its generation (see [`super::index`]), so the
+//! occupancy of an index is updated whenever an element gets a new generation or its generation
+//! ends. When a skeleton becomes occupied or unoccupied, so does the index of its parent that it
+//! is the sub of.
+
+use crate::{EphemeralPosition, ParentData, Skeleton, Spacing};
+use crate::skeleton::index::NO_GENERATION;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+#[derive(Default)]
+pub(crate) struct Occupancy {
+    /// The first level has a bit for every index, and every bit of a level above tells whether
+    /// the corresponding word of the level below has any bit set. The last level has exactly one
+    /// word.
+    levels: Vec<Vec<u64>>,
+    len: usize,
+}
+
+impl Occupancy {
+    /// Returns whether any index is occupied.
+    pub(crate) fn any(&self) -> bool {
+        self.levels.last().is_some_and(|level| level[0] != 0)
+    }
+
+    pub(crate) fn get(&self, index: usize) -> bool {
+        index < self.len && self.levels[0][index / WORD_BITS] >> (index % WORD_BITS) & 1 == 1
+    }
+
+    pub(crate) fn set(&mut self, index: usize, occupied: bool) {
+        if index >= self.len {
+            if !occupied {
+                return;
+            }
+            self.grow(index + 1);
+        }
+        let mut index = index;
+        let mut occupied = occupied;
+        for level in &mut self.levels {
+            let word = &mut level[index / WORD_BITS];
+            let was_empty = *word == 0;
+            if occupied {
+                *word |= 1 << (index % WORD_BITS);
+            } else {
+                *word &= !(1 << (index % WORD_BITS));
+            }
+            // the levels above only need to change if this word became empty or stopped being empty
+            let is_empty = *word == 0;
+            if is_empty == was_empty {
+                break;
+            }
+            occupied = !is_empty;
+            index /= WORD_BITS;
+        }
+    }
+
+    fn grow(&mut self, len: usize) {
+        self.len = len;
+        let mut bits = len;
+        let mut level = 0;
+        loop {
+            let words = bits.div_ceil(WORD_BITS).max(1);
+            if level == self.levels.len() {
+                // a new top level summarizes the previous top level, which had only one word
+                let summary = self.levels.last().map_or(0, |below| (below[0] != 0) as u64);
+                self.levels.push(vec![summary]);
+            }
+            self.levels[level].resize(words, 0);
+            if words == 1 {
+                break;
+            }
+            bits = words;
+            level += 1;
+        }
+    }
+
+    /// Drops all indices from the given one onwards.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        for index in len..self.len {
+            self.set(index, false);
+        }
+        self.len = len;
+        let mut bits = len;
+        let mut level = 0;
+        loop {
+            let words = bits.div_ceil(WORD_BITS).max(1);
+            self.levels[level].truncate(words);
+            if words == 1 {
+                // the levels above only summarized words that were just dropped
+                self.levels.truncate(level + 1);
+                break;
+            }
+            bits = words;
+            level += 1;
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.levels.clear();
+        self.len = 0;
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.levels.shrink_to_fit();
+        for level in &mut self.levels {
+            level.shrink_to_fit();
+        }
+    }
+
+    /// Returns the first occupied index at or after the given one.
+    pub(crate) fn next(&self, from: usize) -> Option<usize> {
+        if from >= self.len {
+            return None;
+        }
+        let mut index = from;
+        let mut level = 0;
+        // go up until a word has a bit set at or after the index
+        let found = loop {
+            let words = &self.levels[level];
+            if index / WORD_BITS >= words.len() {
+                return None;
+            }
+            let word = words[index / WORD_BITS] & (!0 << (index % WORD_BITS));
+            if word != 0 {
+                break index - index % WORD_BITS + word.trailing_zeros() as usize;
+            }
+            level += 1;
+            if level == self.levels.len() {
+                return None;
+            }
+            index = index / WORD_BITS + 1;
+        };
+        // then go back down, always to the first bit that is set
+        let mut index = found;
+        while level > 0 {
+            level -= 1;
+            index = index * WORD_BITS + self.levels[level][index].trailing_zeros() as usize;
+        }
+        Some(index)
+    }
+
+    /// Returns the last occupied index at or before the given one.
+    pub(crate) fn previous(&self, from: usize) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+        let mut index = from.min(self.len - 1);
+        let mut level = 0;
+        // go up until a word has a bit set at or before the index
+        let found = loop {
+            let bit = index % WORD_BITS;
+            let mask = if bit == WORD_BITS - 1 { !0 } else { (1 << (bit + 1)) - 1 };
+            let word = self.levels[level][index / WORD_BITS] & mask;
+            if word != 0 {
+                break index - bit + (WORD_BITS - 1 - word.leading_zeros() as usize);
+            }
+            if index / WORD_BITS == 0 {
+                return None;
+            }
+            level += 1;
+            index = index / WORD_BITS - 1;
+        };
+        // then go back down, always to the last bit that is set
+        let mut index = found;
+        while level > 0 {
+            level -= 1;
+            index = index * WORD_BITS + (WORD_BITS - 1 - self.levels[level][index].leading_zeros() as usize);
+        }
+        Some(index)
+    }
+}
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Returns whether there is an element in this skeleton or any of its subs.
+    pub(crate) fn is_occupied(&self) -> bool {
+        self.occupancy.any()
+    }
+
+    /// Updates whether the given index is occupied, after an element has been placed there or
+    /// removed from there, as well as the occupancy of the indices in the ancestors of this
+    /// skeleton that lead to it.
+    pub(super) fn update_occupancy(&mut self, index: usize) {
+        let occupied = self.generation(index) != NO_GENERATION
+            || self.sub(index).is_some_and(|sub| sub.borrow().is_occupied());
+        let was_occupied = self.is_occupied();
+        self.occupancy.set(index, occupied);
+        if self.is_occupied() == was_occupied {
+            return;
+        }
+        let mut occupied = !was_occupied;
+        let mut parent_data = self.parent_data.as_ref()
+            .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent));
+        while let Some((parent, index)) = parent_data {
+            let mut parent = parent.borrow_mut();
+            let was_occupied = parent.is_occupied();
+            let index_occupied = occupied || parent.generation(index) != NO_GENERATION;
+            parent.occupancy.set(index, index_occupied);
+            if parent.is_occupied() == was_occupied {
+                break;
+            }
+            occupied = !was_occupied;
+            parent_data = parent.parent_data.as_ref()
+                .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent));
+        }
+    }
+}
+
+impl<Kind, S: Spacing, T> EphemeralPosition<Kind, S, T> {
+    pub(crate) fn holds_element(&self) -> bool {
+        self.skeleton.borrow().generation(self.index) != NO_GENERATION
+    }
+
+    /// Returns the next index that holds an element.
+    pub(crate) fn into_next_element(self) -> Option<Self> {
+        self.into_next()?.into_element_at_or_after()
+    }
+
+    /// Returns the previous index that holds an element.
+    pub(crate) fn into_previous_element(self) -> Option<Self> {
+        self.into_previous()?.into_element_at_or_before()
+    }
+
+    /// Returns this index if it holds an element, or the next one that does.
+    pub(crate) fn into_element_at_or_after(self) -> Option<Self> {
+        let mut current = self;
+        while !current.holds_element() {
+            current = current.skip_forwards()?;
+        }
+        Some(current)
+    }
+
+    /// Returns this index if it holds an element, or the previous one that does.
+    pub(crate) fn into_element_at_or_before(self) -> Option<Self> {
+        let mut current = self;
+        while !current.holds_element() {
+            current = current.skip_backwards()?;
+        }
+        Some(current)
+    }
+
+    /// Moves on from this empty index to the next occupied index of its skeleton, into its sub if
+    /// that is occupied, or out of its skeleton if no index after it is occupied.
+    fn skip_forwards(self) -> Option<Self> {
+        let (index, position) = {
+            let skeleton = self.skeleton.borrow();
+            if skeleton.sub(self.index).is_some_and(|sub| sub.borrow().is_occupied()) {
+                drop(skeleton);
+                return self.into_next();
+            }
+            let index = skeleton.occupancy.next(self.index + 1).unwrap_or(skeleton.links.len());
+            let distance = skeleton.shallow_position_of(index) - skeleton.shallow_position_of(self.index);
+            (index, self.position + distance)
+        };
+        let target = Self::new(self.skeleton, index, position);
+        if target.skeleton.borrow().occupancy.get(index) {
+            Some(target)
+        } else {
+            // the end of this skeleton, which has nothing left in it
+            target.into_next()
+        }
+    }
+
+    /// Moves back from this empty index to the previous index of its skeleton that holds an
+    /// element, into the sub of the previous occupied index if that is occupied, or out of its
+    /// skeleton if no index before it is occupied.
+    fn skip_backwards(self) -> Option<Self> {
+        if self.index == 0 {
+            return self.into_previous();
+        }
+        let (index, position) = {
+            let skeleton = self.skeleton.borrow();
+            // the sub of an index comes after the index itself, so if it is occupied, the closest
+            // element is at the end of that sub, which is entered from the index after it
+            let index = match skeleton.occupancy.previous(self.index - 1) {
+                Some(index) if skeleton.sub(index).is_some_and(|sub| sub.borrow().is_occupied()) => index + 1,
+                Some(index) => index,
+                None => 0,
+            };
+            let distance = skeleton.shallow_position_of(self.index) - skeleton.shallow_position_of(index);
+            (index, self.position - distance)
+        };
+        let target = Self::new(self.skeleton, index, position);
+        if target.holds_element() {
+            Some(target)
+        } else {
+            target.into_previous()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{HollowRangeSpacedList, SpacedList};
+    use super::Occupancy;
+
+    #[test]
+    fn next_and_previous() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut occupancy = Occupancy::default();
+        let mut expected = vec![false; 10_000];
+        for _ in 0..2_000 {
+            let index = rng.gen_range(0..expected.len());
+            let occupied = rng.gen_bool(0.3);
+            occupancy.set(index, occupied);
+            expected[index] = occupied;
+        }
+        occupancy.truncate(9_000);
+        expected.truncate(9_000);
+        for from in 0..expected.len() {
+            assert_eq!(occupancy.get(from), expected[from]);
+            assert_eq!(occupancy.next(from), (from..expected.len()).find(|&index| expected[index]));
+            assert_eq!(occupancy.previous(from), (0..=from).rev().find(|&index| expected[index]));
+        }
+        assert_eq!(occupancy.any(), expected.contains(&true));
+    }
+
+    #[test]
+    fn mostly_empty() {
+        let mut list: SpacedList<u64, u64> = SpacedList::new();
+        let indices = (0..50_000).map(|i| list.push(1, i).index()).collect_vec();
+        for (i, index) in indices.into_iter().enumerate() {
+            if i % 10_000 != 5 {
+                list.remove(index);
+            }
+        }
+        let expected = [5, 10_005, 20_005, 30_005, 40_005];
+        assert_eq!(list.iter().map(|pos| pos.element().unwrap()).collect_vec(), expected);
+        assert_eq!(list.iter_backwards().map(|pos| pos.element().unwrap()).collect_vec(),
+                   expected.into_iter().rev().collect_vec());
+        assert_eq!(list.first().unwrap().element().unwrap(), 5);
+        assert_eq!(list.last().unwrap().element().unwrap(), 40_005);
+        assert_eq!(list.after(6).unwrap().element().unwrap(), 10_005);
+        assert_eq!(list.before(40_005).unwrap().element().unwrap(), 30_005);
+        assert_eq!(list.at_or_after(20_000).unwrap().element().unwrap(), 20_005);
+        assert!(list.at(20_000).is_none());
+        assert_eq!(list.first().unwrap().into_next().unwrap().element().unwrap(), 10_005);
+    }
+
+    #[test]
+    fn reuse_empty_ranges() {
+        let mut list: HollowRangeSpacedList<u64> = HollowRangeSpacedList::new();
+        list.insert(0, 2);
+        let b = list.insert(10, 12);
+        list.insert(20, 22);
+        list.remove(b.index());
+        let c = list.insert(14, 16);
+        // the empty range of b is reused instead of putting c into a sub
+        assert!(c.skeleton.borrow().parent_data.is_none());
+        list.insert(5, 7);
+        assert_eq!(list.iter_ranges().map(|(start, end)| (start.position(), end.position())).collect_vec(),
+                   [(0, 2), (5, 7), (14, 16), (20, 22)]);
+    }
+}
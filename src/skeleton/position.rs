@@ -8,14 +8,15 @@ use itertools::Itertools;
 use maybe_owned::MaybeOwned;
 
 use crate::{BackwardsIter, BoundType, ElementRef, ElementRefMut, EphemeralPosition, ForwardsIter,
-            HollowIndex, Index, RangeKind, Skeleton, Spacing};
+            HollowIndex, Index, RangeKind, Skeleton, Spacing, StaleIndexError};
 
 macro_rules! position {
     ($name:ident; <Kind, S: Spacing$(, $T:ident)?>; $type:ty; $skeleton:ty) => {
         pub struct $name<Kind, S: Spacing$(, $T)?> {
             // TODO store an Index / a HollowIndex instead (for EphemeralPosition, an EphemeralIndex)
             pub(crate) skeleton: Rc<RefCell<$skeleton>>,
-            pub(crate) index: isize,
+            pub(crate) index: usize,
+            pub(crate) generation: usize,
             pub(crate) position: S,
         }
 
@@ -24,38 +25,28 @@ macro_rules! position {
                 Self {
                     skeleton: self.skeleton.clone(),
                     index: self.index,
+                    generation: self.generation,
                     position: self.position,
                 }
             }
         }
 
         impl<Kind, S: Spacing$(, $T)?> $type {
-            pub(crate) fn new(skeleton: Rc<RefCell<$skeleton>>, index: isize, position: S) -> Self {
+            pub(crate) fn new(skeleton: Rc<RefCell<$skeleton>>, index: usize, generation: usize, position: S) -> Self {
                 Self {
                     skeleton,
                     index,
+                    generation,
                     position,
                 }
             }
 
             pub(crate) fn at_start(skeleton: Rc<RefCell<$skeleton>>) -> Self {
-                let index = skeleton.borrow().first_persistent_index;
-                let position = skeleton.borrow().offset;
-                Self {
-                    skeleton,
-                    index,
-                    position,
-                }
+                EphemeralPosition::at_start(skeleton).into()
             }
 
             pub(crate) fn at_end(skeleton: Rc<RefCell<$skeleton>>) -> Self {
-                let index = (skeleton.borrow().elements.len() - 1) as isize;
-                let position = skeleton.borrow().last_position();
-                Self {
-                    skeleton,
-                    index,
-                    position,
-                }
+                EphemeralPosition::at_end(skeleton).into()
             }
 
             pub fn position(&self) -> S {
@@ -79,17 +70,17 @@ macro_rules! position {
             }
 
             pub fn into_next(self) -> Option<Self> {
-                self.ephemeral().into_next().map(|ephemeral| ephemeral.persistent().into())
+                self.ephemeral().into_next_element().map(|ephemeral| ephemeral.persistent().into())
             }
 
             pub fn into_previous(self) -> Option<Self> {
-                self.ephemeral().into_previous().map(|ephemeral| ephemeral.persistent().into())
+                self.ephemeral().into_previous_element().map(|ephemeral| ephemeral.persistent().into())
             }
         }
 
         impl<Kind: RangeKind, S: Spacing$(, $T)?> $name<Kind, S$(, $T)?> {
             pub fn bound_type(&self) -> BoundType {
-                BoundType::of_signed(self.index)
+                BoundType::of(self.index)
             }
 
             pub fn span(&self) -> S {
@@ -102,6 +93,7 @@ macro_rules! position {
                         let end = Self::new(
                             self.skeleton.clone(),
                             self.index + 1,
+                            self.generation,
                             self.position + self.span());
                         (self, end)
                     }
@@ -109,18 +101,20 @@ macro_rules! position {
                         let start = Self::new(
                             self.skeleton.clone(),
                             self.index - 1,
+                            self.generation,
                             self.position - self.span());
                         (start, self)
                     }
                 }
             }
 
-            pub fn range(&self) -> (MaybeOwned<Self>, MaybeOwned<Self>) {
+            pub fn range(&self) -> (MaybeOwned<'_, Self>, MaybeOwned<'_, Self>) {
                 match self.bound_type() {
                     BoundType::Start => {
                         let end = Self::new(
                             self.skeleton.clone(),
                             self.index + 1,
+                            self.generation,
                             self.position + self.span());
                         (self.into(), end.into())
                     }
@@ -128,6 +122,7 @@ macro_rules! position {
                         let start = Self::new(
                             self.skeleton.clone(),
                             self.index - 1,
+                            self.generation,
                             self.position - self.span());
                         (start.into(), self.into())
                     }
@@ -169,19 +164,31 @@ impl<Kind, S: Spacing, T> Position<Kind, S, T> {
     }
 
     pub(crate) fn ephemeral(&self) -> EphemeralPosition<Kind, S, T> {
-        self.skeleton.borrow().from_persistent.get(&self.index).cloned()
-            .map_or(
-                EphemeralPosition::new(self.skeleton.clone(), self.index as usize, self.position),
-                |index| EphemeralPosition::new(index.skeleton, index.index, self.position),
-            )
+        let index = self.index().ephemeral();
+        EphemeralPosition::new(index.skeleton, index.index, self.position)
+    }
+
+    /// Returns whether the element at this position is still in its spaced list.
+    pub fn is_valid(&self) -> bool {
+        self.index().is_valid()
+    }
+
+    pub fn try_element(&self) -> Result<ElementRef<Kind, S, T>, StaleIndexError> {
+        self.index().try_element()
+    }
+
+    /// Returns the current position of the element, which may differ from [`Self::position`] if
+    /// spacings have been changed since this position was created.
+    pub fn try_position(&self) -> Result<S, StaleIndexError> {
+        Ok(self.index().try_position()?.position)
     }
 
     pub fn into_index(self) -> Index<Kind, S, T> {
-        Index::new(self.skeleton, self.index)
+        Index::new(self.skeleton, self.index, self.generation)
     }
 
     pub fn index(&self) -> Index<Kind, S, T> {
-        Index::new(self.skeleton.clone(), self.index)
+        Index::new(self.skeleton.clone(), self.index, self.generation)
     }
 }
 
@@ -199,24 +206,41 @@ impl<Kind, S: Spacing> HollowPosition<Kind, S> {
         position.ephemeral()
     }
 
+    /// Returns whether the element at this position is still in its spaced list.
+    pub fn is_valid(&self) -> bool {
+        self.index().is_valid()
+    }
+
+    /// Hollow spaced lists don't store elements, so this only checks that the element at this
+    /// position is still in its spaced list.
+    pub fn try_element(&self) -> Result<(), StaleIndexError> {
+        self.index().try_element()
+    }
+
+    /// Returns the current position of the element, which may differ from [`Self::position`] if
+    /// spacings have been changed since this position was created.
+    pub fn try_position(&self) -> Result<S, StaleIndexError> {
+        Ok(self.index().try_position()?.position)
+    }
+
     pub fn into_index(self) -> HollowIndex<Kind, S> {
-        HollowIndex::new(self.skeleton, self.index)
+        HollowIndex::new(self.skeleton, self.index, self.generation)
     }
 
     pub fn index(&self) -> HollowIndex<Kind, S> {
-        HollowIndex::new(self.skeleton.clone(), self.index)
+        HollowIndex::new(self.skeleton.clone(), self.index, self.generation)
     }
 }
 
 impl<Kind, S: Spacing> From<Position<Kind, S, ()>> for HollowPosition<Kind, S> {
     fn from(position: Position<Kind, S, ()>) -> Self {
-        Self::new(position.skeleton, position.index, position.position)
+        Self::new(position.skeleton, position.index, position.generation, position.position)
     }
 }
 
 impl<Kind, S: Spacing> From<HollowPosition<Kind, S>> for Position<Kind, S, ()> {
     fn from(position: HollowPosition<Kind, S>) -> Self {
-        Self::new(position.skeleton, position.index, position.position)
+        Self::new(position.skeleton, position.index, position.generation, position.position)
     }
 }
 
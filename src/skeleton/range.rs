@@ -1,11 +1,11 @@
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::mem;
 use std::rc::Rc;
 use num_traits::zero;
 use thiserror::Error;
 
-use crate::{ElementSlot, BoundType, EphemeralIndex, EphemeralPosition, Index, Range, Skeleton, Spacing};
+use crate::{ElementSlot, BoundType, EphemeralIndex, EphemeralPosition, Range, Skeleton, Spacing};
+use crate::skeleton::insertion::{Displaced, Inserted, InsertionStep};
 
 #[derive(Error, Debug)]
 pub enum RangePushError {
@@ -36,9 +36,22 @@ impl<S: Spacing, T> Skeleton<Range, S, T> {
             // cannot fail because we would have returned with an Err already if span were < 0
             this.borrow_mut().increase_spacing(0, span);
             this.borrow_mut().elements.push(ElementSlot::Some(element));
+            this.borrow_mut().new_generation(0, 2);
             Ok(EphemeralPosition::new(this, 0, distance))
         } else if distance < zero() {
             Err(RangePushError::NegativeDistanceInNonEmptyList)
+        } else if !this.borrow().has_room() && this.borrow().parent_data.is_some() {
+            let hyper = Self::into_hyper(this);
+            Self::try_push(hyper, distance, span, element)
+        } else if this.borrow().hyper {
+            let (sub, sub_position, distance) = Self::hyper_push_target(this.clone(), distance);
+            // cannot fail, because distance and span are non-negative
+            let result = Self::try_push(sub, distance, span, element).unwrap();
+            this.borrow_mut().sync_last_link();
+            Ok(EphemeralPosition {
+                position: sub_position + result.position,
+                ..result
+            })
         } else {
             // last element slot must always be full => no need to handle the case that it's empty
 
@@ -50,6 +63,7 @@ impl<S: Spacing, T> Skeleton<Range, S, T> {
             // cannot fail because we would have returned with an Err already if span were < 0
             this.borrow_mut().increase_spacing(span_index, span);
             this.borrow_mut().elements.push(ElementSlot::Some(element));
+            this.borrow_mut().new_generation(span_index, 2);
             Ok(EphemeralPosition::new(this, span_index, start_position))
         }
     }
@@ -58,7 +72,7 @@ impl<S: Spacing, T> Skeleton<Range, S, T> {
     //    __3__   __2__
     //  4 | 3 | 2 | 2 |
     //      A       B                  A.0 is now at index 0
-    //                                 persistent index of A.0 = 0, of A.1 = 1
+    //                                 persistent index of A.0 = (0, gen 0), of A.1 = (1, gen 0)
     //
     // insert C at 1 with span 2:
     // replace old value with new value
@@ -66,7 +80,7 @@ impl<S: Spacing, T> Skeleton<Range, S, T> {
     //    __3__   __2__
     //  4 | 3 | 2 | 2 |
     //      C       B                  C.0 is now at index 0 (ike)
-    // adjust spacings                 persistent index of C.0 = -2, of C.1 = -1
+    // adjust spacings                 persistent index of C.0 = (0, gen 2), of C.1 = (1, gen 2)
     //    ____8____
     //    __2__   __2__
     //  1 | 2 | 6 | 2 |
@@ -93,28 +107,57 @@ impl<S: Spacing, T> Skeleton<Range, S, T> {
     //  1 | 2 | 6 | 2 |
     //      C       B
     //        sub:                     persistent indices:
-    //           __3__                 A.0 = +0  A.1 = +1
-    //         1 | 3 |                 B.0 = +2  B.1 = +3
-    //             A                   C.0 = -2  C.1 = -1
+    //           __3__                 A.0 = (0, gen 0)  A.1 = (1, gen 0)  (moved into the sub)
+    //         1 | 3 |                 B.0 = (2, gen 1)  B.1 = (3, gen 1)
+    //             A                   C.0 = (0, gen 2)  C.1 = (1, gen 2)
     pub(crate) fn try_insert(this: Rc<RefCell<Self>>, position: S, span: S, element: T)
-                             -> Result<EphemeralPosition<Range, S, T>, RangeInsertionError> {
+                             -> Result<Inserted<Range, S, T>, RangeInsertionError> {
+        Self::insert_iteratively(this, position, (span, element), 2, |skeleton, position, (span, element)|
+            Self::insertion_step(skeleton, position, span, element))
+    }
+
+    fn insertion_step(this: Rc<RefCell<Self>>, position: S, span: S, element: T)
+                      -> Result<InsertionStep<Range, S, T, (S, T)>, RangeInsertionError> {
         if span < zero() {
             Err(RangeInsertionError::NegativeSpan)
         } else if this.borrow().elements.is_empty() {
             // we checked that span is non-negative, so NegativeSpan can't occur, and
             // we checked that the list is empty, so NegativeDistanceInNonEmptyList can't occur
             // so this cannot fail
-            Ok(Self::try_push(this, position, span, element).unwrap())
-        } else if position < this.borrow().offset {
-            let previous_first_position = this.borrow().offset;
-            let previous_first_span = this.borrow().links[0];
-
-            if position + span > previous_first_position {
+            Ok(InsertionStep::Placed(Self::try_push(this, position, span, element).unwrap().into_index()))
+        } else if position >= this.borrow().last_position() {
+            let distance = position - this.borrow().last_position();
+            // we checked that span is non-negative, so NegativeSpan can't occur, and
+            // distance cannot be negative either (see its definition and the line above)
+            // so this cannot fail
+            Ok(InsertionStep::Placed(Self::try_push(this, distance, span, element).unwrap().into_index()))
+        } else if this.borrow().hyper {
+            if position < this.borrow().offset {
+                if position + span > this.borrow().offset {
+                    return Err(RangeInsertionError::RangeIntersectsExistingRange);
+                }
+                Self::move_hyper_start(this.clone(), position);
+            }
+            let (sub, sub_position, next_sub_position) = Self::hyper_sub_at_or_before(this, position);
+            if position + span > next_sub_position {
+                return Err(RangeInsertionError::RangeIntersectsExistingRange);
+            }
+            Ok(InsertionStep::IntoSub(sub, position - sub_position, (span, element)))
+        } else if position < this.borrow().offset
+            // a range without span fits right before a range starting at the same position
+            || position == this.borrow().offset && span == zero() && this.borrow().links[0] > zero() {
+            let offset = this.borrow().offset;
+            if position + span > offset && !this.borrow_mut().move_empty_ranges(0, offset, position + span) {
                 return Err(RangeInsertionError::RangeIntersectsExistingRange);
             }
 
+            let previous_first_index = EphemeralIndex::new(this.clone(), 0).persistent();
+            let previous_first_position = this.borrow().offset;
+            let previous_first_span = this.borrow().links[0];
+
             let previous_first_element_slot =
-                mem::replace(&mut this.borrow_mut().elements[0], ElementSlot::Some(element));
+                this.borrow_mut().elements[0].replace(element);
+            this.borrow_mut().new_generation(0, 2);
 
             this.borrow_mut().offset = position;
             match span.cmp(&previous_first_span) {
@@ -151,61 +194,66 @@ impl<S: Spacing, T> Skeleton<Range, S, T> {
              */
             this.borrow_mut().increase_spacing_after_index(1, (previous_first_position + previous_first_span) - (position + span));
 
-            if let ElementSlot::Some(previous_first_element) = previous_first_element_slot {
-                // reinsert old element
-
-                // cannot fail, because we made enough space
-                let insertion_index = Self::try_insert(
-                    this.clone(),
-                    previous_first_position,
-                    previous_first_span,
-                    previous_first_element,
-                ).unwrap().into_index();
-
-                let first_persistent_index = this.borrow().first_persistent_index;
-                this.borrow_mut().from_persistent.insert(first_persistent_index, insertion_index.clone());
-                this.borrow_mut().from_persistent.insert(first_persistent_index + 1, insertion_index.into_range().1);
+            let first_index = EphemeralIndex::new(this, 0);
+            match previous_first_element_slot {
+                // the old element needs to be reinserted, which cannot fail, because we made enough
+                // space
+                ElementSlot::Some(previous_first_element) => Ok(InsertionStep::Displaced(first_index, Displaced {
+                    index: previous_first_index,
+                    position: previous_first_position,
+                    element: (previous_first_span, previous_first_element),
+                })),
+                ElementSlot::None => Ok(InsertionStep::Placed(first_index)),
             }
-
-            this.borrow_mut().first_persistent_index -= 2;
-            let first_persistent_index = this.borrow().first_persistent_index;
-
-            let first_index = EphemeralIndex::new(this.clone(), 0);
-            this.borrow_mut().from_persistent.insert(first_persistent_index, first_index.clone());
-            this.borrow_mut().from_persistent.insert(first_persistent_index + 1, first_index.into_range().1);
-
-            let first_index = Index::new(this.clone(), first_persistent_index);
-            this.borrow_mut().into_persistent.insert(0, first_index.clone());
-            this.borrow_mut().into_persistent.insert(1, first_index.into_range().1);
-
-            Ok(EphemeralPosition::new(this, 0, position))
-        } else if position >= this.borrow().last_position() {
-            let distance = position - this.borrow().last_position();
-            // we checked that span is non-negative, so NegativeSpan can't occur, and
-            // distance cannot be negative either (see its definition and the line above)
-            // so this cannot fail
-            Ok(Self::try_push(this, distance, span, element).unwrap())
         } else {
-            let result = Self::shallow_at_or_before(this.clone(), position).unwrap();
+            let mut result = Self::shallow_at_or_before(this.clone(), position).unwrap();
+            if BoundType::of(result.index) == BoundType::Start && result.position == position && span == zero() {
+                // a range without span fits right before a range starting at the same position, so
+                // it is inserted after the end of the range before that one (the first range of
+                // this skeleton can't start at the position, as that case is handled above)
+                let index = result.index - 1;
+                let end_position = position - this.borrow().link(index);
+                result = EphemeralPosition::new(this.clone(), index, end_position);
+            }
             if BoundType::of(result.index) == BoundType::Start {
                 return Err(RangeInsertionError::RangeStartsInsideExistingRange);
             }
+            let reused = this.borrow_mut().reuse_empty_range(result.index, result.position, position, span);
+            if let Some(start) = reused {
+                this.borrow_mut().elements[start / 2] = ElementSlot::Some(element);
+                this.borrow_mut().new_generation(start, 2);
+                return Ok(InsertionStep::Placed(EphemeralIndex::new(this, start)));
+            }
+            // a range that starts before the end of the sub after the bound has to go into it, which
+            // can still fail, so nothing may be moved to make room for it before
+            if this.borrow().sub(result.index).is_some_and(|sub| position < result.position + sub.borrow().last_position()) {
+                let sub = Self::ensure_sub(this, result.index);
+                return Ok(InsertionStep::IntoSub(sub, position - result.position, (span, element)));
+            }
             let space_between = this.borrow().link(result.index);
             // I have some doubts if this should be > or >=
-            if position + span > result.position + space_between {
+            if position + span > result.position + space_between
+                && !this.borrow_mut().move_empty_ranges(result.index + 1, result.position + space_between, position + span) {
                 return Err(RangeInsertionError::RangeIntersectsExistingRange);
             }
+            if let Some(spacings) = Self::splice_spacings(&this, &result, position, span) {
+                return Ok(InsertionStep::Placed(Self::splice(&this, result.index, &spacings, element)));
+            }
             let sub = Self::ensure_sub(this, result.index);
-            Ok(EphemeralPosition {
-                position,
-                ..Self::try_insert(sub, position - result.position, span, element)?
-            })
+            Ok(InsertionStep::IntoSub(sub, position - result.position, (span, element)))
         }
     }
+
+    pub(crate) fn remove(this: Rc<RefCell<Self>>, index: EphemeralIndex<Range, S, T>) -> Option<T> {
+        // both bounds of a range share one element slot, so they are removed together
+        Self::remove_element(this, index.index / 2, 2)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
+
     use crate::RangeSpacedList;
 
     #[test]
@@ -229,4 +277,19 @@ mod tests {
 
         // println!("{} at {} in {:?}", a.ephemeral().element(), a.ephemeral().index, a.ephemeral().skeleton.borrow().elements);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn zero_width_range_before_range_at_same_position() {
+        let mut list: RangeSpacedList<i32, char> = RangeSpacedList::new();
+        list.insert(-42, -42, 'a');
+        list.insert(-42, -41, 'b');
+        list.insert(-36, -34, 'c');
+        list.insert(-20, -20, 'd');
+        // the front insertion displaces a, which has to fit right before b again
+        list.insert(-44, -44, 'e');
+        assert_eq!(list.iter_ranges().map(|(start, end)| (start.position(), end.position(), start.element().unwrap()))
+                       .collect_vec(),
+                   [(-44, -44, 'e'), (-42, -42, 'a'), (-42, -41, 'b'), (-36, -34, 'c'), (-20, -20, 'd')]);
+        assert!(list.try_insert(-41, -41, 'f').is_ok());
+    }
+}
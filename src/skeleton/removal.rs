@@ -0,0 +1,412 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use num_traits::zero;
+use thiserror::Error;
+
+use crate::{ParentData, Skeleton, Spacing};
+
+#[derive(Error, Debug)]
+pub enum RemovalError {
+    #[error("Cannot remove an element that has already been removed.")]
+    ElementAlreadyRemoved,
+    #[error("Cannot remove an element of another list.")]
+    ElementOfOtherList,
+}
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Empties the element slot at the given index and cleans up afterwards: trailing empty slots
+    /// are dropped (so that the last element slot is full again) and subs that no longer contain
+    /// anything are detached from their parent.
+    ///
+    /// `links_per_element` is the number of links that belong to each element slot (1 for nodes,
+    /// 2 for ranges, as each range has a span and a spacing before it).
+    pub(super) fn remove_element(this: Rc<RefCell<Self>>, element_index: usize, links_per_element: usize)
+                                 -> Option<T> {
+        let element = this.borrow_mut().elements.get_mut(element_index)?.take()?;
+        Self::end_generation(&this, element_index * links_per_element, links_per_element);
+        let ranges = links_per_element == 2;
+        if ranges {
+            this.borrow_mut().collapse_range(element_index * 2);
+        }
+        let mut skeleton = this;
+        loop {
+            skeleton.borrow_mut().trim(links_per_element);
+            let parent_data = skeleton.borrow().parent_data.as_ref()
+                .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent));
+            match parent_data {
+                None => {
+                    let mut root = skeleton.borrow_mut();
+                    if root.is_vacant() {
+                        root.hyper = false;
+                        root.clear();
+                    }
+                    break;
+                }
+                Some((parent, index_in_parent)) if parent.borrow().hyper => {
+                    // hypers can't have gaps in their subs, so vacant subs are only dropped at the end
+                    if index_in_parent + 1 != parent.borrow().subs.len() {
+                        break;
+                    }
+                    parent.borrow_mut().trim_hyper();
+                    skeleton = parent;
+                }
+                Some((parent, index_in_parent)) => {
+                    let vacant = skeleton.borrow().is_vacant();
+                    if vacant {
+                        skeleton.borrow_mut().clear();
+                        parent.borrow_mut().subs[index_in_parent] = None;
+                    }
+                    // an empty range that contains inner ranges shrinks along with them
+                    let outer_range_is_empty = ranges && index_in_parent % 2 == 0
+                        && parent.borrow().elements[index_in_parent / 2].is_none();
+                    if outer_range_is_empty {
+                        parent.borrow_mut().collapse_range(index_in_parent);
+                    }
+                    // a parent whose last element slot is empty ends where its last sub does (see
+                    // trim), which may just have moved
+                    let parent_ends_in_sub = parent.borrow().elements.last().is_some_and(Option::is_none);
+                    if !vacant && !outer_range_is_empty && !parent_ends_in_sub {
+                        break;
+                    }
+                    skeleton = parent;
+                }
+            }
+        }
+        Some(element)
+    }
+
+    /// Shrinks the empty range starting at the given index as far as possible, so that it gets in
+    /// the way of new ranges as little as possible: to zero width at its start, or, if it has a
+    /// sub containing inner ranges, to the extent of that sub.
+    fn collapse_range(&mut self, start: usize) {
+        let end = start + 1;
+        let span = self.link(start);
+        match self.sub(start) {
+            None => {
+                self.decrease_spacing(start, span);
+                self.increase_spacing_after_index(end, span);
+            }
+            Some(sub) => {
+                let offset = sub.borrow().offset;
+                // the start of a skeleton can't move if it is the sub of a hyper
+                if start > 0 || !self.is_sub_of_hyper() {
+                    if start == 0 {
+                        self.offset += offset;
+                    } else {
+                        self.increase_spacing(start - 1, offset);
+                    }
+                    self.decrease_spacing(start, offset);
+                    sub.borrow_mut().offset = zero();
+                }
+                let excess = self.link(start) - sub.borrow().last_position();
+                self.decrease_spacing(start, excess);
+                self.increase_spacing_after_index(end, excess);
+            }
+        }
+    }
+
+    /// Moves the empty ranges that start at or after the given start index (which is at the given
+    /// position) and before `end_position` to `end_position`, so that a range ending there can be
+    /// inserted right before the start index. This is only possible if those ranges have been
+    /// collapsed to zero width and nothing else is in the way, except for more such ranges at the
+    /// start of a sub, which are moved as well. Returns whether it succeeded.
+    pub(super) fn move_empty_ranges(&mut self, start: usize, position: S, end_position: S) -> bool {
+        let mut moved = vec![];
+        let mut index = start;
+        let mut position = position;
+        while position < end_position {
+            if index >= self.links.len() || self.elements[index / 2].is_some()
+                || self.link(index) != zero() || self.sub(index).is_some() {
+                return false;
+            }
+            moved.push((index, position));
+            let end = index + 1;
+            if end == self.links.len() {
+                // moving the last range would make this skeleton longer, which subs can't do
+                if self.parent_data.is_some() {
+                    return false;
+                }
+                break;
+            }
+            if let Some(sub) = self.sub(end) {
+                // the sub is positioned relative to the end of the range, which is at the same
+                // position as its start, as the range has zero width
+                let sub_offset = sub.borrow().offset;
+                if position + sub_offset < end_position
+                    && !sub.borrow_mut().move_empty_ranges(0, sub_offset, end_position - position) {
+                    return false;
+                }
+                break;
+            }
+            position += self.link(end);
+            index += 2;
+        }
+        for (index, position) in moved.into_iter().rev() {
+            let change = end_position - position;
+            if index == 0 {
+                self.offset += change;
+            } else {
+                self.increase_spacing(index - 1, change);
+            }
+            self.decrease_spacing_after_index(index + 1, change);
+        }
+        true
+    }
+
+    /// Tries to make room for a range at the given position and with the given span by reusing an
+    /// empty range right next to the given end index, which is at the given position: either the
+    /// range that ends there, or the one after it. This is only possible if that range has been
+    /// collapsed to zero width, has no subs around it that would move along with it, and the new
+    /// range fits before the index after it. Returns the start index of the reused range, whose
+    /// element slot can then be filled.
+    pub(super) fn reuse_empty_range(&mut self, end: usize, end_position: S, position: S, span: S) -> Option<usize> {
+        let new_end_position = position + span;
+        for start in [end.checked_sub(1)?, end + 1] {
+            if start + 1 >= self.links.len() || self.elements[start / 2].is_some() || self.link(start) != zero()
+                || (start > 0 && self.sub(start - 1).is_some())
+                || self.sub(start).is_some() || self.sub(start + 1).is_some() {
+                continue;
+            }
+            let range_position = if start < end { end_position } else { end_position + self.link(end) };
+            if new_end_position > range_position + self.link(start + 1) {
+                continue;
+            }
+            // move the start, then the end, and then move the index after the range back to where it was
+            if start == 0 {
+                // the start of a skeleton can't move if it is the sub of a hyper
+                if position != range_position && self.is_sub_of_hyper() {
+                    continue;
+                }
+                self.offset += position - range_position;
+            } else if position > range_position {
+                self.increase_spacing(start - 1, position - range_position);
+            } else {
+                self.decrease_spacing(start - 1, range_position - position);
+            }
+            self.increase_spacing(start, span);
+            if new_end_position > range_position {
+                self.decrease_spacing(start + 1, new_end_position - range_position);
+            } else {
+                self.increase_spacing(start + 1, range_position - new_end_position);
+            }
+            return Some(start);
+        }
+        None
+    }
+
+    pub(super) fn is_sub_of_hyper(&self) -> bool {
+        self.parent_data.as_ref()
+            .is_some_and(|ParentData { parent, .. }| parent.upgrade().unwrap().borrow().hyper)
+    }
+
+    /// Returns whether this skeleton contains nothing but a single empty element slot, or is a
+    /// hyper that contains nothing but a single vacant sub.
+    fn is_vacant(&self) -> bool {
+        if self.hyper {
+            return self.links.len() == 1 && self.last_sub().borrow().is_vacant();
+        }
+        self.elements.len() == 1 && self.elements[0].is_none() && self.subs.iter().all(Option::is_none)
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.links.clear();
+        self.elements.clear();
+        self.subs.clear();
+        // next_generation and moved are kept, as indices into this skeleton may still be around
+        let origins = self.origins.keys().copied().collect::<Vec<_>>();
+        for index in origins {
+            self.forget_origin(index);
+        }
+        self.generations.clear();
+        self.occupancy.clear();
+        self.counts.clear();
+        self.offset = zero();
+        self.length = zero();
+        self.depth = 0;
+    }
+
+    fn pop_link(&mut self) {
+        let link = self.link(self.links.len() - 1);
+        self.length -= link;
+        if self.links.len().is_power_of_two() {
+            self.depth -= 1;
+        }
+        self.links.pop();
+        self.subs.pop();
+        self.forget_origin(self.links.len() + 1);
+        self.generations.truncate(self.links.len() + 1);
+        self.occupancy.truncate(self.links.len() + 1);
+        self.counts.truncate(self.links.len() + 1);
+    }
+
+    /// Drops empty element slots from the end of this skeleton. If the links of such a slot have a
+    /// sub, the slot can't be dropped without losing the sub, so it is moved to the end of the
+    /// sub instead.
+    fn trim(&mut self, links_per_element: usize) {
+        while !self.hyper && self.elements.len() > 1 && self.elements.last().unwrap().is_none() {
+            let first_link_index = self.links.len() - links_per_element;
+            let last_sub_index = (first_link_index..self.links.len()).rev()
+                .find(|&index| self.subs[index].is_some());
+            match last_sub_index {
+                None => {
+                    for _ in 0..links_per_element {
+                        self.pop_link();
+                    }
+                    self.elements.pop();
+                }
+                Some(index) => {
+                    let sub_end = self.sub(index).unwrap().borrow().last_position();
+                    let link = self.link(index);
+                    // subs always end at or before the end of their link
+                    self.decrease_spacing(index, link - sub_end);
+                    for index in index + 1..self.links.len() {
+                        let link = self.link(index);
+                        self.decrease_spacing(index, link);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Drops vacant subs from the end of this hyper, and makes sure its last link still spans
+    /// exactly its last sub.
+    fn trim_hyper(&mut self) {
+        while self.links.len() > 1 && self.last_sub().borrow().is_vacant() {
+            self.pop_link();
+            self.elements.pop();
+        }
+        self.sync_last_link();
+    }
+
+    /// Trims the given skeleton and the subs at its end, deepest first. Removals only trim
+    /// skeletons up to the first one that is followed by more elements, so a skeleton that comes
+    /// to be at the end of a list otherwise (by splitting the list) can still end in empty slots.
+    pub(super) fn trim_end(this: &Rc<RefCell<Self>>, links_per_element: usize) {
+        if this.borrow().hyper {
+            loop {
+                this.borrow_mut().trim_hyper();
+                let last_sub = this.borrow().last_sub();
+                Self::trim_end(&last_sub, links_per_element);
+                if !last_sub.borrow().is_vacant() || this.borrow().links.len() == 1 {
+                    this.borrow_mut().sync_last_link();
+                    return;
+                }
+            }
+        }
+        // the last sub is only at the end if all element slots after its link are empty
+        let last_sub = {
+            let skeleton = this.borrow();
+            (0..skeleton.links.len()).rev()
+                .find(|&index| skeleton.subs[index].is_some())
+                .filter(|&index| skeleton.elements[(index + 1) / links_per_element..].iter().all(Option::is_none))
+        };
+        if let Some(index) = last_sub {
+            let sub = this.borrow().sub(index).unwrap();
+            Self::trim_end(&sub, links_per_element);
+            if sub.borrow().is_vacant() {
+                sub.borrow_mut().clear();
+                this.borrow_mut().subs[index] = None;
+            }
+        }
+        this.borrow_mut().trim(links_per_element);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{NestedRangeSpacedList, RangeSpacedList, SpacedList};
+
+    #[test]
+    fn end_follows_removals() {
+        for seed in 0..2_000 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: SpacedList<i32, i32> = SpacedList::new();
+            let mut elements = vec![];
+            for element in 0..60 {
+                if rng.gen_bool(0.4) && !elements.is_empty() {
+                    let (index, _) = elements.swap_remove(rng.gen_range(0..elements.len()));
+                    list.remove(index);
+                } else if rng.gen_bool(0.3) {
+                    let position = list.push(rng.gen_range(0..4), element);
+                    elements.push((position.index(), position.position()));
+                } else {
+                    let position = rng.gen_range(0..30);
+                    elements.push((list.insert(position, element).index(), position));
+                }
+                if let Some(end) = elements.iter().map(|&(_, position)| position).max() {
+                    assert_eq!(list.end(), end);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn end_follows_removals_from_appended_lists() {
+        for seed in 0..1_000 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: SpacedList<i32, i32> = SpacedList::new();
+            let mut indices = vec![];
+            for element in 0..40 {
+                if rng.gen_bool(0.5) && !indices.is_empty() {
+                    list.remove(indices.swap_remove(rng.gen_range(0..indices.len())));
+                } else {
+                    // appending lists that were appended to themselves nests hypers
+                    let mut other = SpacedList::new();
+                    for _ in 0..rng.gen_range(1..3) {
+                        let mut part = SpacedList::new();
+                        indices.push(part.push(rng.gen_range(0..3), element).index());
+                        other.append(&mut part, rng.gen_range(0..3));
+                    }
+                    list.append(&mut other, rng.gen_range(0..3));
+                }
+                if let Some(end) = indices.iter().map(|index| index.position().position()).max() {
+                    assert_eq!(list.end(), end);
+                }
+            }
+        }
+    }
+
+    macro_rules! end_follows_range_removals {
+        ($name:ident, $list:ident) => {
+            #[test]
+            fn $name() {
+                for seed in 0..1_000 {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    let mut list: $list<i32, i32> = $list::new();
+                    // the ends of the ranges in the list
+                    let mut ranges = vec![];
+                    for element in 0..150 {
+                        let end = list.end();
+                        if rng.gen_bool(0.4) && !ranges.is_empty() {
+                            let (index, _) = ranges.swap_remove(rng.gen_range(0..ranges.len()));
+                            list.remove(index);
+                        } else if rng.gen_bool(0.3) {
+                            let (spacing, span) = (rng.gen_range(0..4), rng.gen_range(0..4));
+                            let start = list.push(spacing, span, element);
+                            ranges.push((start.index(), start.position() + span));
+                        } else {
+                            let start = rng.gen_range(0..120);
+                            let span = rng.gen_range(0..6);
+                            match list.try_insert(start, start + span, element) {
+                                Ok(start) => ranges.push((start.index(), start.position() + span)),
+                                // failed insertions leave the list as it was
+                                Err(_) => assert_eq!(list.end(), end),
+                            }
+                        }
+                        if let Some(end) = ranges.iter().map(|&(_, end)| end).max() {
+                            assert_eq!(list.end(), end);
+                        }
+                    }
+                }
+            }
+        };
+    }
+
+    end_follows_range_removals!(end_follows_range_removals, RangeSpacedList);
+    end_follows_range_removals!(end_follows_nested_range_removals, NestedRangeSpacedList);
+}
@@ -0,0 +1,300 @@
+//! Every traversal walks from the root skeleton down to the element it is looking for, even if the
+//! previous traversal found an element right next to it. To make many traversals around the same
+//! place cheaper, a spaced list can keep a small cache of the positions that recent traversals
+//! have found, called shortcuts. A traversal then starts at the shortcut closest to its target and
+//! steps through the elements from there, which takes constant time if the element it is looking
+//! for is only a few elements away. If it isn't, the traversal gives up after [`SHORTCUT_STEPS`]
+//! steps and walks from the root after all.
+//!
+//! Shortcuts are ephemeral positions, so they are dropped whenever the spaced list changes.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::{EphemeralPosition, Spacing};
+
+/// The number of elements a traversal steps through, starting at a shortcut, before it walks from
+/// the root instead.
+pub(crate) const SHORTCUT_STEPS: usize = 16;
+
+/// Which of the elements whose position is in range a traversal is looking for.
+pub(crate) enum Search {
+    /// The last one, for example the last element <= the target.
+    Last,
+    /// The first one, for example the first element > the target.
+    First,
+}
+
+pub(crate) struct Shortcuts<Kind, S: Spacing, T> {
+    capacity: usize,
+    /// The most recently found position comes last.
+    entries: Vec<EphemeralPosition<Kind, S, T>>,
+}
+
+impl<Kind, S: Spacing, T> Default for Shortcuts<Kind, S, T> {
+    fn default() -> Self {
+        Self {
+            capacity: 0,
+            entries: vec![],
+        }
+    }
+}
+
+impl<Kind, S: Spacing, T> Shortcuts<Kind, S, T> {
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        let excess = self.entries.len().saturating_sub(capacity);
+        self.entries.drain(..excess);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn remember(&mut self, found: &EphemeralPosition<Kind, S, T>) {
+        let same = |entry: &EphemeralPosition<Kind, S, T>|
+            Rc::ptr_eq(&entry.skeleton, &found.skeleton) && entry.index == found.index;
+        if self.capacity == 0 || self.entries.last().is_some_and(same) {
+            return;
+        }
+        let existing = self.entries.iter()
+            .position(same);
+        match existing {
+            Some(existing) => {
+                self.entries.remove(existing);
+            }
+            None if self.entries.len() == self.capacity => {
+                self.entries.remove(0);
+            }
+            None => {}
+        }
+        self.entries.push(found.clone());
+    }
+
+    fn nearest(&self, target: S) -> Option<EphemeralPosition<Kind, S, T>> {
+        self.entries.iter()
+            .min_by_key(|entry| if entry.position > target { entry.position - target } else { target - entry.position })
+            .cloned()
+    }
+
+    /// Finds the first or last element whose position is `in_range` and that `matches`, starting
+    /// at the nearest shortcut, or using the given traversal from the root if that takes too many
+    /// steps. `in_range` must hold for a prefix of the elements when searching for the last one,
+    /// and for a suffix when searching for the first one.
+    pub(crate) fn traverse(
+        shortcuts: &RefCell<Self>,
+        target: S,
+        search: Search,
+        in_range: impl Fn(S) -> bool,
+        matches: impl Fn(&EphemeralPosition<Kind, S, T>) -> bool,
+        traverse: impl FnOnce() -> Option<EphemeralPosition<Kind, S, T>>,
+    ) -> Option<EphemeralPosition<Kind, S, T>> {
+        if shortcuts.borrow().capacity == 0 {
+            return traverse();
+        }
+        // the shortcuts aren't borrowed while stepping, as the condition of a traversal might
+        // traverse the spaced list as well
+        let nearest = shortcuts.borrow().nearest(target);
+        let found = match nearest.and_then(|start| Self::step(start, search, &in_range, &matches)) {
+            Some(found) => found,
+            None => traverse(),
+        };
+        if let Some(found) = &found {
+            shortcuts.borrow_mut().remember(found);
+        }
+        found
+    }
+
+    /// Steps from the given position to the element that [`Self::traverse`] is looking for, or
+    /// returns `None` if that takes more than [`SHORTCUT_STEPS`] steps.
+    fn step(
+        start: EphemeralPosition<Kind, S, T>,
+        search: Search,
+        in_range: impl Fn(S) -> bool,
+        matches: impl Fn(&EphemeralPosition<Kind, S, T>) -> bool,
+    ) -> Option<Option<EphemeralPosition<Kind, S, T>>> {
+        let (towards, away): (fn(_) -> _, fn(_) -> _) = match search {
+            Search::Last => (EphemeralPosition::into_next_element, EphemeralPosition::into_previous_element),
+            Search::First => (EphemeralPosition::into_previous_element, EphemeralPosition::into_next_element),
+        };
+        let mut steps = 0;
+        if in_range(start.position) {
+            // the element is the last (or first) match among the elements in range from here on,
+            // if there is any
+            let mut found = matches(&start).then(|| start.clone());
+            let mut current = start.clone();
+            loop {
+                if steps == SHORTCUT_STEPS {
+                    return None;
+                }
+                steps += 1;
+                match towards(current) {
+                    Some(next) if in_range(next.position) => {
+                        if matches(&next) {
+                            found = Some(next.clone());
+                        }
+                        current = next;
+                    }
+                    _ => break,
+                }
+            }
+            if found.is_some() {
+                return Some(found);
+            }
+        }
+        // otherwise, it is the first match in the other direction
+        let mut current = start;
+        loop {
+            if steps == SHORTCUT_STEPS {
+                return None;
+            }
+            steps += 1;
+            match away(current) {
+                Some(next) if in_range(next.position) && matches(&next) => return Some(Some(next)),
+                Some(next) => current = next,
+                None => return Some(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{NestedRangeSpacedList, RangeSpacedList, SpacedList};
+
+    /// Asserts that the given traversal functions return the same results for both lists, the
+    /// first of which has no shortcuts.
+    macro_rules! assert_same_results {
+        ($a:expr, $b:expr, $target:expr, $condition:expr;
+         $($function:ident)*; $($conditional_function:ident)*) => {
+            $(assert_eq!($a.$function($target).map(|position| (position.position(), position.element().unwrap())),
+                         $b.$function($target).map(|position| (position.position(), position.element().unwrap())),
+                         "{} {}", stringify!($function), $target);)*
+            $(assert_eq!($a.$conditional_function($target, $condition)
+                             .map(|position| (position.position(), position.element().unwrap())),
+                         $b.$conditional_function($target, $condition)
+                             .map(|position| (position.position(), position.element().unwrap())),
+                         "{} {}", stringify!($conditional_function), $target);)*
+        };
+    }
+
+    #[test]
+    fn nodes() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut a: SpacedList<i64, i64> = SpacedList::new();
+            let mut b: SpacedList<i64, i64> = SpacedList::new();
+            b.set_shortcut_capacity(4);
+            for element in 0..400 {
+                let position = rng.gen_range(0..300);
+                a.insert(position, element);
+                b.insert(position, element);
+            }
+            let mut cursor = 150;
+            for _ in 0..2000 {
+                if rng.gen_bool(0.01) {
+                    let position = rng.gen_range(0..300);
+                    a.insert(position, -1);
+                    b.insert(position, -1);
+                }
+                if rng.gen_bool(0.01) {
+                    let _ = a.try_increase_spacing_after(cursor, 3);
+                    let _ = b.try_increase_spacing_after(cursor, 3);
+                }
+                cursor += rng.gen_range(-4..=4);
+                let modulus = rng.gen_range(2..5);
+                assert_same_results!(a, b, cursor, |element: &i64| element % modulus == 0;
+                    before at_or_before at at_or_after after;
+                    conditional_before conditional_at_or_before conditional_at conditional_at_or_after
+                    conditional_after);
+            }
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut a: RangeSpacedList<i64, i64> = RangeSpacedList::new();
+            let mut b: RangeSpacedList<i64, i64> = RangeSpacedList::new();
+            b.set_shortcut_capacity(3);
+            for element in 0..400 {
+                let start = rng.gen_range(0..1000);
+                let end = start + rng.gen_range(0..3);
+                if a.try_insert(start, end, element).is_ok() {
+                    b.insert(start, end, element);
+                }
+            }
+            let mut cursor = 500;
+            for _ in 0..2000 {
+                if rng.gen_bool(0.01) {
+                    let _ = a.try_decrease_spacing_after(cursor, 1);
+                    let _ = b.try_decrease_spacing_after(cursor, 1);
+                }
+                cursor += rng.gen_range(-4..=4);
+                let modulus = rng.gen_range(2..5);
+                assert_same_results!(a, b, cursor, |element: &i64| element % modulus == 0;
+                    starting_before starting_at_or_before starting_at starting_at_or_after starting_after
+                    ending_before ending_at ending_after
+                    starting_or_ending_before starting_or_ending_at starting_or_ending_at_or_after;
+                    conditional_starting_before conditional_starting_at conditional_ending_at_or_before
+                    conditional_ending_after conditional_starting_or_ending_at_or_after);
+            }
+        }
+    }
+
+    #[test]
+    fn nested_ranges() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut a: NestedRangeSpacedList<i64, i64> = NestedRangeSpacedList::new();
+            let mut b: NestedRangeSpacedList<i64, i64> = NestedRangeSpacedList::new();
+            b.set_shortcut_capacity(3);
+            for element in 0..400 {
+                let start = rng.gen_range(0..300);
+                let end = start + rng.gen_range(0..30);
+                if a.try_insert(start, end, element).is_ok() {
+                    b.insert(start, end, element);
+                }
+            }
+            let mut cursor = 150;
+            for _ in 0..2000 {
+                cursor += rng.gen_range(-4..=4);
+                let modulus = rng.gen_range(2..5);
+                assert_same_results!(a, b, cursor, |element: &i64| element % modulus == 0;
+                    starting_at_or_before starting_after ending_before ending_at_or_after
+                    starting_or_ending_at_or_before starting_or_ending_at;
+                    conditional_starting_at_or_after conditional_ending_before conditional_starting_or_ending_after);
+            }
+        }
+    }
+
+    #[test]
+    fn invalidation() {
+        let mut list: SpacedList<i32, char> = SpacedList::new();
+        list.set_shortcut_capacity(2);
+        list.insert(0, 'a');
+        list.insert(10, 'b');
+        list.insert(20, 'c');
+        assert_eq!(list.at_or_before(15).unwrap().element().unwrap(), 'b');
+        list.increase_spacing_after(5, 10);
+        assert_eq!(list.at_or_before(15).unwrap().element().unwrap(), 'a');
+        assert_eq!(list.at(20).unwrap().element().unwrap(), 'b');
+        list.insert(12, 'd');
+        assert_eq!(list.at_or_before(15).unwrap().element().unwrap(), 'd');
+        let d = list.at(12).unwrap().index();
+        list.remove(d);
+        assert_eq!(list.at_or_before(15).unwrap().element().unwrap(), 'a');
+        list.decrease_spacing_after(5, 10);
+        assert_eq!(list.at_or_after(15).unwrap().element().unwrap(), 'c');
+        list.insert(20, 'e');
+        assert_eq!(list.at(20).unwrap().element().unwrap(), 'e');
+    }
+}
@@ -0,0 +1,245 @@
+//! An element inserted between two elements of a skeleton normally goes into a sub. That is cheap
+//! for the insertion itself, but every traversal that reaches the element afterwards has to hop
+//! into the sub, which for small skeletons costs more than the whole skeleton does. Skeletons with
+//! fewer than [`SPLICE_THRESHOLD`] links therefore splice the element right into their links,
+//! elements and subs instead, and rebuild their links from the spacings between their indices.
+//!
+//! Splicing moves every element after the new one to a later index. Like any other move, this is
+//! recorded (see [`index`]), so persistent indices keep referring to the same element.
+//!
+//! [`index`]: super::index
+
+use std::cell::RefCell;
+use std::iter::repeat_n;
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+
+use num_traits::zero;
+
+use crate::{EphemeralIndex, EphemeralPosition, RangeKind, Skeleton, Spacing};
+use crate::skeleton::index::{NO_GENERATION, WeakIndex};
+
+/// The number of links below which elements are spliced into a skeleton instead of being put into
+/// a sub.
+pub(crate) const SPLICE_THRESHOLD: usize = 32;
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Returns whether an element that falls between two indices of this skeleton should be
+    /// spliced into it, rather than put into a sub.
+    pub(super) fn can_splice(&self) -> bool {
+        !self.hyper && self.links.len() < SPLICE_THRESHOLD
+    }
+
+    /// Replaces all links of this skeleton by links with the given spacings.
+    pub(super) fn rebuild_links(&mut self, spacings: Vec<S>) {
+        self.links.clear();
+        self.length = zero();
+        for (index, spacing) in spacings.into_iter().enumerate() {
+            let mut link = spacing;
+            for degree in 0..index.trailing_ones() {
+                link += self.links[index - (1 << degree)];
+            }
+            self.links.push(link);
+            self.length += spacing;
+        }
+        self.depth = self.links.len().checked_ilog2().map_or(0, |log| log as usize + 1);
+    }
+
+    /// Places an element right after the given index, by replacing the link at that index with
+    /// the given spacings, one more than the element has links (the spacing before the element,
+    /// its span if it is a range, and the spacing after it). The index must not be the last one,
+    /// and any sub at it must end before the element. Returns the first index of the element.
+    pub(super) fn splice(this: &Rc<RefCell<Self>>, index: usize, spacings: &[S], element: T)
+                         -> EphemeralIndex<Kind, S, T> {
+        let links_per_element = spacings.len() - 1;
+        let first_index = index + 1;
+        // the indices that elements were moved away from, and the generations of those elements
+        let mut shifted = vec![];
+        {
+            let mut skeleton = this.borrow_mut();
+            let mut all_spacings = (0..skeleton.links.len()).map(|index| skeleton.link(index)).collect::<Vec<_>>();
+            all_spacings.splice(index..=index, spacings.iter().copied());
+            skeleton.rebuild_links(all_spacings);
+
+            skeleton.elements.insert(first_index / links_per_element, None);
+            skeleton.subs.splice(first_index..first_index, repeat_n(None, links_per_element));
+            for (index, sub) in skeleton.subs.iter().enumerate().skip(first_index + links_per_element) {
+                if let Some(sub) = sub {
+                    sub.borrow_mut().parent_data.as_mut().unwrap().index_in_parent = index;
+                }
+            }
+
+            if skeleton.generations.len() > first_index {
+                skeleton.generations.splice(first_index..first_index, repeat_n(NO_GENERATION, links_per_element));
+            }
+            for (index, &generation) in skeleton.generations.iter().enumerate().skip(first_index) {
+                if generation != NO_GENERATION {
+                    shifted.push((index, generation));
+                }
+            }
+            skeleton.origins = mem::take(&mut skeleton.origins).into_iter()
+                .map(|(index, origin)| (if index >= first_index { index + links_per_element } else { index }, origin))
+                .collect();
+
+            for index in first_index..=skeleton.links.len() {
+                let occupied = skeleton.generation(index) != NO_GENERATION
+                    || skeleton.sub(index).is_some_and(|sub| sub.borrow().is_occupied());
+                skeleton.occupancy.set(index, occupied);
+            }
+            skeleton.rebuild_counts(links_per_element);
+        }
+
+        // the entries of elements that were moved here before (including removed ones, see
+        // [`index`](super::index)) need to refer to their new index, and the other elements are
+        // recorded as moved
+        let origins = this.borrow().origins.iter()
+            .filter(|&(&index, _)| index >= first_index + links_per_element)
+            .map(|(&index, origin)| (index, origin.clone()))
+            .collect::<Vec<_>>();
+        for (index, origin) in origins {
+            // the origin may be this skeleton itself, so it can only be borrowed now
+            if let Some(skeleton) = origin.skeleton.upgrade() {
+                if let Some(moved) = skeleton.borrow_mut().moved.get_mut(&(origin.index, origin.generation))
+                    .filter(|moved| moved.index == index - links_per_element && ptr::eq(moved.skeleton.as_ptr(), Rc::as_ptr(this))) {
+                    moved.index = index;
+                }
+            }
+        }
+        for (index, generation) in shifted {
+            let mut skeleton = this.borrow_mut();
+            if skeleton.origins.contains_key(&index) {
+                continue;
+            }
+            let previous_index = index - links_per_element;
+            skeleton.moved.insert((previous_index, generation), WeakIndex {
+                skeleton: Rc::downgrade(this),
+                index,
+                generation,
+            });
+            skeleton.origins.insert(index, WeakIndex {
+                skeleton: Rc::downgrade(this),
+                index: previous_index,
+                generation,
+            });
+        }
+
+        this.borrow_mut().elements[first_index / links_per_element] = Some(element);
+        this.borrow_mut().new_generation(first_index, links_per_element);
+        EphemeralIndex::new(this.clone(), first_index)
+    }
+}
+
+impl<Kind: RangeKind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Returns the spacings to splice a range at the given position and with the given span into
+    /// this skeleton with, right after the given end, if it should be spliced in. The range must
+    /// fit before the next range.
+    pub(super) fn splice_spacings(this: &Rc<RefCell<Self>>, end: &EphemeralPosition<Kind, S, T>, position: S, span: S)
+                                  -> Option<[S; 3]> {
+        let skeleton = this.borrow();
+        let before_sub_end = skeleton.sub(end.index)
+            .is_some_and(|sub| position <= end.position + sub.borrow().last_position());
+        if !skeleton.can_splice() || before_sub_end {
+            return None;
+        }
+        let distance = position - end.position;
+        Some([distance, span, skeleton.link(end.index) - distance - span])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::{NestedRangeSpacedList, RangeSpacedList, SpacedList, StaleIndexError};
+
+    use super::SPLICE_THRESHOLD;
+
+    #[test]
+    fn nodes() {
+        let mut list: SpacedList<u32, u32> = SpacedList::new();
+        list.insert(0, 0);
+        list.insert(100, 100);
+        let mut indices = vec![];
+        for position in [50, 25, 75, 60, 10, 90, 55] {
+            indices.push((list.insert(position, position).index(), position));
+        }
+        assert!(list.skeleton.borrow().subs.iter().all(Option::is_none));
+        // an element at the position of another one goes into a sub instead of a zero-width link
+        indices.push((list.insert(55, 55).index(), 55));
+        assert!(list.skeleton.borrow().subs.iter().any(Option::is_some));
+        assert_eq!(list.iter().map(|pos| pos.position()).collect_vec(), [0, 10, 25, 50, 55, 55, 60, 75, 90, 100]);
+        for (index, position) in &indices {
+            assert_eq!(index.element().unwrap(), *position);
+            assert_eq!(index.position().position(), *position);
+        }
+        assert_eq!(list.at_or_after(56).unwrap().element().unwrap(), 60);
+        assert_eq!(list.before(55).unwrap().element().unwrap(), 50);
+    }
+
+    #[test]
+    fn only_small_skeletons() {
+        let mut list: SpacedList<u32, u32> = SpacedList::new();
+        for i in 0..2 * SPLICE_THRESHOLD as u32 {
+            list.push(10, i);
+        }
+        list.insert(15, 100);
+        assert!(list.skeleton.borrow().subs.iter().any(Option::is_some));
+        assert_eq!(list.at(15).unwrap().element().unwrap(), 100);
+    }
+
+    #[test]
+    fn removal() {
+        let mut list: SpacedList<u32, char> = SpacedList::new();
+        list.insert(0, 'a');
+        let c = list.insert(20, 'c');
+        list.insert(30, 'd');
+        // moves 'c' and 'd' to later indices
+        let b = list.insert(10, 'b');
+        list.remove(c.index());
+        assert!(matches!(c.index().try_element(), Err(StaleIndexError::ElementRemoved)));
+        let e = list.insert(20, 'e');
+        assert!(matches!(c.index().try_element(), Err(StaleIndexError::SlotReused)));
+        list.remove(b.index());
+        assert_eq!(list.iter().map(|pos| pos.element().unwrap()).collect_vec(), ['a', 'e', 'd']);
+        assert_eq!(e.index().position().position(), 20);
+        assert_eq!(list.at(30).unwrap().index().position().position(), 30);
+    }
+
+    #[test]
+    fn ranges() {
+        let mut list: RangeSpacedList<i32, char> = RangeSpacedList::new();
+        let a = list.insert(0, 2, 'a');
+        let d = list.insert(20, 30, 'd');
+        let c = list.insert(10, 15, 'c');
+        let b = list.insert(5, 10, 'b');
+        assert!(list.skeleton.borrow().subs.iter().all(Option::is_none));
+        assert!(list.try_insert(14, 16, 'x').is_err());
+        assert_eq!(list.iter_ranges().map(|(start, end)| (start.position(), end.position(), start.element().unwrap()))
+                       .collect_vec(),
+                   [(0, 2, 'a'), (5, 10, 'b'), (10, 15, 'c'), (20, 30, 'd')]);
+        for (range, element) in [(a, 'a'), (b, 'b'), (c, 'c'), (d, 'd')] {
+            let (start, end) = range.index().into_range();
+            assert_eq!(start.element().unwrap(), element);
+            assert_eq!(end.position().position() - start.position().position(), range.span());
+        }
+    }
+
+    #[test]
+    fn nested_ranges() {
+        let mut list: NestedRangeSpacedList<i32, char> = NestedRangeSpacedList::new();
+        list.insert(0, 10, 'a');
+        let d = list.insert(40, 50, 'd');
+        let b = list.insert(15, 25, 'b');
+        list.insert(17, 20, 'e');
+        list.insert(30, 35, 'c');
+        assert_eq!(list.iter()
+                       .map(|bound| (bound.position(), bound.element().unwrap()))
+                       .collect_vec(),
+                   [(0, 'a'), (10, 'a'), (15, 'b'), (17, 'e'), (20, 'e'), (25, 'b'), (30, 'c'), (35, 'c'),
+                    (40, 'd'), (50, 'd')]);
+        assert_eq!(b.index().element().unwrap(), 'b');
+        assert_eq!(d.index().position().position(), 40);
+        assert_eq!(list.starting_at(17).unwrap().element().unwrap(), 'e');
+    }
+}
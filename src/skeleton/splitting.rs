@@ -0,0 +1,559 @@
+//! Splitting a spaced list at a position moves everything at or after it into a new list, and
+//! appending a list onto another moves everything of it behind the end of the other one. Both
+//! move whole skeletons wherever they can, which keeps the indices into them valid for free.
+//!
+//! Appending puts the root of the appended list into the hyper at the root of the other list as
+//! its last sub (see [`hyper`]), or makes a new hyper holding both roots.
+//!
+//! Splitting takes the subs of hypers apart: subs that lie entirely before or after the position
+//! stay whole and are put into a new hyper for each part. Only the one skeleton that is not a hyper
+//! and has elements on both sides of the position is split by taking its elements out and pushing
+//! them onto two new skeletons, recording them as moved (see [`index`]), like compacting does (see
+//! [`compaction`]). Ranges are never spread over several subs of a hyper, so the ranges that
+//! contain the position can only be in that skeleton.
+//!
+//! [`hyper`]: super::hyper
+//! [`index`]: super::index
+//! [`compaction`]: super::compaction
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use num_traits::zero;
+use thiserror::Error;
+
+use crate::{EphemeralPosition, NestedRange, Node, ParentData, Range, Skeleton, Spacing};
+use crate::skeleton::compaction::Bound;
+
+#[derive(Error, Debug)]
+pub enum SplitError<S: Spacing> {
+    #[error("Cannot split at {position}, which lies inside the range from {start} to {end}.")]
+    PositionInsideRange { position: S, start: S, end: S },
+}
+
+#[derive(Error, Debug)]
+pub enum AppendError<S: Spacing> {
+    #[error("Cannot append a list at a negative spacing ({spacing}) from the end of a non-empty list.")]
+    NegativeSpacing { spacing: S },
+}
+
+/// The roots of the parts before and after the position that a skeleton is split at.
+type Parts<Kind, S, T> = (Rc<RefCell<Skeleton<Kind, S, T>>>, Rc<RefCell<Skeleton<Kind, S, T>>>);
+
+/// Like [`Parts`], but `None` for parts without elements.
+type PartsIfAny<Kind, S, T> = (Option<Rc<RefCell<Skeleton<Kind, S, T>>>>, Option<Rc<RefCell<Skeleton<Kind, S, T>>>>);
+
+/// The bounds of the parts before and after the position that a skeleton is split at.
+type PartBounds<Kind, S, T> = (Vec<Bound<Kind, S, T>>, Vec<Bound<Kind, S, T>>);
+
+/// Which part of a split skeleton a range that has started, but not ended yet, belongs to.
+enum Side {
+    /// The left part, with the position of its start in the bounds of that part.
+    Left(usize),
+    Right,
+    /// Both parts, as it contains the position the skeleton is split at.
+    Both,
+}
+
+/// Returns a function to split ranges with that must never be called, for splits that have been
+/// checked not to split any ranges.
+fn never_split<T>() -> impl FnMut(&mut T) -> T {
+    |_| unreachable!("no range contains the position")
+}
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Returns the first element (or bound) of the given skeleton, if it has any.
+    pub(crate) fn first_element(root: &Rc<RefCell<Self>>) -> Option<EphemeralPosition<Kind, S, T>> {
+        root.borrow().is_occupied().then(|| EphemeralPosition::at_start(root.clone()))?.into_element_at_or_after()
+    }
+
+    /// Splits the given root skeleton into the elements before the given position and those at or
+    /// after it. Ranges that contain the position are split by `split`, which gets the value of
+    /// the part before the position and returns the value of the part after it. Returns the roots
+    /// of both parts.
+    fn split_root(
+        root: Rc<RefCell<Self>>,
+        position: S,
+        links_per_element: usize,
+        split: &mut impl FnMut(&mut T) -> T,
+        push: &impl Fn(Rc<RefCell<Self>>, S, S, T) -> EphemeralPosition<Kind, S, T>,
+    ) -> Parts<Kind, S, T> {
+        match Self::first_element(&root) {
+            None => return (root, Self::new(None)),
+            Some(first) if first.position >= position => return (Self::new(None), root),
+            Some(_) if root.borrow().last_position() < position => return (root, Self::new(None)),
+            Some(_) => {}
+        }
+        let (left, right) = Self::split_skeleton(root, position, links_per_element, split, push);
+        if let Some(left) = &left {
+            // whole skeletons can end in the empty slots of removed elements that were followed by
+            // elements in the other part
+            Self::trim_end(left, links_per_element);
+        }
+        (left.unwrap_or_else(|| Self::new(None)), right.unwrap_or_else(|| Self::new(None)))
+    }
+
+    /// Splits the given skeleton, which has no parent, into two skeletons without a parent, which
+    /// are positioned by their offset like it is. Parts without elements are `None`.
+    fn split_skeleton(
+        this: Rc<RefCell<Self>>,
+        position: S,
+        links_per_element: usize,
+        split: &mut impl FnMut(&mut T) -> T,
+        push: &impl Fn(Rc<RefCell<Self>>, S, S, T) -> EphemeralPosition<Kind, S, T>,
+    ) -> PartsIfAny<Kind, S, T> {
+        if !this.borrow().hyper {
+            let bounds = Self::take_bounds(this.clone(), links_per_element);
+            Self::retire(this);
+            let (left, right) = Self::partition_bounds(bounds, position, links_per_element, split);
+            let rebuild = |bounds: Vec<_>| (!bounds.is_empty())
+                .then(|| Self::push_bounds(Self::new(None), bounds, links_per_element, push));
+            return (rebuild(left), rebuild(right));
+        }
+        let mut left = vec![];
+        let mut right = vec![];
+        for sub in Self::take_subs(&this) {
+            let (start, end) = {
+                let sub = sub.borrow();
+                (sub.offset, sub.last_position())
+            };
+            if end < position {
+                left.push(sub);
+            } else if start >= position {
+                right.push(sub);
+            } else {
+                let (left_part, right_part) = Self::split_skeleton(sub, position, links_per_element, split, push);
+                left.extend(left_part);
+                right.extend(right_part);
+            }
+        }
+        (Self::join(left), Self::join(right))
+    }
+
+    /// Takes the subs out of this hyper, which has no parent, and returns them without a parent,
+    /// positioned by their offset like the hyper was. The hyper is left empty.
+    fn take_subs(this: &Rc<RefCell<Self>>) -> Vec<Rc<RefCell<Self>>> {
+        let mut hyper = this.borrow_mut();
+        let mut position = hyper.offset;
+        let mut subs = vec![];
+        for index in 0..hyper.links.len() {
+            // hypers have a sub at every index except for the last one
+            let sub = hyper.subs[index].take().unwrap();
+            {
+                let mut sub = sub.borrow_mut();
+                sub.offset = position;
+                sub.parent_data = None;
+            }
+            position += hyper.link(index);
+            subs.push(sub);
+        }
+        hyper.clear();
+        hyper.hyper = false;
+        subs
+    }
+
+    /// Returns the only one of the given skeletons, or a hyper holding all of them, or `None` if
+    /// there are none.
+    fn join(mut skeletons: Vec<Rc<RefCell<Self>>>) -> Option<Rc<RefCell<Self>>> {
+        match skeletons.len() {
+            0 => None,
+            1 => skeletons.pop(),
+            _ => Some(Self::build_hyper(skeletons)),
+        }
+    }
+
+    /// Divides the given bounds into those of elements before the given position and those of
+    /// elements at or after it. The ranges that contain the position end up in both, split by
+    /// `split`.
+    fn partition_bounds(bounds: Vec<Bound<Kind, S, T>>, position: S, links_per_element: usize,
+                        split: &mut impl FnMut(&mut T) -> T) -> PartBounds<Kind, S, T> {
+        let mut left = vec![];
+        let mut right = vec![];
+        let mut open = vec![];
+        // whether the ranges containing the position have been split already
+        let mut crossed = false;
+        for bound in bounds {
+            match bound {
+                Bound::Start(index, start, span, element) => {
+                    if start < position {
+                        if links_per_element == 2 {
+                            open.push(Side::Left(left.len()));
+                        }
+                        left.push(Bound::Start(index, start, span, element));
+                        continue;
+                    }
+                    if !crossed {
+                        crossed = true;
+                        Self::split_open_ranges(&mut left, &mut right, &mut open, position, split);
+                    }
+                    if links_per_element == 2 {
+                        open.push(Side::Right);
+                    }
+                    right.push(Bound::Start(index, start, span, element));
+                }
+                Bound::End => {
+                    if !crossed && matches!(open.last(), Some(&Side::Left(start)) if Self::end_of(&left[start]) > position) {
+                        crossed = true;
+                        Self::split_open_ranges(&mut left, &mut right, &mut open, position, split);
+                    }
+                    // cannot fail, as every end comes after its start
+                    match open.pop().unwrap() {
+                        Side::Left(_) => left.push(Bound::End),
+                        Side::Right => right.push(Bound::End),
+                        Side::Both => {
+                            left.push(Bound::End);
+                            right.push(Bound::End);
+                        }
+                    }
+                }
+            }
+        }
+        (left, right)
+    }
+
+    fn end_of(bound: &Bound<Kind, S, T>) -> S {
+        match bound {
+            Bound::Start(_, start, span, _) => *start + *span,
+            Bound::End => unreachable!("only the starts of ranges are open"),
+        }
+    }
+
+    /// Splits the open ranges that contain the given position, outermost first: their part before
+    /// the position stays in the left bounds, and their part after it starts the right bounds.
+    fn split_open_ranges(left: &mut [Bound<Kind, S, T>], right: &mut Vec<Bound<Kind, S, T>>, open: &mut [Side],
+                         position: S, split: &mut impl FnMut(&mut T) -> T) {
+        for side in open {
+            let Side::Left(start) = *side else {
+                continue;
+            };
+            let Bound::Start(_, range_start, span, element) = &mut left[start] else {
+                unreachable!("only the starts of ranges are open");
+            };
+            let range_end = *range_start + *span;
+            if range_end <= position {
+                continue;
+            }
+            *span = position - *range_start;
+            right.push(Bound::Start(None, position, range_end - position, split(element)));
+            *side = Side::Both;
+        }
+    }
+
+    /// Appends the root skeleton `other` to the root skeleton `this`, such that the first element
+    /// of `other` ends up `spacing` after the end of `this`, and returns the new root. If `this`
+    /// has no elements, the first element of `other` ends up at `spacing`, like pushing onto it
+    /// would put it.
+    pub(crate) fn append(this: Rc<RefCell<Self>>, other: Rc<RefCell<Self>>, spacing: S)
+                         -> Result<Rc<RefCell<Self>>, AppendError<S>> {
+        let Some(first) = Self::first_element(&other) else {
+            return Ok(this);
+        };
+        let occupied = this.borrow().is_occupied();
+        if occupied && spacing < zero() {
+            return Err(AppendError::NegativeSpacing { spacing });
+        }
+        // the indices of removed elements before the first element would lie before it
+        let leading = first.position - other.borrow().offset;
+        // cannot fail, as only the gaps before the first element are closed
+        Self::try_change_spacing_before_element_unbounded(&other, &first, false, leading).unwrap();
+        if !occupied {
+            other.borrow_mut().offset = spacing;
+            return Ok(other);
+        }
+        other.borrow_mut().offset = this.borrow().last_position() + spacing;
+        if this.borrow().hyper && !this.borrow().is_full() {
+            Self::push_sub(&this, other);
+            Ok(this)
+        } else {
+            Ok(Self::build_hyper(vec![this, other]))
+        }
+    }
+
+    /// Adds the given skeleton, which has no parent and is positioned by its offset, to the end of
+    /// this hyper as its last sub.
+    fn push_sub(this: &Rc<RefCell<Self>>, sub: Rc<RefCell<Self>>) {
+        let index = {
+            let mut hyper = this.borrow_mut();
+            let mut skeleton = sub.borrow_mut();
+            let last_index = hyper.links.len() - 1;
+            let gap = skeleton.offset - hyper.last_position();
+            hyper.increase_spacing(last_index, gap);
+            let index = hyper.push_link();
+            hyper.increase_spacing(index, skeleton.length);
+            hyper.elements.push(None);
+            skeleton.offset = zero();
+            skeleton.parent_data = Some(ParentData {
+                parent: Rc::downgrade(this),
+                index_in_parent: index,
+            });
+            index
+        };
+        let mut hyper = this.borrow_mut();
+        let count = sub.borrow().count();
+        hyper.subs[index] = Some(sub);
+        hyper.update_occupancy(index);
+        hyper.increase_count(index, count);
+    }
+}
+
+impl<S: Spacing, T> Skeleton<Node, S, T> {
+    /// Splits the given root skeleton into the elements before the given position and those at or
+    /// after it, and returns the roots of both parts.
+    pub(crate) fn split_off(root: Rc<RefCell<Self>>, position: S) -> Parts<Node, S, T> {
+        // cannot fail, as the elements are pushed in order
+        Self::split_root(root, position, 1, &mut never_split(), &|skeleton, distance, _, element|
+            Self::try_push(skeleton, distance, element).unwrap())
+    }
+}
+
+impl<S: Spacing, T> Skeleton<Range, S, T> {
+    /// Splits the given root skeleton into the ranges starting before the given position and those
+    /// starting at or after it, and returns the roots of both parts. A range containing the
+    /// position is split by `split` (see [`Skeleton::split_root`]) if it is given, or makes the
+    /// split fail otherwise.
+    pub(crate) fn split_off(root: Rc<RefCell<Self>>, position: S, split: Option<&mut dyn FnMut(&mut T) -> T>)
+                            -> Result<Parts<Range, S, T>, SplitError<S>> {
+        // as ranges don't overlap, only the last range starting before the position can contain it
+        let containing = Self::conditional_starting_before(root.clone(), position, |slot| slot.is_some())
+            .map(|start| start.into_range())
+            .filter(|(_, end)| end.position > position);
+        // cannot fail, as the ranges are pushed in order
+        let push = |skeleton, distance, span, element| Self::try_push(skeleton, distance, span, element).unwrap();
+        match (containing, split) {
+            (Some((start, end)), None) =>
+                Err(SplitError::PositionInsideRange { position, start: start.position, end: end.position }),
+            (_, Some(split)) => Ok(Self::split_root(root, position, 2, &mut |element: &mut T| split(element), &push)),
+            (None, None) => Ok(Self::split_root(root, position, 2, &mut never_split(), &push)),
+        }
+    }
+}
+
+impl<S: Spacing, T> Skeleton<NestedRange, S, T> {
+    /// Splits the given root skeleton like the function of the same name for [`Range`] skeletons
+    /// does. The ranges that contain the position are all split along with each other.
+    pub(crate) fn split_off(root: Rc<RefCell<Self>>, position: S, split: Option<&mut dyn FnMut(&mut T) -> T>)
+                            -> Result<Parts<NestedRange, S, T>, SplitError<S>> {
+        // the ranges that contain the position contain the last range starting before it
+        let mut containing = None;
+        let mut range = Self::conditional_starting_before(root.clone(), position, |slot| slot.is_some());
+        while let Some(start) = range {
+            let end = start.clone().into_range().1;
+            if start.holds_element() && end.position > position {
+                containing = Some((start.position, end.position));
+            }
+            range = Self::parent_range(start);
+        }
+        // cannot fail, as the ranges are pushed in order
+        let push = |skeleton, distance, span, element| Self::try_push(skeleton, distance, span, element).unwrap();
+        match (containing, split) {
+            (Some((start, end)), None) => Err(SplitError::PositionInsideRange { position, start, end }),
+            (_, Some(split)) => Ok(Self::split_root(root, position, 2, &mut |element: &mut T| split(element), &push)),
+            (None, None) => Ok(Self::split_root(root, position, 2, &mut never_split(), &push)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{NestedRangeSpacedList, RangeOrder, RangeSpacedList, SpacedList};
+
+    #[test]
+    fn split_and_append_nodes() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: SpacedList<u64, u32> = SpacedList::new();
+            let mut indices = vec![];
+            // enough elements for hypers, with subs from insertions and empty slots from removals
+            for element in 0..20_000 {
+                indices.push((element, list.push(rng.gen_range(0..3), element).index()));
+            }
+            for element in 20_000..21_000 {
+                indices.push((element, list.insert(rng.gen_range(0..list.end()), element).index()));
+            }
+            let (indices, removed): (Vec<_>, Vec<_>) = indices.into_iter().partition(|_| rng.gen_bool(0.9));
+            for (_, index) in removed {
+                list.remove(index);
+            }
+            let contents = list.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec();
+            for _ in 0..5 {
+                let position = rng.gen_range(0..list.end() + 2);
+                let mut right = list.split_off(position);
+                let (before, after): (Vec<_>, Vec<_>) = contents.iter().partition(|&&(element_position, _)| element_position < position);
+                assert_eq!(list.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec(), before);
+                assert_eq!(right.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec(), after);
+                assert_eq!((list.size(), right.size()), (before.len(), after.len()));
+                for (element, index) in &indices {
+                    assert_eq!(index.element().unwrap(), *element);
+                }
+                let spacing = after.first().map_or(0, |&(first, _)| first - before.last().map_or(0, |&(last, _)| last));
+                list.append(&mut right, spacing);
+                assert!(right.is_empty());
+                assert_eq!(list.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec(), contents);
+                assert_eq!(list.size(), contents.len());
+            }
+            // the joined list works as usual
+            for (element, index) in indices {
+                assert_eq!(list.remove(index), element);
+            }
+            assert!(list.is_empty());
+        }
+    }
+
+    #[test]
+    fn split_ranges() {
+        let mut list: RangeSpacedList<u64, u64> = RangeSpacedList::new();
+        let indices = (0..10_000).map(|i| list.push(3, 2, i).index()).collect_vec();
+        assert!(list.try_split_off(3 + 5 * 5_000 + 1).is_err());
+        let mut right = list.split_off_splitting(3 + 5 * 5_000 + 1, |element| *element + 100_000);
+        assert_eq!(list.iter_ranges().next_back().map(|(start, end)| (start.position(), end.position(), start.element().unwrap())),
+                   Some((3 + 5 * 5_000, 3 + 5 * 5_000 + 1, 5_000)));
+        assert_eq!(right.iter_ranges().next().map(|(start, end)| (start.position(), end.position(), start.element().unwrap())),
+                   Some((3 + 5 * 5_000 + 1, 3 + 5 * 5_000 + 2, 105_000)));
+        assert_eq!((list.size(), right.size()), (5_001, 5_000));
+        for (i, index) in indices.into_iter().enumerate() {
+            assert_eq!(index.element().unwrap(), i as u64);
+        }
+        let end = list.end();
+        let mut appended: RangeSpacedList<u64, u64> = RangeSpacedList::new();
+        appended.push(10, 1, 0);
+        right.append(&mut appended, 4);
+        list.append(&mut right, 0);
+        assert_eq!(list.size(), 10_002);
+        assert_eq!(list.ending_at_or_before(end).unwrap().element().unwrap(), 5_000);
+        assert_eq!(list.last().unwrap().position(), 3 + 5 * 9_999 + 2 + 4 + 1);
+    }
+
+    #[test]
+    fn split_nested_ranges() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: NestedRangeSpacedList<i64, u32> = NestedRangeSpacedList::new();
+            for element in 0..1_000 {
+                let start = rng.gen_range(0..10_000);
+                let _ = list.try_insert(start, start + rng.gen_range(0..100), element);
+            }
+            let ranges = |list: &NestedRangeSpacedList<i64, u32>| list.iter_ranges(RangeOrder::PreOrder)
+                .map(|(start, end, _)| (start.position(), end.position(), start.element().unwrap()))
+                .sorted()
+                .collect_vec();
+            let expected = ranges(&list);
+            let position = rng.gen_range(0..10_000);
+            let containing = expected.iter().filter(|&&(start, end, _)| start < position && position < end).collect_vec();
+            let right = if containing.is_empty() {
+                list.try_split_off(position).unwrap()
+            } else {
+                assert!(list.try_split_off(position).is_err());
+                list.split_off_splitting(position, |element| *element + 1_000)
+            };
+            let (before, after): (Vec<_>, Vec<_>) = expected.iter().partition(|&&(start, _, _)| start < position);
+            assert_eq!(ranges(&list), before.iter()
+                .map(|&(start, end, element)| (start, end.min(position), element))
+                .collect_vec());
+            assert_eq!(ranges(&right), containing.iter()
+                .map(|&&(_, end, element)| (position, end, element + 1_000))
+                .chain(after)
+                .sorted()
+                .collect_vec());
+        }
+    }
+
+    #[test]
+    fn split_and_append_after_removals() {
+        for seed in 0..1_000 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: SpacedList<i32, i32> = SpacedList::new();
+            // the index, position and value of every element in the list
+            let mut elements = vec![];
+            for element in 0..100 {
+                if rng.gen_bool(0.3) && !elements.is_empty() {
+                    let (index, _, value) = elements.swap_remove(rng.gen_range(0..elements.len()));
+                    assert_eq!(list.remove(index), value);
+                } else if rng.gen_bool(0.7) {
+                    let position = rng.gen_range(0..60);
+                    elements.push((list.insert(position, element).index(), position, element));
+                } else {
+                    let position = rng.gen_range(0..60);
+                    let mut right = list.split_off(position);
+                    let (before, after): (Vec<_>, Vec<_>) = elements.into_iter()
+                        .partition(|&(_, element_position, _)| element_position < position);
+                    let end = before.iter().map(|&(_, position, _)| position).max();
+                    if let Some(end) = end {
+                        assert_eq!(list.end(), end);
+                    }
+                    let spacing = rng.gen_range(0..5);
+                    list.append(&mut right, spacing);
+                    let first = after.iter().map(|&(_, position, _)| position).min().unwrap_or(0);
+                    let shift = end.unwrap_or(0) + spacing - first;
+                    elements = before.into_iter()
+                        .chain(after.into_iter().map(|(index, position, value)| (index, position + shift, value)))
+                        .collect();
+                }
+                let mut expected = elements.iter().map(|&(_, position, value)| (position, value)).collect_vec();
+                expected.sort();
+                let mut actual = list.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec();
+                // the order of elements at the same position isn't known
+                actual.sort();
+                assert_eq!(actual, expected);
+                for (index, position, value) in &elements {
+                    assert_eq!((index.position().position(), index.element().unwrap()), (*position, *value));
+                }
+            }
+        }
+    }
+
+    macro_rules! split_and_append_ranges_after_removals {
+        ($name:ident, $list:ident) => {
+            #[test]
+            fn $name() {
+                for seed in 0..1_000 {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    let mut list: $list<i32, i32> = $list::new();
+                    // the index, start, end and value of every range in the list
+                    let mut ranges = vec![];
+                    for element in 0..100 {
+                        if rng.gen_bool(0.3) && !ranges.is_empty() {
+                            let (index, _, _, value) = ranges.swap_remove(rng.gen_range(0..ranges.len()));
+                            assert_eq!(list.remove(index), value);
+                        } else if rng.gen_bool(0.7) {
+                            let start = rng.gen_range(0..60);
+                            let end = start + rng.gen_range(0..6);
+                            if let Ok(position) = list.try_insert(start, end, element) {
+                                ranges.push((position.index(), start, end, element));
+                            }
+                        } else {
+                            let position = rng.gen_range(0..60);
+                            let Ok(mut right) = list.try_split_off(position) else {
+                                continue;
+                            };
+                            let (before, after): (Vec<_>, Vec<_>) = ranges.into_iter()
+                                .partition(|&(_, start, _, _)| start < position);
+                            let end = before.iter().map(|&(_, _, end, _)| end).max();
+                            if let Some(end) = end {
+                                assert_eq!(list.end(), end);
+                            }
+                            let spacing = rng.gen_range(0..5);
+                            list.append(&mut right, spacing);
+                            let first = after.iter().map(|&(_, start, _, _)| start).min().unwrap_or(0);
+                            let shift = end.unwrap_or(0) + spacing - first;
+                            ranges = before.into_iter()
+                                .chain(after.into_iter().map(|(index, start, end, value)| (index, start + shift, end + shift, value)))
+                                .collect();
+                        }
+                        for (index, start, end, value) in &ranges {
+                            let (start_position, end_position) = index.position().into_range();
+                            assert_eq!((start_position.position(), end_position.position(), index.element().unwrap()),
+                                       (*start, *end, *value));
+                        }
+                    }
+                }
+            }
+        };
+    }
+
+    split_and_append_ranges_after_removals!(split_and_append_ranges_after_removals, RangeSpacedList);
+    split_and_append_ranges_after_removals!(split_and_append_nested_ranges_after_removals, NestedRangeSpacedList);
+}
@@ -72,6 +72,43 @@ macro_rules! previous {
     };
 }
 
+/// Like [`next`] and [`previous`], but these fragments skip indices without an element (which can
+/// never satisfy a condition) in logarithmic time, using the occupancy of the skeletons. Shallow
+/// traversals can't skip, as the occupancy of an index includes its sub.
+macro_rules! next_element {
+    (shallow; $($rest:tt)*) => {
+        next!(shallow; $($rest)*)
+    };
+    (deep; $skeleton:ident, $index:ident, $position:ident) => {
+        match EphemeralPosition::new($skeleton.clone(), $index, $position).into_next_element() {
+            Some(next) => {
+                $skeleton = next.skeleton;
+                $index = next.index;
+                $position = next.position;
+                Ok(())
+            }
+            None => Err("Tried to move to next element but there is no element after this one"),
+        }
+    };
+}
+
+macro_rules! previous_element {
+    (shallow; $($rest:tt)*) => {
+        previous!(shallow; $($rest)*)
+    };
+    (deep; $skeleton:ident, $index:ident, $position:ident) => {
+        match EphemeralPosition::new($skeleton.clone(), $index, $position).into_previous_element() {
+            Some(previous) => {
+                $skeleton = previous.skeleton;
+                $index = previous.index;
+                $position = previous.position;
+                Ok(())
+            }
+            None => Err("Tried to move to previous element but there is no element before this one"),
+        }
+    };
+}
+
 /// These fragments implement logic that needs to be executed after the loop has found a target.
 /// For example, when an element is searched for that == a target, the loop only searches for
 /// the last element that <= a target. In the case that it <=, but not == the target, logic in
@@ -126,7 +163,7 @@ macro_rules! after_loop {
                 // move to the last "end" index before this
                 // under the assumption that the list has the structure start end start end etc.,
                 // finding the previous bound suffices
-                previous!($depth; $skeleton, $index, $position).ok()?;
+                previous_element!($depth; $skeleton, $index, $position).ok()?;
                 // however, if the list, for example, has the structure s e s [s e] e ([...] = sub),
                 // and we're at the second s, we would need to backtrack all the way to the first e
                 // for lists that are structured like s [s [s [...] e] e] e, this is O(n)!
@@ -144,17 +181,25 @@ macro_rules! after_loop {
         $skeleton:ident, $degree:ident, $index:ident, $position:ident) => {
         {
             while !$condition!($index, $skeleton.clone(), $($args),*) {
-                previous!($depth; $skeleton, $index, $position).ok()?;
+                previous_element!($depth; $skeleton, $index, $position).ok()?;
             }
             Some(EphemeralPosition::new($skeleton, $index, $position))
         }
     };
     ($depth:ident, ==, $target:ident, $condition:ident ($($args:tt),*);
         $skeleton:ident, $degree:ident, $index:ident, $position:ident) => {
-        if $position == $target && $condition!($index, $skeleton.clone(), $($args),*) {
-            Some(EphemeralPosition::new($skeleton, $index, $position))
-        } else {
-            None
+        {
+            // the loop finds the last index at the target, but an earlier one at the same position
+            // might match the condition (for example, the end of a range right before the start of
+            // the next one, or the end of the last sub of a hyper)
+            while $position == $target && !$condition!($index, $skeleton.clone(), $($args),*) {
+                previous_element!($depth; $skeleton, $index, $position).ok()?;
+            }
+            if $position == $target {
+                Some(EphemeralPosition::new($skeleton, $index, $position))
+            } else {
+                None
+            }
         }
     };
     ($depth:ident, >=, $target:ident, $condition:ident ($($args:tt),*);
@@ -164,7 +209,7 @@ macro_rules! after_loop {
                 next!($depth; $skeleton, $index, $position).unwrap();
             }
             while !$condition!($index, $skeleton.clone(), $($args),*) {
-                next!($depth; $skeleton, $index, $position).ok()?;
+                next_element!($depth; $skeleton, $index, $position).ok()?;
             }
             Some(EphemeralPosition::new($skeleton, $index, $position))
         }
@@ -174,7 +219,7 @@ macro_rules! after_loop {
         {
             next!($depth; $skeleton, $index, $position).unwrap();
             while !$condition!($index, $skeleton.clone(), $($args),*) {
-                next!($depth; $skeleton, $index, $position).ok()?;
+                next_element!($depth; $skeleton, $index, $position).ok()?;
             }
             Some(EphemeralPosition::new($skeleton, $index, $position))
         }
@@ -182,4 +227,4 @@ macro_rules! after_loop {
     // endregion
 }
 
-pub(super) use {next, previous, after_loop};
\ No newline at end of file
+pub(super) use {next, previous, next_element, previous_element, after_loop};
\ No newline at end of file
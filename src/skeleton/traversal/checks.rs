@@ -36,9 +36,9 @@ macro_rules! trivial_results {
         }
     };
     (==, $target:ident, $condition:ident ($($args:tt),*); $skeleton:ident) => {
-        if $condition!(0, $skeleton.clone(), $($args),*) && $target == $skeleton.borrow().offset() {
-            return Some(EphemeralPosition::at_start($skeleton));
-        } else if $condition!($skeleton.borrow().links.len(), $skeleton.clone(), $($args),*) && $target == $skeleton.borrow().last_position() {
+        // the first element can't be returned right away if the target is at the start, as there
+        // may be more elements at the same position after it, and the last one is the result
+        if $condition!($skeleton.borrow().links.len(), $skeleton.clone(), $($args),*) && $target == $skeleton.borrow().last_position() {
             return Some(EphemeralPosition::at_end($skeleton));
         }
     };
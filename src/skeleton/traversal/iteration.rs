@@ -1,10 +1,367 @@
 use std::cell::RefCell;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
 use std::rc::Rc;
 
-use crate::{Position, Skeleton, Spacing};
+use crate::{BoundType, EphemeralPosition, HollowPosition, Position, RangeKind, Skeleton, Spacing};
 
 // TODO implement parallel iteration
-// TODO use IntoIterator and DoubleEndedIterator traits where applicable
+
+/// An iterator over all elements of a spaced list (for ranges, over all of their bounds), in
+/// ascending order from the front and in descending order from the back.
+///
+/// The number of elements is known from the start, so the iterator knows when the front and back
+/// meet, and its length is exact. Only [`Position`]s are produced by it, which are turned into
+/// `P`, so that hollow lists can use it as well.
+pub struct Iter<Kind, S: Spacing, T, P = Position<Kind, S, T>> {
+    front: Option<Position<Kind, S, T>>,
+    back: Option<Position<Kind, S, T>>,
+    remaining: usize,
+    /// Skeletons don't keep their parents alive, so this keeps the list alive for iterators that
+    /// consume it.
+    _root: Rc<RefCell<Skeleton<Kind, S, T>>>,
+    _position: PhantomData<P>,
+}
+
+/// An [`Iter`] over the bounds of a hollow spaced list.
+pub type HollowIter<Kind, S> = Iter<Kind, S, (), HollowPosition<Kind, S>>;
+
+impl<Kind, S: Spacing, T, P> Iter<Kind, S, T, P> {
+    /// Creates an iterator over the given root skeleton, which holds `len` elements (or bounds).
+    pub(crate) fn new(root: Rc<RefCell<Skeleton<Kind, S, T>>>, len: usize) -> Self {
+        let (front, back) = if len == 0 {
+            (None, None)
+        } else {
+            (EphemeralPosition::at_start(root.clone()).into_element_at_or_after().map(Into::into),
+             EphemeralPosition::at_end(root.clone()).into_element_at_or_before().map(Into::into))
+        };
+        Self {
+            front,
+            back,
+            remaining: len,
+            _root: root,
+            _position: PhantomData,
+        }
+    }
+}
+
+impl<Kind, S: Spacing, T, P> Clone for Iter<Kind, S, T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            front: self.front.clone(),
+            back: self.back.clone(),
+            remaining: self.remaining,
+            _root: self._root.clone(),
+            _position: PhantomData,
+        }
+    }
+}
+
+impl<Kind, S: Spacing, T, P: From<Position<Kind, S, T>>> Iterator for Iter<Kind, S, T, P> {
+    type Item = P;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let position = self.front.take()?;
+        if self.remaining > 0 {
+            self.front = position.clone().into_next();
+        }
+        Some(position.into())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<Kind, S: Spacing, T, P: From<Position<Kind, S, T>>> DoubleEndedIterator for Iter<Kind, S, T, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let position = self.back.take()?;
+        if self.remaining > 0 {
+            self.back = position.clone().into_previous();
+        }
+        Some(position.into())
+    }
+}
+
+impl<Kind, S: Spacing, T, P: From<Position<Kind, S, T>>> ExactSizeIterator for Iter<Kind, S, T, P> {}
+
+impl<Kind, S: Spacing, T, P: From<Position<Kind, S, T>>> FusedIterator for Iter<Kind, S, T, P> {}
+
+/// An iterator over the ranges of a range spaced list, as pairs of their start and end, built on
+/// [`Iter`], which produces the bounds of each range one after the other.
+pub struct Ranges<Kind, S: Spacing, T, P = Position<Kind, S, T>> {
+    bounds: Iter<Kind, S, T, P>,
+}
+
+/// [`Ranges`] of a hollow range spaced list.
+pub type HollowRanges<Kind, S> = Ranges<Kind, S, (), HollowPosition<Kind, S>>;
+
+impl<Kind, S: Spacing, T, P> Ranges<Kind, S, T, P> {
+    pub(crate) fn new(bounds: Iter<Kind, S, T, P>) -> Self {
+        Self { bounds }
+    }
+}
+
+impl<Kind, S: Spacing, T, P> Clone for Ranges<Kind, S, T, P> {
+    fn clone(&self) -> Self {
+        Self { bounds: self.bounds.clone() }
+    }
+}
+
+impl<Kind, S: Spacing, T, P: From<Position<Kind, S, T>>> Iterator for Ranges<Kind, S, T, P> {
+    type Item = (P, P);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.bounds.next()?, self.bounds.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.bounds.len() / 2;
+        (len, Some(len))
+    }
+}
+
+impl<Kind, S: Spacing, T, P: From<Position<Kind, S, T>>> DoubleEndedIterator for Ranges<Kind, S, T, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let end = self.bounds.next_back()?;
+        Some((self.bounds.next_back()?, end))
+    }
+}
+
+impl<Kind, S: Spacing, T, P: From<Position<Kind, S, T>>> ExactSizeIterator for Ranges<Kind, S, T, P> {}
+
+impl<Kind, S: Spacing, T, P: From<Position<Kind, S, T>>> FusedIterator for Ranges<Kind, S, T, P> {}
+
+/// Returns whether the given range of positions contains no position at all.
+pub(crate) fn is_empty_window<S: Spacing>(range: &impl RangeBounds<S>) -> bool {
+    match (range.start_bound(), range.end_bound()) {
+        (Bound::Included(start), Bound::Included(end)) => start > end,
+        (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end) | Bound::Excluded(end)) => start >= end,
+        _ => false,
+    }
+}
+
+/// Returns whether the given positions refer to the same element (for ranges, bound). Comparing
+/// slots isn't enough, as a slot that an element has been moved away from can be reused by another
+/// one, which then only differs in its generation.
+fn same_element<Kind, S: Spacing, T>(a: &Position<Kind, S, T>, b: &Position<Kind, S, T>) -> bool {
+    Rc::ptr_eq(&a.skeleton, &b.skeleton) && a.index == b.index && a.generation == b.generation
+}
+
+/// An iterator over the elements of a spaced list (for ranges, over their bounds) positioned within
+/// a window, in ascending order from the front and in descending order from the back. If a bound
+/// type is given, only bounds of that type are produced.
+///
+/// It starts at the first and the last element in the window, and stops when the front and back
+/// meet, or when the front leaves the end of the window or the back leaves its start (which is
+/// where it would stop if the window is empty, as its first element then comes after its last).
+pub struct Window<Kind, S: Spacing, T, P = Position<Kind, S, T>> {
+    front: Option<Position<Kind, S, T>>,
+    back: Option<Position<Kind, S, T>>,
+    start: (Bound<S>, Bound<S>),
+    end: (Bound<S>, Bound<S>),
+    bound: Option<BoundType>,
+    _position: PhantomData<P>,
+}
+
+/// A [`Window`] over the bounds of a hollow spaced list.
+pub type HollowWindow<Kind, S> = Window<Kind, S, (), HollowPosition<Kind, S>>;
+
+impl<Kind, S: Spacing, T, P> Window<Kind, S, T, P> {
+    /// Creates an iterator over the elements between `first` and `last` that lie within the given
+    /// range. `first` and `last` must be the first element at or after its start and the last one
+    /// at or before its end, respectively (bounds of another type than the given one are skipped).
+    pub(crate) fn new(first: Option<Position<Kind, S, T>>, last: Option<Position<Kind, S, T>>,
+                      range: impl RangeBounds<S>, bound: Option<BoundType>) -> Self {
+        let (front, back) = match (first, last) {
+            (Some(first), Some(last)) if !is_empty_window(&range) => (Some(first), Some(last)),
+            _ => (None, None),
+        };
+        Self {
+            front,
+            back,
+            start: (range.start_bound().cloned(), Bound::Unbounded),
+            end: (Bound::Unbounded, range.end_bound().cloned()),
+            bound,
+            _position: PhantomData,
+        }
+    }
+
+    fn finish(&mut self) {
+        self.front = None;
+        self.back = None;
+    }
+
+    fn matches(&self, position: &Position<Kind, S, T>) -> bool {
+        self.bound.is_none_or(|bound| BoundType::of(position.index) == bound)
+    }
+}
+
+impl<Kind, S: Spacing, T, P> Clone for Window<Kind, S, T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            front: self.front.clone(),
+            back: self.back.clone(),
+            start: self.start,
+            end: self.end,
+            bound: self.bound,
+            _position: PhantomData,
+        }
+    }
+}
+
+impl<Kind, S: Spacing, T, P: From<Position<Kind, S, T>>> Iterator for Window<Kind, S, T, P> {
+    type Item = P;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let position = self.front.take()?;
+            if !self.end.contains(&position.position) {
+                self.finish();
+                return None;
+            }
+            if same_element(&position, self.back.as_ref()?) {
+                self.finish();
+            } else {
+                self.front = position.clone().into_next();
+            }
+            if self.matches(&position) {
+                return Some(position.into());
+            }
+        }
+    }
+}
+
+impl<Kind, S: Spacing, T, P: From<Position<Kind, S, T>>> DoubleEndedIterator for Window<Kind, S, T, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let position = self.back.take()?;
+            if !self.start.contains(&position.position) {
+                self.finish();
+                return None;
+            }
+            if same_element(self.front.as_ref()?, &position) {
+                self.finish();
+            } else {
+                self.back = position.clone().into_previous();
+            }
+            if self.matches(&position) {
+                return Some(position.into());
+            }
+        }
+    }
+}
+
+impl<Kind, S: Spacing, T, P: From<Position<Kind, S, T>>> FusedIterator for Window<Kind, S, T, P> {}
+
+/// An iterator over the ranges of a range spaced list between a first and a last one, as pairs of
+/// their start and end, in ascending order from the front and in descending order from the back.
+///
+/// Like [`Window`], it stops when the front and back meet, or when the given bound of the range at
+/// the front leaves the end of the window, or the other bound of the range at the back leaves its
+/// start.
+pub struct RangeWindow<Kind, S: Spacing, T, P = Position<Kind, S, T>> {
+    /// The start of the range at the front.
+    front: Option<Position<Kind, S, T>>,
+    /// The end of the range at the back.
+    back: Option<Position<Kind, S, T>>,
+    start: (Bound<S>, Bound<S>),
+    end: (Bound<S>, Bound<S>),
+    /// Whether the end (rather than the start) of the range at the front must lie within the end
+    /// of the window, and the start (rather than the end) of the range at the back within its start.
+    contained: bool,
+    _position: PhantomData<P>,
+}
+
+/// A [`RangeWindow`] over the ranges of a hollow range spaced list.
+pub type HollowRangeWindow<Kind, S> = RangeWindow<Kind, S, (), HollowPosition<Kind, S>>;
+
+impl<Kind: RangeKind, S: Spacing, T, P> RangeWindow<Kind, S, T, P> {
+    /// Creates an iterator over the ranges from the one starting at `first` to the one ending at
+    /// `last` that overlap the given range or, if `contained` is true, lie within it.
+    pub(crate) fn new(first: Option<Position<Kind, S, T>>, last: Option<Position<Kind, S, T>>,
+                      range: impl RangeBounds<S>, contained: bool) -> Self {
+        let (front, back) = match (first, last) {
+            (Some(first), Some(last)) if !is_empty_window(&range) => (Some(first), Some(last)),
+            _ => (None, None),
+        };
+        Self {
+            front,
+            back,
+            start: (range.start_bound().cloned(), Bound::Unbounded),
+            end: (Bound::Unbounded, range.end_bound().cloned()),
+            contained,
+            _position: PhantomData,
+        }
+    }
+
+    fn finish(&mut self) {
+        self.front = None;
+        self.back = None;
+    }
+}
+
+impl<Kind, S: Spacing, T, P> Clone for RangeWindow<Kind, S, T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            front: self.front.clone(),
+            back: self.back.clone(),
+            start: self.start,
+            end: self.end,
+            contained: self.contained,
+            _position: PhantomData,
+        }
+    }
+}
+
+impl<Kind: RangeKind, S: Spacing, T, P: From<Position<Kind, S, T>>> Iterator for RangeWindow<Kind, S, T, P> {
+    type Item = (P, P);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.front.take()?;
+        let (_, end) = start.clone().into_range();
+        let checked = if self.contained { &end } else { &start };
+        if !self.end.contains(&checked.position) {
+            self.finish();
+            return None;
+        }
+        if same_element(&end, self.back.as_ref()?) {
+            self.finish();
+        } else {
+            self.front = end.clone().into_next();
+        }
+        Some((start.into(), end.into()))
+    }
+}
+
+impl<Kind: RangeKind, S: Spacing, T, P: From<Position<Kind, S, T>>> DoubleEndedIterator for RangeWindow<Kind, S, T, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let end = self.back.take()?;
+        let (start, _) = end.clone().into_range();
+        let checked = if self.contained { &start } else { &end };
+        if !self.start.contains(&checked.position) {
+            self.finish();
+            return None;
+        }
+        if same_element(self.front.as_ref()?, &start) {
+            self.finish();
+        } else {
+            self.back = start.clone().into_previous();
+        }
+        Some((start.into(), end.into()))
+    }
+}
+
+impl<Kind: RangeKind, S: Spacing, T, P: From<Position<Kind, S, T>>> FusedIterator for RangeWindow<Kind, S, T, P> {}
 
 pub(crate) struct ForwardsIter<Kind, S: Spacing, T> {
     position: Option<Position<Kind, S, T>>,
@@ -16,12 +373,6 @@ impl<Kind, S: Spacing, T> ForwardsIter<Kind, S, T> {
             position: Some(position),
         }
     }
-
-    pub(crate) fn from_start(skeleton: Rc<RefCell<Skeleton<Kind, S, T>>>) -> Self {
-        Self {
-            position: Some(Position::at_start(skeleton)),
-        }
-    }
 }
 
 impl<Kind, S: Spacing, T> Iterator for ForwardsIter<Kind, S, T> {
@@ -48,12 +399,6 @@ impl<Kind, S: Spacing, T> BackwardsIter<Kind, S, T> {
             position: Some(position),
         }
     }
-
-    pub(crate) fn from_end(skeleton: Rc<RefCell<Skeleton<Kind, S, T>>>) -> Self {
-        Self {
-            position: Some(Position::at_end(skeleton)),
-        }
-    }
 }
 
 impl<Kind, S: Spacing, T> Iterator for BackwardsIter<Kind, S, T> {
@@ -1,14 +1,3 @@
-/// If an element <= a target is searched for, the search can be aborted as soon as an element
-/// is found that equals the target.
-macro_rules! break_if_equal {
-    (<, $target:ident; $position:ident) => {};
-    (<=, $target:ident; $position:ident) => {
-        if $position == $target {
-            break;
-        }
-    };
-}
-
 /// If the traversal is deep, a result might be found in a sub.
 macro_rules! into_sub_if_deep {
     (shallow, $($_rest:tt)*) => {};
@@ -36,18 +25,20 @@ macro_rules! into_sub_if_deep {
 /// the associated link equals the spacing between nodes whose difference in node index is
 /// 2^n. At each step in this loop, the degree is reduced by one, corresponding to a halving
 /// of the search space. This gives it a O(log n) time complexity (although subs can worsen
-/// this, their impact is manageable in most cases).
+/// this, they are kept from nesting too deeply, see the balancing module).
 macro_rules! r#loop {
     // All five comparison operators can, for the purpose of this traversal, be reduced to two.
     // For example, to find the first element > the target, it suffices to find the last element
-    // <= the target, as its successor is surely the intended result.
+    // <= the target, as its successor is surely the intended result. Likewise, the first element
+    // >= the target is the successor of the last element < the target (finding the last element
+    // <= the target instead would skip other elements at the target that come before it).
 
     // region redirect loop cmp
     ($depth:ident, ==, $($rest:tt)*) => {
         r#loop!($depth, <=, $($rest)*)
     };
     ($depth:ident, >=, $($rest:tt)*) => {
-        r#loop!($depth, <=, $($rest)*)
+        r#loop!($depth, <, $($rest)*)
     };
     ($depth:ident, >, $($rest:tt)*) => {
         r#loop!($depth, <=, $($rest)*)
@@ -69,14 +60,11 @@ macro_rules! r#loop {
 
             // this prefetching strategy leads to significant performance improvements (20-40%)
             if $degree > 0 && $skeleton.borrow().link_index_is_in_bounds(get_link_index($index + (1 << $degree), $degree - 1)) {
-                // SAFETY: _mm_prefetch does not change the behaviour of the program, and the parameters are valid
-                unsafe {
-                    let reference = &$skeleton.borrow().links[get_link_index($index, $degree - 1)];
-                    // FIXME we probably shouldn't use core::arch::x86_64::_MM_HINT_T0 here, but 3 directly instead!
-                    prefetch_read_data(reference as *const S as *const i8, core::arch::x86_64::_MM_HINT_T0);
-                    let reference = &$skeleton.borrow().links[get_link_index($index + (1 << $degree), $degree - 1)];
-                    prefetch_read_data(reference as *const S as *const i8, core::arch::x86_64::_MM_HINT_T0);
-                }
+                let reference = &$skeleton.borrow().links[get_link_index($index, $degree - 1)];
+                // locality 3 corresponds to _MM_HINT_T0
+                prefetch_read_data::<_, 3>(reference as *const S);
+                let reference = &$skeleton.borrow().links[get_link_index($index + (1 << $degree), $degree - 1)];
+                prefetch_read_data::<_, 3>(reference as *const S);
 
                 // this seems to (mostly) make performance (much) worse, especially for small to medium sized skeletons
                 /*if $degree > 1 {
@@ -97,7 +85,8 @@ macro_rules! r#loop {
             if next_position $cmp $target {
                 $position = next_position;
                 $index += 1 << $degree;
-                break_if_equal!($cmp, $target; $position);
+                // no need to abort early if next_position equals the target: there might be more
+                // indices at the same position after this one (zero-width ranges, for example)
             }
 
             if $degree > 0 {
@@ -110,4 +99,4 @@ macro_rules! r#loop {
     };
 }
 
-pub(super) use {break_if_equal, into_sub_if_deep, r#loop};
+pub(super) use {into_sub_if_deep, r#loop};
@@ -3,6 +3,7 @@ use std::cell::Ref;
 use std::rc::Rc;
 use std::intrinsics::prefetch_read_data;
 
+use num_traits::zero;
 use paste::paste;
 
 use crate::{NestedRange, Node, ElementSlot, EphemeralPosition, EphemeralIndex, ParentData, Range, Skeleton, Spacing, BoundType};
@@ -188,16 +189,43 @@ impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
         if index > this.borrow().links.len() {
             return None;
         }
-        let mut position = this.borrow().offset;
+        // positions in a sub are relative to the position of its index in the parent, so the
+        // positions of all indices up to the root need to be added up
+        let mut position = zero();
+        let mut skeleton = this.clone();
+        let mut index_in_skeleton = index;
+        loop {
+            position += skeleton.borrow().shallow_position_of(index_in_skeleton);
+            let parent_data = skeleton.borrow().parent_data.as_ref()
+                .map(|ParentData { parent, index_in_parent }| (parent.upgrade().unwrap(), *index_in_parent));
+            match parent_data {
+                Some((parent, index_in_parent)) => {
+                    skeleton = parent;
+                    index_in_skeleton = index_in_parent;
+                }
+                None => break,
+            }
+        }
+        Some(EphemeralPosition::new(this, index, position))
+    }
+
+    pub(crate) fn shallow_position_of(&self, index: usize) -> S {
+        Self::shallow_position_in(&self.links, self.depth, self.offset, index)
+    }
+
+    /// Like [`Self::shallow_position_of`], for a skeleton whose links, depth and offset are
+    /// borrowed apart from the rest of it.
+    pub(crate) fn shallow_position_in(links: &[S], depth: usize, offset: S, index: usize) -> S {
+        let mut position = offset;
         let mut current_index = 0;
-        for degree in (0..this.borrow().depth).rev() {
+        for degree in (0..depth).rev() {
             let next_index = current_index + (1 << degree);
             if next_index <= index {
-                position += this.borrow().links[current_index];
+                position += links[get_link_index(current_index, degree)];
                 current_index = next_index;
             }
         }
-        Some(EphemeralPosition::new(this, index, position))
+        position
     }
 }
 
@@ -0,0 +1,430 @@
+//! Iterating over positions (see [`iteration`]) resolves a persistent index for every element,
+//! which is needed for positions to stay valid, but is slower than going through the elements
+//! directly. [`Values`] and [`ValuesMut`] instead borrow every skeleton of a list once, and then
+//! go through the borrowed skeletons like a tree, yielding references to the elements along with
+//! their positions: the element at an index comes before the elements in its sub, and empty
+//! indices are skipped using occupancy (see [`occupancy`]).
+//!
+//! The skeletons stay borrowed for as long as [`Values`] or [`ValuesMut`] exist, and the
+//! references that iterating over them yields can't outlive them, so [`Position`]s and [`Index`]es
+//! of the list can't be used to access its elements in the meantime, like with [`ElementRef`] and
+//! [`ElementRefMut`].
+//!
+//! [`iteration`]: super::traversal::iteration
+//! [`occupancy`]: super::occupancy
+//! [`Position`]: crate::Position
+//! [`Index`]: crate::Index
+//! [`ElementRef`]: crate::ElementRef
+//! [`ElementRefMut`]: crate::ElementRefMut
+
+use std::cell::{Ref, RefCell, RefMut};
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::mem;
+use std::rc::Rc;
+
+use num_traits::zero;
+use ouroboros::self_referencing;
+
+use crate::{Skeleton, Spacing};
+use crate::skeleton::ElementSlot;
+use crate::skeleton::occupancy::Occupancy;
+
+type Skeletons<Kind, S, T> = Vec<Rc<RefCell<Skeleton<Kind, S, T>>>>;
+type Subs<Kind, S, T> = [Option<Rc<RefCell<Skeleton<Kind, S, T>>>>];
+
+#[self_referencing]
+struct SkeletonRefs<'a, Kind: 'a, S: Spacing + 'a, T: 'a> {
+    skeletons: Skeletons<Kind, S, T>,
+    #[borrows(skeletons)]
+    #[covariant]
+    guards: Vec<Ref<'this, Skeleton<Kind, S, T>>>,
+    list: PhantomData<&'a ()>,
+}
+
+#[self_referencing]
+struct SkeletonRefsMut<'a, Kind: 'a, S: Spacing + 'a, T: 'a> {
+    skeletons: Skeletons<Kind, S, T>,
+    #[borrows(skeletons)]
+    #[covariant]
+    guards: Vec<RefMut<'this, Skeleton<Kind, S, T>>>,
+    list: PhantomData<&'a mut ()>,
+}
+
+impl<Kind, S: Spacing, T> Skeleton<Kind, S, T> {
+    /// Returns the given skeleton and the subs at its occupied indices, their subs, and so on, in
+    /// the order in which iterating over values descends into them.
+    fn occupied_skeletons(this: &Rc<RefCell<Self>>) -> Skeletons<Kind, S, T> {
+        let mut skeletons = vec![];
+        let mut stack = vec![this.clone()];
+        while let Some(skeleton) = stack.pop() {
+            {
+                let skeleton = skeleton.borrow();
+                stack.extend(skeleton.subs.iter().enumerate().rev()
+                    .filter(|&(index, _)| skeleton.occupancy.get(index))
+                    .filter_map(|(_, sub)| sub.clone()));
+            }
+            skeletons.push(skeleton);
+        }
+        skeletons
+    }
+}
+
+/// The element slots of a skeleton that haven't been visited yet, which can be borrowed apart
+/// from the rest of the skeleton, shared or mutably.
+trait Slots: Default {
+    type Value;
+
+    /// Returns the value in the slot that comes `skip` slots after the first one, if it has any,
+    /// and leaves only the slots after it.
+    fn take(&mut self, skip: usize) -> Option<Self::Value>;
+}
+
+impl<'v, T> Slots for &'v [ElementSlot<T>] {
+    type Value = &'v T;
+
+    fn take(&mut self, skip: usize) -> Option<&'v T> {
+        let slots: &'v [ElementSlot<T>] = self;
+        let (slot, rest) = slots.get(skip..)?.split_first()?;
+        *self = rest;
+        slot.as_ref()
+    }
+}
+
+impl<'v, T> Slots for &'v mut [ElementSlot<T>] {
+    type Value = &'v mut T;
+
+    fn take(&mut self, skip: usize) -> Option<&'v mut T> {
+        let (slot, rest) = mem::take(self).get_mut(skip..)?.split_first_mut()?;
+        *self = rest;
+        slot.as_mut()
+    }
+}
+
+/// A borrowed skeleton that is being gone through, with its element slots borrowed apart from
+/// the rest of it.
+struct Level<'v, Kind, S: Spacing, T, E> {
+    links: &'v [S],
+    depth: usize,
+    offset: S,
+    subs: &'v Subs<Kind, S, T>,
+    occupancy: &'v Occupancy,
+    slots: E,
+    /// The element slot that `slots` starts with.
+    first_slot: usize,
+    /// The position that the skeleton is positioned relative to, that is, the position of the
+    /// index of its parent that it is the sub of.
+    origin: S,
+    /// The index to go on from, and whether its element has been visited already.
+    index: usize,
+    visited_element: bool,
+}
+
+impl<'v, Kind, S: Spacing, T> Level<'v, Kind, S, T, &'v [ElementSlot<T>]> {
+    fn new(skeleton: &'v Skeleton<Kind, S, T>) -> Self {
+        Level {
+            links: &skeleton.links,
+            depth: skeleton.depth,
+            offset: skeleton.offset,
+            subs: &skeleton.subs,
+            occupancy: &skeleton.occupancy,
+            slots: &skeleton.elements,
+            first_slot: 0,
+            origin: zero(),
+            index: 0,
+            visited_element: false,
+        }
+    }
+}
+
+impl<'v, Kind, S: Spacing, T> Level<'v, Kind, S, T, &'v mut [ElementSlot<T>]> {
+    fn new_mut(skeleton: &'v mut Skeleton<Kind, S, T>) -> Self {
+        let Skeleton { links, elements, subs, offset, depth, occupancy, .. } = skeleton;
+        Level {
+            links,
+            depth: *depth,
+            offset: *offset,
+            subs,
+            occupancy,
+            slots: elements,
+            first_slot: 0,
+            origin: zero(),
+            index: 0,
+            visited_element: false,
+        }
+    }
+}
+
+impl<Kind, S: Spacing, T, E> Level<'_, Kind, S, T, E> {
+    fn position(&self, index: usize) -> S {
+        self.origin + Skeleton::<Kind, S, T>::shallow_position_in(self.links, self.depth, self.offset, index)
+    }
+}
+
+/// Goes through borrowed skeletons like a tree, yielding the elements (for ranges, at their
+/// starts) in the element slots `E` along with their positions.
+struct Walk<'v, Kind, S: Spacing, T, E> {
+    /// The skeletons that haven't been descended into yet, in reverse order of when they will be.
+    pending: Vec<Level<'v, Kind, S, T, E>>,
+    /// The skeleton being gone through, after the skeletons that it is a sub of.
+    levels: Vec<Level<'v, Kind, S, T, E>>,
+    remaining: usize,
+    links_per_element: usize,
+}
+
+impl<'v, Kind, S: Spacing, T, E: Slots> Walk<'v, Kind, S, T, E> {
+    /// Creates a walk over the given skeletons, which are those that
+    /// [`Skeleton::occupied_skeletons`] returns, in the same order.
+    fn new(mut pending: Vec<Level<'v, Kind, S, T, E>>, len: usize, links_per_element: usize) -> Self {
+        pending.reverse();
+        let levels = pending.pop().into_iter().collect();
+        Self { pending, levels, remaining: len, links_per_element }
+    }
+
+    fn next(&mut self) -> Option<(S, E::Value)> {
+        if self.remaining == 0 {
+            return None;
+        }
+        loop {
+            let level = self.levels.last_mut()?;
+            let Some(index) = level.occupancy.next(level.index) else {
+                self.levels.pop();
+                continue;
+            };
+            if index != level.index {
+                level.index = index;
+                level.visited_element = false;
+            }
+            if !level.visited_element {
+                level.visited_element = true;
+                // the ends of ranges hold the same elements as their starts
+                if index % self.links_per_element == 0 {
+                    let slot = index / self.links_per_element;
+                    let value = level.slots.take(slot - level.first_slot);
+                    level.first_slot = slot + 1;
+                    if let Some(value) = value {
+                        self.remaining -= 1;
+                        return Some((level.position(index), value));
+                    }
+                }
+            }
+            level.index = index + 1;
+            level.visited_element = false;
+            if level.subs.get(index).is_some_and(Option::is_some) {
+                let origin = level.position(index);
+                // cannot fail, as the skeletons are collected in the order they are descended into
+                let mut sub = self.pending.pop().unwrap();
+                sub.origin = origin;
+                self.levels.push(sub);
+            }
+        }
+    }
+}
+
+/// Shared access to all elements of a spaced list (for ranges, of all ranges), which can be
+/// iterated over with [`Values::iter`] or by reference, yielding their positions (for ranges, the
+/// positions of their starts) and references to them in ascending order.
+pub struct Values<'a, Kind: 'a, S: Spacing + 'a, T: 'a> {
+    refs: SkeletonRefs<'a, Kind, S, T>,
+    len: usize,
+    links_per_element: usize,
+}
+
+impl<'a, Kind, S: Spacing, T> Values<'a, Kind, S, T> {
+    pub(crate) fn new(root: &Rc<RefCell<Skeleton<Kind, S, T>>>, len: usize, links_per_element: usize) -> Self {
+        let refs = SkeletonRefsBuilder {
+            skeletons: Skeleton::occupied_skeletons(root),
+            guards_builder: |skeletons: &Skeletons<Kind, S, T>|
+                skeletons.iter().map(|skeleton| skeleton.borrow()).collect(),
+            list: PhantomData,
+        }.build();
+        Self { refs, len, links_per_element }
+    }
+
+    pub fn iter(&self) -> ValuesIter<'_, Kind, S, T> {
+        let levels = self.refs.borrow_guards().iter().map(|guard| Level::new(guard)).collect();
+        ValuesIter { walk: Walk::new(levels, self.len, self.links_per_element) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<'v, Kind, S: Spacing, T> IntoIterator for &'v Values<'_, Kind, S, T> {
+    type Item = (S, &'v T);
+    type IntoIter = ValuesIter<'v, Kind, S, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Exclusive access to all elements of a spaced list (for ranges, of all ranges), which can be
+/// iterated over with [`ValuesMut::iter_mut`] or by mutable reference, yielding their positions
+/// (for ranges, the positions of their starts) and mutable references to them in ascending order.
+pub struct ValuesMut<'a, Kind: 'a, S: Spacing + 'a, T: 'a> {
+    refs: SkeletonRefsMut<'a, Kind, S, T>,
+    len: usize,
+    links_per_element: usize,
+}
+
+impl<'a, Kind, S: Spacing, T> ValuesMut<'a, Kind, S, T> {
+    pub(crate) fn new(root: &Rc<RefCell<Skeleton<Kind, S, T>>>, len: usize, links_per_element: usize) -> Self {
+        let refs = SkeletonRefsMutBuilder {
+            skeletons: Skeleton::occupied_skeletons(root),
+            guards_builder: |skeletons: &Skeletons<Kind, S, T>|
+                skeletons.iter().map(|skeleton| skeleton.borrow_mut()).collect(),
+            list: PhantomData,
+        }.build();
+        Self { refs, len, links_per_element }
+    }
+
+    pub fn iter(&self) -> ValuesIter<'_, Kind, S, T> {
+        let levels = self.refs.borrow_guards().iter().map(|guard| Level::new(guard)).collect();
+        ValuesIter { walk: Walk::new(levels, self.len, self.links_per_element) }
+    }
+
+    pub fn iter_mut(&mut self) -> ValuesIterMut<'_, Kind, S, T> {
+        let levels = self.refs.with_guards_mut(|guards| guards.iter_mut().map(|guard| Level::new_mut(guard)).collect());
+        ValuesIterMut { walk: Walk::new(levels, self.len, self.links_per_element) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<'v, Kind, S: Spacing, T> IntoIterator for &'v ValuesMut<'_, Kind, S, T> {
+    type Item = (S, &'v T);
+    type IntoIter = ValuesIter<'v, Kind, S, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'v, Kind, S: Spacing, T> IntoIterator for &'v mut ValuesMut<'_, Kind, S, T> {
+    type Item = (S, &'v mut T);
+    type IntoIter = ValuesIterMut<'v, Kind, S, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// An iterator over the elements borrowed by [`Values`] (or [`ValuesMut`]), see there.
+pub struct ValuesIter<'v, Kind, S: Spacing, T> {
+    walk: Walk<'v, Kind, S, T, &'v [ElementSlot<T>]>,
+}
+
+impl<'v, Kind, S: Spacing, T> Iterator for ValuesIter<'v, Kind, S, T> {
+    type Item = (S, &'v T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.walk.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.walk.remaining, Some(self.walk.remaining))
+    }
+}
+
+impl<Kind, S: Spacing, T> ExactSizeIterator for ValuesIter<'_, Kind, S, T> {}
+
+impl<Kind, S: Spacing, T> FusedIterator for ValuesIter<'_, Kind, S, T> {}
+
+/// An iterator over the elements borrowed by [`ValuesMut`], see there.
+pub struct ValuesIterMut<'v, Kind, S: Spacing, T> {
+    walk: Walk<'v, Kind, S, T, &'v mut [ElementSlot<T>]>,
+}
+
+impl<'v, Kind, S: Spacing, T> Iterator for ValuesIterMut<'v, Kind, S, T> {
+    type Item = (S, &'v mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.walk.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.walk.remaining, Some(self.walk.remaining))
+    }
+}
+
+impl<Kind, S: Spacing, T> ExactSizeIterator for ValuesIterMut<'_, Kind, S, T> {}
+
+impl<Kind, S: Spacing, T> FusedIterator for ValuesIterMut<'_, Kind, S, T> {}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{RangeSpacedList, SpacedList};
+
+    #[test]
+    fn values_match_positions() {
+        for seed in 0..4 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut list: SpacedList<i64, u32> = SpacedList::new();
+            let mut ranges: RangeSpacedList<i64, u32> = RangeSpacedList::new();
+            let mut indices = vec![];
+            let mut range_indices = vec![];
+            for element in 0..1_000 {
+                // inserting out of order puts elements into subs
+                let position = rng.gen_range(0..5_000);
+                indices.push(list.insert(position, element).index());
+                if let Ok(range) = ranges.try_insert(position, position + rng.gen_range(0..3), element) {
+                    range_indices.push(range.index());
+                }
+            }
+            for index in indices.into_iter().filter(|_| rng.gen_bool(0.5)) {
+                list.remove(index);
+            }
+            for index in range_indices.into_iter().filter(|_| rng.gen_bool(0.5)) {
+                ranges.remove(index);
+            }
+
+            let expected = list.iter().map(|position| (position.position(), position.element().unwrap())).collect_vec();
+            let values = list.iter_values();
+            assert_eq!(values.len(), expected.len());
+            assert_eq!(values.iter().len(), expected.len());
+            assert_eq!(values.iter().map(|(position, &value)| (position, value)).collect_vec(), expected);
+            drop(values);
+            for (_, value) in &mut list.iter_values_mut() {
+                *value += 1;
+            }
+            assert_eq!(list.iter().map(|position| (position.position(), position.element().unwrap() - 1)).collect_vec(),
+                       expected);
+
+            let expected = ranges.iter_ranges()
+                .map(|(start, _)| (start.position(), start.element().unwrap()))
+                .collect_vec();
+            let values = ranges.iter_values();
+            assert_eq!(values.iter().len(), expected.len());
+            assert_eq!((&values).into_iter().map(|(position, &value)| (position, value)).collect_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn values_of_borrowed_elements() {
+        let words = ["b".to_string(), "a".to_string()];
+        let mut list: SpacedList<i32, &str> = SpacedList::new();
+        list.insert(4, &words[0]);
+        list.insert(2, &words[1]);
+        assert_eq!(list.iter_values().iter().map(|(position, &word)| (position, word)).collect_vec(), [(2, "a"), (4, "b")]);
+        for (_, word) in &mut list.iter_values_mut() {
+            *word = &words[0];
+        }
+        assert_eq!(list.iter_values().iter().map(|(position, &word)| (position, word)).collect_vec(), [(2, "b"), (4, "b")]);
+    }
+}
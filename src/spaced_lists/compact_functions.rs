@@ -0,0 +1,23 @@
+macro_rules! compact_functions {
+    ($kind:ident) => {
+        /// Rebuilds this list without any of the empty element slots and unnecessary subs that
+        /// removals and insertions leave behind. Indices into this list stay valid, positions
+        /// don't.
+        pub fn compact(&mut self) -> Compaction {
+            self.shortcuts.get_mut().clear();
+            let (skeleton, compaction) = Skeleton::<$kind, _, _>::compact(self.skeleton.clone());
+            self.skeleton = skeleton;
+            compaction
+        }
+
+        /// Compacts this list (see [`Self::compact`]) and frees the memory it has allocated, but
+        /// doesn't use.
+        pub fn shrink_to_fit(&mut self) -> Compaction {
+            let compaction = self.compact();
+            Skeleton::shrink_to_fit(self.skeleton.clone());
+            compaction
+        }
+    };
+}
+
+pub(super) use compact_functions;
@@ -0,0 +1,167 @@
+macro_rules! construction_functions {
+    (Node; ($($T:ident)?)) => {
+        /// Builds a spaced list from elements at the given positions, which must be sorted.
+        /// This takes linear time, unlike inserting the elements one at a time.
+        pub fn from_sorted_iter(elements: impl IntoIterator<Item=construction_functions!(@item (S) ($($T)?))>)
+                                -> Result<Self, FromSortedIterError> {
+            let elements = elements.into_iter()
+                .map(|construction_functions!(@pattern (position) value ($($T)?))| (position, construction_functions!(@value value ($($T)?))));
+            Ok(Self::from_skeleton(Skeleton::<Node, _, _>::from_sorted(elements)?))
+        }
+
+        /// Builds a spaced list from elements with the given spacings, each from the element
+        /// before it (or from zero, for the first one), like [`Self::push`] does. This takes
+        /// linear time.
+        pub fn from_spacings(elements: impl IntoIterator<Item=construction_functions!(@item (S) ($($T)?))>)
+                             -> Result<Self, FromSortedIterError> {
+            let mut position = zero();
+            Self::from_sorted_iter(elements.into_iter()
+                .map(|construction_functions!(@pattern (spacing) value ($($T)?))| {
+                    position += spacing;
+                    construction_functions!(@tuple (position) value ($($T)?))
+                }))
+        }
+
+        /// Appends the given elements, which must be sorted and positioned at or after the end of
+        /// this list, by building a list of them in one go (see [`Self::from_sorted_iter`]).
+        fn extend_sorted(&mut self, elements: Vec<construction_functions!(@item (S) ($($T)?))>) {
+            if elements.is_empty() {
+                return;
+            }
+            let mut tail = display_unwrap!(Self::from_sorted_iter(elements));
+            let spacing = if self.is_empty() { tail.start() } else { tail.start() - self.end() };
+            self.append(&mut tail, spacing);
+        }
+    };
+    ($range_kind:ident; ($($T:ident)?)) => {
+        /// Builds a spaced list from ranges with the given starts and ends, which must be sorted
+        /// by their start
+        #[doc = construction_functions!(@nesting $range_kind)]
+        /// This takes linear time, unlike inserting the ranges one at a time.
+        pub fn from_sorted_iter(ranges: impl IntoIterator<Item=construction_functions!(@item (S, S) ($($T)?))>)
+                                -> Result<Self, FromSortedIterError> {
+            Self::from_sorted_iter_with_span(ranges.into_iter()
+                .map(|construction_functions!(@pattern (start, end) value ($($T)?))|
+                    construction_functions!(@tuple (start, end - start) value ($($T)?))))
+        }
+
+        /// Like [`Self::from_sorted_iter`], but takes the span of each range instead of its end.
+        pub fn from_sorted_iter_with_span(ranges: impl IntoIterator<Item=construction_functions!(@item (S, S) ($($T)?))>)
+                                          -> Result<Self, FromSortedIterError> {
+            let ranges = ranges.into_iter()
+                .map(|construction_functions!(@pattern (start, span) value ($($T)?))|
+                    (start, span, construction_functions!(@value value ($($T)?))));
+            Ok(Self::from_skeleton(Skeleton::<$range_kind, _, _>::from_sorted(ranges)?))
+        }
+
+        /// Builds a spaced list from ranges with the given spacings, each from the end of the
+        /// range before it (or from zero, for the first one), and spans, like [`Self::push`]
+        /// does. This takes linear time.
+        pub fn from_spacings(ranges: impl IntoIterator<Item=construction_functions!(@item (S, S) ($($T)?))>)
+                             -> Result<Self, FromSortedIterError> {
+            let mut end = zero();
+            Self::from_sorted_iter_with_span(ranges.into_iter()
+                .map(|construction_functions!(@pattern (spacing, span) value ($($T)?))| {
+                    let start = end + spacing;
+                    end = start + span;
+                    construction_functions!(@tuple (start, span) value ($($T)?))
+                }))
+        }
+
+        /// Appends the given ranges, which must be sorted, must not intersect and must start at or
+        /// after the end of this list, by building a list of them in one go (see
+        /// [`Self::from_sorted_iter`]).
+        fn extend_sorted(&mut self, ranges: Vec<construction_functions!(@item (S, S) ($($T)?))>) {
+            if ranges.is_empty() {
+                return;
+            }
+            let mut tail = display_unwrap!(Self::from_sorted_iter(ranges));
+            let spacing = if self.is_empty() { tail.start() } else { tail.start() - self.end() };
+            self.append(&mut tail, spacing);
+        }
+    };
+    (@item ($S:ident) ()) => { $S };
+    (@item ($($S:ident),+) ()) => { ($($S),+) };
+    (@item ($($S:ident),+) ($T:ident)) => { ($($S),+, $T) };
+    (@pattern ($single:ident) $value:ident ()) => { $single };
+    (@pattern ($($S:ident),+) $value:ident ()) => { ($($S),+) };
+    (@pattern ($($S:ident),+) $value:ident ($T:ident)) => { ($($S),+, $value) };
+    (@tuple ($single:expr) $value:ident ()) => { $single };
+    (@tuple ($($S:expr),+) $value:ident ()) => { ($($S),+) };
+    (@tuple ($($S:expr),+) $value:ident ($T:ident)) => { ($($S),+, $value) };
+    (@value_type ()) => { () };
+    (@value_type ($T:ident)) => { $T };
+    (@value $value:ident ()) => { () };
+    (@value $value:ident ($T:ident)) => { $value };
+    (@nesting Range) => { " and must not intersect." };
+    (@nesting NestedRange) => { " and must not intersect, unless one contains the other. Outer ranges must come before the ranges inside of them." };
+}
+
+macro_rules! construction_trait_impls {
+    (Node; ($($T:ident)?), $type:ty) => {
+        /// Panics if the elements aren't sorted, see [`Self::from_sorted_iter`].
+        impl<S: Spacing$(, $T)?> FromIterator<construction_functions!(@item (S) ($($T)?))> for $type {
+            fn from_iter<I: IntoIterator<Item=construction_functions!(@item (S) ($($T)?))>>(elements: I) -> Self {
+                display_unwrap!(Self::from_sorted_iter(elements))
+            }
+        }
+
+        /// Builds the sorted runs of elements that are positioned at or after the end of the list
+        /// into a list of their own in one go and appends it, and inserts the others, so that
+        /// extending by sorted elements takes linear time.
+        impl<S: Spacing$(, $T)?> Extend<construction_functions!(@item (S) ($($T)?))> for $type {
+            fn extend<I: IntoIterator<Item=construction_functions!(@item (S) ($($T)?))>>(&mut self, elements: I) {
+                let mut tail = vec![];
+                // the position of the last element of the tail, if there is one
+                let mut tail_end = None;
+                for construction_functions!(@pattern (position) value ($($T)?)) in elements {
+                    if tail_end.is_some_and(|end| position < end) {
+                        self.extend_sorted(std::mem::take(&mut tail));
+                        tail_end = None;
+                    }
+                    if tail_end.is_some() || self.is_empty() || position >= self.end() {
+                        tail_end = Some(position);
+                        tail.push(construction_functions!(@tuple (position) value ($($T)?)));
+                    } else {
+                        self.insert(position$(, value ${ignore($T)})?);
+                    }
+                }
+                self.extend_sorted(tail);
+            }
+        }
+    };
+    ($range_kind:ident; ($($T:ident)?), $type:ty) => {
+        /// Panics if the ranges aren't sorted or intersect, see [`Self::from_sorted_iter`].
+        impl<S: Spacing$(, $T)?> FromIterator<construction_functions!(@item (S, S) ($($T)?))> for $type {
+            fn from_iter<I: IntoIterator<Item=construction_functions!(@item (S, S) ($($T)?))>>(ranges: I) -> Self {
+                display_unwrap!(Self::from_sorted_iter(ranges))
+            }
+        }
+
+        /// Builds the sorted runs of ranges that start at or after the end of the list into a list
+        /// of their own in one go and appends it, and inserts the others (panicking if they
+        /// intersect a range in the list), so that extending by sorted ranges takes linear time.
+        impl<S: Spacing$(, $T)?> Extend<construction_functions!(@item (S, S) ($($T)?))> for $type {
+            fn extend<I: IntoIterator<Item=construction_functions!(@item (S, S) ($($T)?))>>(&mut self, ranges: I) {
+                let mut tail = vec![];
+                // the end of the last range of the tail, if there is one
+                let mut tail_end = None;
+                for construction_functions!(@pattern (start, end) value ($($T)?)) in ranges {
+                    if tail_end.is_some_and(|tail_end| start < tail_end) {
+                        self.extend_sorted(std::mem::take(&mut tail));
+                        tail_end = None;
+                    }
+                    if tail_end.is_some() || self.is_empty() || start >= self.end() {
+                        tail_end = Some(end);
+                        tail.push(construction_functions!(@tuple (start, end) value ($($T)?)));
+                    } else {
+                        self.insert(start, end$(, value ${ignore($T)})?);
+                    }
+                }
+                self.extend_sorted(tail);
+            }
+        }
+    };
+}
+
+pub(super) use {construction_functions, construction_trait_impls};
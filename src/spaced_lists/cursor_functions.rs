@@ -0,0 +1,21 @@
+macro_rules! cursor_functions {
+    (Node) => {
+        /// Returns a cursor at the first element at or after the given position, or past the ends
+        /// of the list if there is none.
+        #[must_use]
+        pub fn cursor_at(&self, position: S) -> Cursor<'_, S, T> {
+            let current = Skeleton::<Node, _, _>::conditional_at_or_after(self.skeleton.clone(), position, |slot| slot.is_some());
+            Cursor::new(&self.skeleton, current)
+        }
+
+        /// Returns a cursor at the first element at or after the given position (or past the ends
+        /// of the list if there is none), which can edit the list around it.
+        pub fn cursor_mut(&mut self, position: S) -> CursorMut<'_, S, T> {
+            let current = Skeleton::<Node, _, _>::conditional_at_or_after(self.skeleton.clone(), position, |slot| slot.is_some());
+            CursorMut::new(&mut self.skeleton, self.shortcuts.get_mut(), current)
+        }
+    };
+    ($range_kind:ident) => {};
+}
+
+pub(super) use cursor_functions;
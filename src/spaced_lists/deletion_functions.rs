@@ -0,0 +1,38 @@
+macro_rules! deletion_functions {
+    (Node; ($($T:ident)?)) => {
+        // the parentheses are only unnecessary if there is no $T
+        #[allow(unused_parens)]
+        pub fn delete_region(&mut self, start: S, end: S) -> Vec<(S$(, $T)?)> {
+            display_unwrap!(self.try_delete_region(start, end))
+        }
+
+        /// Removes all elements at or after `start` and before `end`, and moves all elements after
+        /// them forwards by `end - start`, so that the gap closes. Returns the removed elements
+        /// with the positions they had, in ascending order.
+        #[allow(unused_parens)]
+        pub fn try_delete_region(&mut self, start: S, end: S) -> Result<Vec<(S$(, $T)?)>, DeletionError<S>> {
+            self.shortcuts.get_mut().clear();
+            let removed = Skeleton::<Node, _, _>::delete_region(&self.skeleton, start, end)?;
+            Ok(removed.into_iter().map(|(position, _element)| (position$(, _element ${ignore($T)})?)).collect())
+        }
+    };
+    ($range_kind:ident; ($($T:ident)?)) => {
+        pub fn delete_region(&mut self, start: S, end: S, policy: StraddlePolicy) -> Vec<(S, S$(, $T)?)> {
+            display_unwrap!(self.try_delete_region(start, end, policy))
+        }
+
+        /// Removes all ranges that lie between `start` and `end` (except for empty ranges at
+        /// `end`), and moves all ranges after them forwards by `end - start`, so that the gap
+        /// closes. Ranges that only partly lie between them are clipped, removed as well, or make
+        /// the deletion fail, depending on the given policy. Returns the removed ranges with the
+        /// positions they had, in ascending order of their starts.
+        pub fn try_delete_region(&mut self, start: S, end: S, policy: StraddlePolicy)
+                                 -> Result<Vec<(S, S$(, $T)?)>, DeletionError<S>> {
+            self.shortcuts.get_mut().clear();
+            let removed = Skeleton::<$range_kind, _, _>::delete_region(&self.skeleton, start, end, policy)?;
+            Ok(removed.into_iter().map(|(start, end, _element)| (start, end$(, _element ${ignore($T)})?)).collect())
+        }
+    };
+}
+
+pub(super) use deletion_functions;
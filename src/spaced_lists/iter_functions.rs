@@ -1,47 +1,133 @@
 macro_rules! iter_functions {
-    (Node; $position:ty) => {
-        pub fn iter(&self) -> impl Iterator<Item=$position> {
-            ForwardsIter::from_start(self.skeleton.clone()).filter(|pos| pos.ephemeral().element().is_some()).map_into()
+    (Node; ($($T:ident)?), $position:ty) => {
+        iter_functions!(@common Node; ($($T)?), $position);
+        range_functions!(Node; ($($T)?), , , None::<BoundType>, $position);
+    };
+    (@common $kind:ident; ($($T:ident)?), $position:ty) => {
+        pub fn iter(&self) -> Iter<$kind, S, iter_functions!(@value_type ($($T)?)), $position> {
+            Iter::new(self.skeleton.clone(), self.size() * iter_functions!(@bounds $kind))
+        }
+
+        pub fn iter_backwards(&self) -> Rev<Iter<$kind, S, iter_functions!(@value_type ($($T)?)), $position>> {
+            self.iter().rev()
+        }
+
+        pub fn into_iter_backwards(self) -> Rev<Iter<$kind, S, iter_functions!(@value_type ($($T)?)), $position>> {
+            self.into_iter().rev()
+        }
+    };
+    (Range; ($($T:ident)?), $position:ty) => {
+        iter_functions!(@common Range; ($($T)?), $position);
+        range_functions!(Range; ($($T)?), , starting_or_ending_, None::<BoundType>, $position);
+        range_functions!(Range; ($($T)?), starting_, starting_, Some(BoundType::Start), $position);
+        range_functions!(Range; ($($T)?), ending_, ending_, Some(BoundType::End), $position);
+
+        pub fn iter_ranges(&self) -> Ranges<Range, S, iter_functions!(@value_type ($($T)?)), $position> {
+            Ranges::new(self.iter())
         }
 
-        // covered by a to-do item somewhere else
-        #[allow(clippy::should_implement_trait)]
-        pub fn into_iter(self) -> impl Iterator<Item=$position> {
-            ForwardsIter::from_start(self.skeleton).filter(|pos| pos.ephemeral().element().is_some()).map_into()
+        pub fn into_iter_ranges(self) -> Ranges<Range, S, iter_functions!(@value_type ($($T)?)), $position> {
+            Ranges::new(self.into_iter())
         }
 
-        pub fn iter_backwards(&self) -> impl Iterator<Item=$position> {
-            BackwardsIter::from_end(self.skeleton.clone()).filter(|pos| pos.ephemeral().element().is_some()).map_into()
+        pub fn iter_ranges_backwards(&self) -> Rev<Ranges<Range, S, iter_functions!(@value_type ($($T)?)), $position>> {
+            self.iter_ranges().rev()
         }
 
-        pub fn into_iter_backwards(self) -> impl Iterator<Item=$position> {
-            BackwardsIter::from_end(self.skeleton).filter(|pos| pos.ephemeral().element().is_some()).map_into()
+        pub fn into_iter_ranges_backwards(self) -> Rev<Ranges<Range, S, iter_functions!(@value_type ($($T)?)), $position>> {
+            self.into_iter_ranges().rev()
         }
     };
-    (Range; $position:ty) => {
-        iter_functions!(Node; $position);
+    (NestedRange; ($($T:ident)?), $position:ty) => {
+        iter_functions!(@common NestedRange; ($($T)?), $position);
+        range_functions!(NestedRange; ($($T)?), , starting_or_ending_, None::<BoundType>, $position);
+        range_functions!(NestedRange; ($($T)?), starting_, starting_, Some(BoundType::Start), $position);
+        range_functions!(NestedRange; ($($T)?), ending_, ending_, Some(BoundType::End), $position);
+
+        /// Returns an iterator over the ranges, as their start, their end and the number of
+        /// ranges containing them, in the given order.
+        pub fn iter_ranges(&self, order: RangeOrder) -> NestedRanges<S, iter_functions!(@value_type ($($T)?)), $position> {
+            NestedRanges::new(Iter::new(self.skeleton.clone(), self.size() * 2), self.size(), order)
+        }
 
-        pub fn iter_ranges(&self) -> impl Iterator<Item=($position, $position)> {
-            self.iter().tuples()
+        pub fn into_iter_ranges(self, order: RangeOrder) -> NestedRanges<S, iter_functions!(@value_type ($($T)?)), $position> {
+            let size = self.size();
+            NestedRanges::new(Iter::new(self.skeleton, size * 2), size, order)
         }
 
-        pub fn into_iter_ranges(self) -> impl Iterator<Item=($position, $position)> {
-            self.into_iter().tuples()
+        /// Returns an iterator over the ranges like [`Self::iter_ranges`], but in reverse, so in
+        /// pre-order, ranges come in descending order of their starts, and in post-order, in
+        /// descending order of their ends.
+        pub fn iter_ranges_backwards(&self, order: RangeOrder) -> Rev<NestedRanges<S, iter_functions!(@value_type ($($T)?)), $position>> {
+            self.iter_ranges(order).rev()
         }
 
-        pub fn iter_ranges_backwards(&self) -> impl Iterator<Item=($position, $position)> {
-            self.iter().tuples()
+        pub fn into_iter_ranges_backwards(self, order: RangeOrder) -> Rev<NestedRanges<S, iter_functions!(@value_type ($($T)?)), $position>> {
+            self.into_iter_ranges(order).rev()
+        }
+    };
+    (@value_type ()) => { () };
+    (@value_type ($T:ident)) => { $T };
+    // the number of bounds of each element, which are iterated over separately
+    (@bounds Node) => { 1 };
+    (@bounds $range_kind:ident) => { 2 };
+}
+
+macro_rules! iter_trait_impls {
+    ($kind:ident; ($($T:ident)?), $type:ty, $position:ty) => {
+        impl<S: Spacing$(, $T)?> IntoIterator for $type {
+            type Item = $position;
+            type IntoIter = Iter<$kind, S, iter_functions!(@value_type ($($T)?)), $position>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                let len = self.size() * iter_functions!(@bounds $kind);
+                Iter::new(self.skeleton, len)
+            }
         }
 
-        pub fn into_iter_ranges_backwards(self) -> impl Iterator<Item=($position, $position)> {
-            self.into_iter().tuples()
+        impl<'a, S: Spacing$(, $T)?> IntoIterator for &'a $type {
+            type Item = $position;
+            type IntoIter = Iter<$kind, S, iter_functions!(@value_type ($($T)?)), $position>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.iter()
+            }
         }
     };
-    (NestedRange; $position:ty) => {
-        iter_functions!(Node; $position);
+}
+
+/// Generates functions iterating over the elements within a range of positions, which are named
+/// after `$function_prefix` and use the traversal functions named after `$traversal_prefix` to
+/// find the first and last element. If `$bound` is given, only bounds of that type are included.
+macro_rules! range_functions {
+    ($kind:ident; ($($T:ident)?), $($function_prefix:ident)?, $($traversal_prefix:ident)?, $bound:expr, $position:ty) => {
+        paste! {
+            /// Returns an iterator over the elements positioned within the given range, in
+            /// ascending order. Finding the first and last element takes logarithmic time, and
+            /// iteration stops at the end of the range (backwards, at its start).
+            pub fn [< $($function_prefix)? range >](&self, range: impl RangeBounds<S>)
+                -> Window<$kind, S, iter_functions!(@value_type ($($T)?)), $position> {
+                let first = match range.start_bound() {
+                    Bound::Included(&start) => self.[< $($traversal_prefix)? at_or_after >](start),
+                    Bound::Excluded(&start) => self.[< $($traversal_prefix)? after >](start),
+                    Bound::Unbounded => self.first(),
+                };
+                let last = match range.end_bound() {
+                    Bound::Included(&end) => self.[< $($traversal_prefix)? at_or_before >](end),
+                    Bound::Excluded(&end) => self.[< $($traversal_prefix)? before >](end),
+                    Bound::Unbounded => self.last(),
+                };
+                Window::new(first.map(Into::into), last.map(Into::into), range, $bound)
+            }
 
-        // TODO nested iter_ranges
-    }
+            /// Returns an iterator over the elements positioned within the given range, in
+            /// descending order, like [`Self::range`] in reverse.
+            pub fn [< $($function_prefix)? range_backwards >](&self, range: impl RangeBounds<S>)
+                -> Rev<Window<$kind, S, iter_functions!(@value_type ($($T)?)), $position>> {
+                self.[< $($function_prefix)? range >](range).rev()
+            }
+        }
+    };
 }
 
-pub(super) use iter_functions;
\ No newline at end of file
+pub(super) use {iter_functions, iter_trait_impls, range_functions};
\ No newline at end of file
@@ -1,36 +1,68 @@
 use std::rc::Rc;
 use std::ops::Deref;
-use std::cell::{RefCell};
-use crate::{ElementSlot, SpacingError, BackwardsIter, display_unwrap, ForwardsIter, HollowPosition, NestedRange, NestedRangeInsertionError, NestedRangePushError, Node, Position, PushError, Range, RangeInsertionError, RangePushError, Skeleton, Spacing, Index};
+use std::cell::{Ref, RefCell};
+use std::convert::Infallible;
+use std::iter::Rev;
+use std::ops::{Bound, RangeBounds};
+use crate::{ElementSlot, SpacingError, SpacingEdit, display_unwrap, HollowPosition, NestedRange, NestedRangeInsertionError, NestedRangePushError, Node, Position, PushError, Range, RangeInsertionError, RangePushError, Skeleton, Spacing, Index, HollowIndex, RemovalError, Compaction, BoundType, FromSortedIterError, BatchInsertionError, RelativeInsertionError, EphemeralPosition, DeletionError, StraddlePolicy, SplitError, AppendError};
+use crate::skeleton::insertion::Inserted;
+use crate::skeleton::shortcuts::{Search, Shortcuts};
+use crate::skeleton::nesting::{NestedRanges, RangeOrder};
+use crate::skeleton::traversal::iteration::{Iter, Ranges, Window, RangeWindow};
+use crate::skeleton::values::{Values, ValuesMut};
+use crate::skeleton::cursor::{Cursor, CursorMut};
+use num_traits::zero;
 use paste::paste;
 use itertools::Itertools;
-use push_insert_functions::push_insert_functions;
-use spacing_functions::spacing_functions;
+use push_insert_functions::{push_insert_functions, batch_functions, relative_insert_functions};
+use spacing_functions::{spacing_functions, element_spacing_functions};
 use trivial_accessors::trivial_accessors;
 use first_last_functions::first_last_functions;
-use traversal_functions::{unconditional_traversal_function, conditional_traversal_function, all_traversal_functions};
-use iter_functions::iter_functions;
+use remove_functions::remove_functions;
+use traversal_functions::{shortcut_traversal, unconditional_traversal_function, conditional_traversal_function, all_traversal_functions};
+use iter_functions::{iter_functions, iter_trait_impls, range_functions};
+use compact_functions::compact_functions;
+use shortcut_functions::shortcut_functions;
+use construction_functions::{construction_functions, construction_trait_impls};
+use rank_functions::rank_functions;
+use overlap_functions::overlap_functions;
+use nesting_functions::nesting_functions;
+use value_functions::value_functions;
+use cursor_functions::cursor_functions;
+use deletion_functions::deletion_functions;
+use split_functions::split_functions;
 
 // TODO do any of these functions actually _need_ a mutable self parameter?
 mod push_insert_functions;
 mod spacing_functions;
 mod trivial_accessors;
 mod first_last_functions;
+mod remove_functions;
 mod traversal_functions;
 mod iter_functions;
+mod compact_functions;
+mod shortcut_functions;
+mod construction_functions;
+mod rank_functions;
+mod overlap_functions;
+mod nesting_functions;
+mod value_functions;
+mod cursor_functions;
+mod deletion_functions;
+mod split_functions;
 
 macro_rules! spaced_list {
-    ($kind:ident; $name:ident, ($($T:ident)?), $type:ty, $skeleton:ty, $position_ident:ident, $position:ty) => {
+    ($kind:ident; $name:ident, ($($T:ident)?), $type:ty, $skeleton:ty, $shortcuts:ty, $position:ty, $index:ty) => {
         pub struct $name<S: Spacing$(, $T)?> {
-            skeleton: Rc<RefCell<$skeleton>>,
-            size: usize,
+            pub(crate) skeleton: Rc<RefCell<$skeleton>>,
+            shortcuts: RefCell<$shortcuts>,
         }
 
         impl<S: Spacing$(, $T)?> Default for $type {
             fn default() -> Self {
                 Self {
                     skeleton: Skeleton::new(None),
-                    size: 0,
+                    shortcuts: RefCell::default(),
                 }
             }
         }
@@ -41,27 +73,44 @@ macro_rules! spaced_list {
                 Self::default()
             }
 
+            fn from_skeleton(skeleton: Rc<RefCell<$skeleton>>) -> Self {
+                Self {
+                    skeleton,
+                    shortcuts: RefCell::default(),
+                }
+            }
+
+            construction_functions!($kind; ($($T)?));
+
             push_insert_functions!($kind; ($($T)?), $position);
+            relative_insert_functions!($kind; ($($T)?), $position, $index);
+            remove_functions!($kind; ($($T)?), $index);
+            deletion_functions!($kind; ($($T)?));
+            split_functions!($kind; ($($T)?));
             spacing_functions!();
+            element_spacing_functions!($kind, $index);
             trivial_accessors!();
-            first_last_functions!($position_ident, $position);
+            first_last_functions!($position);
+            rank_functions!($position);
             all_traversal_functions!($kind; unconditional_, $position);
             $(all_traversal_functions!($kind; conditional_, $position); ${ignore($T)})?
-            iter_functions!($kind; $position);
+            iter_functions!($kind; ($($T)?), $position);
+            $(value_functions!($kind); ${ignore($T)})?
+            $(cursor_functions!($kind); ${ignore($T)})?
+            overlap_functions!($kind; ($($T)?), $position);
+            nesting_functions!($kind; $position);
+            compact_functions!($kind);
+            shortcut_functions!();
         }
-    }
-}
 
-spaced_list!(Node; SpacedList, (T), SpacedList<S, T>, Skeleton<Node, S, T>, Position, Position<Node, S, T>);
-spaced_list!(Range; RangeSpacedList, (T), RangeSpacedList<S, T>, Skeleton<Range, S, T>, Position, Position<Range, S, T>);
-spaced_list!(NestedRange; NestedRangeSpacedList, (T), NestedRangeSpacedList<S, T>, Skeleton<NestedRange, S, T>, Position, Position<NestedRange, S, T>);
-spaced_list!(Node; HollowSpacedList, (), HollowSpacedList<S>, Skeleton<Node, S, ()>, HollowPosition, HollowPosition<Node, S>);
-spaced_list!(Range; HollowRangeSpacedList, (), HollowRangeSpacedList<S>, Skeleton<Range, S, ()>, HollowPosition, HollowPosition<Range, S>);
-spaced_list!(NestedRange; HollowNestedRangeSpacedList, (), HollowNestedRangeSpacedList<S>, Skeleton<NestedRange, S, ()>, HollowPosition, HollowPosition<NestedRange, S>);
-
-impl<S: Spacing, T> SpacedList<S, T> {
-    pub fn remove(&mut self, index: Index<Node, S, T>) -> Option<T> {
-        let ephemeral = index.ephemeral();
-        Skeleton::remove(ephemeral.skeleton.clone(), ephemeral)
+        construction_trait_impls!($kind; ($($T)?), $type);
+        iter_trait_impls!($kind; ($($T)?), $type, $position);
     }
 }
+
+spaced_list!(Node; SpacedList, (T), SpacedList<S, T>, Skeleton<Node, S, T>, Shortcuts<Node, S, T>, Position<Node, S, T>, Index<Node, S, T>);
+spaced_list!(Range; RangeSpacedList, (T), RangeSpacedList<S, T>, Skeleton<Range, S, T>, Shortcuts<Range, S, T>, Position<Range, S, T>, Index<Range, S, T>);
+spaced_list!(NestedRange; NestedRangeSpacedList, (T), NestedRangeSpacedList<S, T>, Skeleton<NestedRange, S, T>, Shortcuts<NestedRange, S, T>, Position<NestedRange, S, T>, Index<NestedRange, S, T>);
+spaced_list!(Node; HollowSpacedList, (), HollowSpacedList<S>, Skeleton<Node, S, ()>, Shortcuts<Node, S, ()>, HollowPosition<Node, S>, HollowIndex<Node, S>);
+spaced_list!(Range; HollowRangeSpacedList, (), HollowRangeSpacedList<S>, Skeleton<Range, S, ()>, Shortcuts<Range, S, ()>, HollowPosition<Range, S>, HollowIndex<Range, S>);
+spaced_list!(NestedRange; HollowNestedRangeSpacedList, (), HollowNestedRangeSpacedList<S>, Skeleton<NestedRange, S, ()>, Shortcuts<NestedRange, S, ()>, HollowPosition<NestedRange, S>, HollowIndex<NestedRange, S>);
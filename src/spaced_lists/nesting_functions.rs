@@ -0,0 +1,65 @@
+macro_rules! nesting_functions {
+    (NestedRange; $position:ty) => {
+        /// Returns the start of the innermost range that contains the given range, or `None` if
+        /// the given range is on the top level. The given position can be either bound of a range.
+        #[must_use]
+        pub fn parent(&self, position: &$position) -> Option<$position> {
+            Skeleton::parent_range(position.ephemeral().into_range().0).map(Into::into)
+        }
+
+        /// Returns the starts of the ranges containing the given range, from the innermost to the
+        /// outermost one.
+        pub fn ancestors(&self, position: &$position) -> impl Iterator<Item=$position> {
+            std::iter::successors(Skeleton::parent_range(position.ephemeral().into_range().0), |parent|
+                Skeleton::parent_range(parent.clone()))
+                .map(Into::into)
+        }
+
+        /// Returns the number of ranges containing the given range, which is zero for ranges on
+        /// the top level.
+        #[must_use]
+        pub fn depth(&self, position: &$position) -> usize {
+            self.ancestors(position).count()
+        }
+
+        /// Returns the starts of the ranges directly inside the given range, in ascending order.
+        pub fn children(&self, position: &$position) -> impl Iterator<Item=$position> {
+            Skeleton::inner_ranges(&position.ephemeral().into_range().0).map_into()
+        }
+
+        /// Returns the starts of the ranges on the top level, which aren't contained in any other
+        /// range, in ascending order.
+        pub fn roots(&self) -> impl Iterator<Item=$position> {
+            Skeleton::top_level_ranges(self.skeleton.clone()).map_into()
+        }
+
+        /// Returns the starts of the other ranges with the same parent as the given range (see
+        /// [`Self::parent`]), or on the top level as well, in ascending order.
+        pub fn siblings(&self, position: &$position) -> impl Iterator<Item=$position> {
+            let start = position.ephemeral().into_range().0;
+            let level = match Skeleton::parent_range(start.clone()) {
+                Some(parent) => Skeleton::inner_ranges(&parent),
+                None => Skeleton::top_level_ranges(self.skeleton.clone()),
+            };
+            level
+                .filter(move |sibling| !(Rc::ptr_eq(&sibling.skeleton, &start.skeleton) && sibling.index == start.index))
+                .map_into()
+        }
+
+        /// Returns the start of the innermost range that contains the given position, including
+        /// ranges that start or end at it.
+        #[must_use]
+        pub fn innermost_containing(&self, position: S) -> Option<$position> {
+            // the last range starting at or before the position is either inside the innermost
+            // range containing the position, or is that range itself
+            let mut range = self.starting_at_or_before(position)?.ephemeral();
+            while range.position + range.span() < position {
+                range = Skeleton::parent_range(range)?;
+            }
+            Some(range.into())
+        }
+    };
+    ($kind:ident; $position:ty) => {};
+}
+
+pub(super) use nesting_functions;
@@ -0,0 +1,52 @@
+macro_rules! overlap_functions {
+    (Range; ($($T:ident)?), $position:ty) => {
+        /// Returns the ranges that contain the given position, as pairs of their start and end, in
+        /// ascending order. Ranges contain their start and end, so there can be more than one if
+        /// ranges touch at the position.
+        pub fn containing(&self, position: S) -> RangeWindow<Range, S, iter_functions!(@value_type ($($T)?)), $position> {
+            self.overlapping(position..=position)
+        }
+
+        /// Returns the ranges that share at least one position with the given range, as pairs of
+        /// their start and end, in ascending order. Ranges contain their start and end, so ranges
+        /// that only touch an included bound of the given range overlap it, and ranges that only
+        /// touch an excluded bound don't. An empty range (such as `a..a`) contains no positions,
+        /// so nothing overlaps it. Finding the first and last range takes logarithmic time.
+        pub fn overlapping(&self, range: impl RangeBounds<S>) -> RangeWindow<Range, S, iter_functions!(@value_type ($($T)?)), $position> {
+            // as ranges don't overlap each other, the first range ending in the given range is
+            // the first one overlapping it, and the last one starting in it is the last one
+            let first = match range.start_bound() {
+                Bound::Included(&start) => self.ending_at_or_after(start).map(|end| end.into_range().0),
+                Bound::Excluded(&start) => self.ending_after(start).map(|end| end.into_range().0),
+                Bound::Unbounded => self.first(),
+            };
+            let last = match range.end_bound() {
+                Bound::Included(&end) => self.starting_at_or_before(end).map(|start| start.into_range().1),
+                Bound::Excluded(&end) => self.starting_before(end).map(|start| start.into_range().1),
+                Bound::Unbounded => self.last(),
+            };
+            RangeWindow::new(first.map(Into::into), last.map(Into::into), range, false)
+        }
+
+        /// Returns the ranges whose start and end both lie within the given range, as pairs of
+        /// their start and end, in ascending order. Finding the first and last range takes
+        /// logarithmic time.
+        pub fn contained_in(&self, range: impl RangeBounds<S>) -> RangeWindow<Range, S, iter_functions!(@value_type ($($T)?)), $position> {
+            // as ranges don't overlap each other, their ends are in the same order as their starts
+            let first = match range.start_bound() {
+                Bound::Included(&start) => self.starting_at_or_after(start),
+                Bound::Excluded(&start) => self.starting_after(start),
+                Bound::Unbounded => self.first(),
+            };
+            let last = match range.end_bound() {
+                Bound::Included(&end) => self.ending_at_or_before(end),
+                Bound::Excluded(&end) => self.ending_before(end),
+                Bound::Unbounded => self.last(),
+            };
+            RangeWindow::new(first.map(Into::into), last.map(Into::into), range, true)
+        }
+    };
+    ($kind:ident; ($($T:ident)?), $position:ty) => {};
+}
+
+pub(super) use overlap_functions;
\ No newline at end of file
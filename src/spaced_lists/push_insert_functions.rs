@@ -6,14 +6,37 @@ macro_rules! push_insert_functions {
 
         // cannot fail
         pub fn insert(&mut self, position: S$(, value: $T)?) -> $position {
-            self.size += 1;
-            Skeleton::<Node, _, _>::insert(self.skeleton.clone(), position, ($(value ${ignore($T)})?)).into()
+            self.shortcuts.get_mut().clear();
+            self.skeleton = Skeleton::make_room(self.skeleton.clone());
+            let inserted = Skeleton::<Node, _, _>::insert(self.skeleton.clone(), position, ($(value ${ignore($T)})?));
+            let (skeleton, position) = Skeleton::<Node, _, _>::balance(self.skeleton.clone(), inserted);
+            self.skeleton = skeleton;
+            position.into()
         }
 
         pub fn try_push(&mut self, spacing: S$(, value: $T)?) -> Result<$position, PushError> {
-            self.size += 1;
+            self.shortcuts.get_mut().clear();
+            self.skeleton = Skeleton::make_room(self.skeleton.clone());
             Ok(Skeleton::<Node, _, _>::try_push(self.skeleton.clone(), spacing, ($(value ${ignore($T)})?))?.into())
         }
+
+        pub fn insert_sorted_batch(&mut self, elements: impl IntoIterator<Item=construction_functions!(@item (S) ($($T)?))>)
+                                   -> Vec<$position> {
+            display_unwrap!(self.try_insert_sorted_batch(elements))
+        }
+
+        /// Inserts elements at the given positions, which must be sorted. The list is walked once
+        /// alongside the elements, so this is faster than inserting the elements one at a time. Returns the positions of the inserted elements, in order. If the elements aren't
+        /// sorted, none of them are inserted.
+        pub fn try_insert_sorted_batch(&mut self, elements: impl IntoIterator<Item=construction_functions!(@item (S) ($($T)?))>)
+                                       -> Result<Vec<$position>, BatchInsertionError<Infallible>> {
+            self.insert_sorted_batch_with(elements.into_iter()
+                .map(|construction_functions!(@pattern (position) value ($($T)?))|
+                    (position, position, construction_functions!(@value value ($($T)?)))),
+                |skeleton, position, _, element| Ok(Skeleton::<Node, _, _>::insert(skeleton, position, element)))
+        }
+
+        batch_functions!(Node; ($($T)?), $position);
     };
     ($range_kind:ident; ($($T:ident)?), $position:ty) => {
         paste! {
@@ -30,7 +53,8 @@ macro_rules! push_insert_functions {
             }
 
             pub fn try_push(&mut self, spacing: S, span: S$(, value: $T)?) -> Result<$position, [< $range_kind PushError >]> {
-                self.size += 1;
+                self.shortcuts.get_mut().clear();
+                self.skeleton = Skeleton::make_room(self.skeleton.clone());
                 Ok(Skeleton::<$range_kind, _, _>::try_push(self.skeleton.clone(), spacing, span, ($(value ${ignore($T)})?))?.into())
             }
 
@@ -39,11 +63,223 @@ macro_rules! push_insert_functions {
             }
 
             pub fn try_insert_with_span(&mut self, start: S, span: S$(, value: $T)?) -> Result<$position, [< $range_kind InsertionError >]> {
-                self.size += 1;
-                Ok(Skeleton::<$range_kind, _, _>::try_insert(self.skeleton.clone(), start, span, ($(value ${ignore($T)})?))?.into())
+                self.shortcuts.get_mut().clear();
+                self.skeleton = Skeleton::make_room(self.skeleton.clone());
+                let inserted = Skeleton::<$range_kind, _, _>::try_insert(self.skeleton.clone(), start, span, ($(value ${ignore($T)})?))?;
+                let (skeleton, position) = Skeleton::<$range_kind, _, _>::balance(self.skeleton.clone(), inserted);
+                self.skeleton = skeleton;
+                Ok(position.into())
+            }
+
+            pub fn insert_sorted_batch(&mut self, ranges: impl IntoIterator<Item=construction_functions!(@item (S, S) ($($T)?))>)
+                                       -> Vec<$position> {
+                display_unwrap!(self.try_insert_sorted_batch(ranges))
+            }
+
+            /// Inserts ranges with the given starts and ends, which must be sorted by their start.
+            /// The list is walked once alongside the ranges, so this is faster than inserting the
+            /// ranges one at a time. Returns the positions of the inserted ranges (of their
+            /// starts), in order. If the ranges aren't sorted, or one of them can't be inserted,
+            /// none of them are.
+            pub fn try_insert_sorted_batch(&mut self, ranges: impl IntoIterator<Item=construction_functions!(@item (S, S) ($($T)?))>)
+                                           -> Result<Vec<$position>, BatchInsertionError<[< $range_kind InsertionError >]>> {
+                self.insert_sorted_batch_with(ranges.into_iter()
+                    .map(|construction_functions!(@pattern (start, end) value ($($T)?))|
+                        (start, end, construction_functions!(@value value ($($T)?)))),
+                    |skeleton, start, end, element| Skeleton::<$range_kind, _, _>::try_insert(skeleton, start, end - start, element))
             }
+
+            batch_functions!($range_kind; ($($T)?), $position);
+        }
+    };
+}
+
+macro_rules! batch_functions {
+    ($kind:ident; ($($T:ident)?), $position:ty) => {
+        /// Inserts the given elements (their start, end and element, with the same start and end
+        /// for nodes) one after the other, using the given insertion function. Removes all of them
+        /// again if one can't be inserted.
+        ///
+        /// The elements of the list are walked once alongside the batch, like merging two sorted
+        /// lists: each element of the batch is inserted next to the last element (or bound) of the
+        /// list at or before its start (see [`Skeleton::relative_insertion_start`]), and the walk
+        /// goes on from the inserted element, as inserting can splice, move elements into subs and
+        /// balance the skeletons that the walk was in.
+        fn insert_sorted_batch_with<E: std::error::Error>(
+            &mut self,
+            elements: impl Iterator<Item=(S, S, construction_functions!(@value_type ($($T)?)))>,
+            insert: impl Fn(Rc<RefCell<Skeleton<$kind, S, construction_functions!(@value_type ($($T)?))>>>, S, S,
+                            construction_functions!(@value_type ($($T)?)))
+                -> Result<Inserted<$kind, S, construction_functions!(@value_type ($($T)?))>, E>,
+        ) -> Result<Vec<$position>, BatchInsertionError<E>> {
+            self.shortcuts.get_mut().clear();
+            let mut inserted: Vec<$position> = vec![];
+            // the last element (or bound) of the list at or before the start of the element to
+            // insert next, and the one after it
+            let mut behind = None;
+            let mut next = Skeleton::first_element(&self.skeleton);
+            let mut previous_start = None;
+            for (index, (start, end, element)) in elements.enumerate() {
+                if previous_start.is_some_and(|previous_start| start < previous_start) {
+                    self.remove_batch(inserted);
+                    return Err(BatchInsertionError::Unsorted { index });
+                }
+                previous_start = Some(start);
+                while let Some(current) = next.take_if(|current| current.position <= start) {
+                    next = current.clone().into_next_element();
+                    behind = Some(current);
+                }
+                let (skeleton, origin) = match behind.as_ref().or(next.as_ref()) {
+                    Some(next_to) => Skeleton::relative_insertion_start(next_to, start, end),
+                    None => (self.skeleton.clone(), zero()),
+                };
+                let skeleton = if Rc::ptr_eq(&skeleton, &self.skeleton) {
+                    self.skeleton = Skeleton::make_room(self.skeleton.clone());
+                    self.skeleton.clone()
+                } else {
+                    skeleton
+                };
+                let mut placed = match insert(skeleton, start - origin, end - origin, element) {
+                    Ok(placed) => placed,
+                    Err(error) => {
+                        self.remove_batch(inserted);
+                        return Err(BatchInsertionError::Insertion { index, error });
+                    }
+                };
+                placed.position.position += origin;
+                let (skeleton, position) = Skeleton::<$kind, _, _>::balance(self.skeleton.clone(), placed);
+                self.skeleton = skeleton;
+                next = position.clone().into_next_element();
+                behind = Some(position.clone());
+                inserted.push(position.into());
+            }
+            Ok(inserted)
+        }
+
+        /// Removes the already inserted elements of a batch again, so that the error that stopped
+        /// the batch is returned with the list as it was before.
+        fn remove_batch(&mut self, inserted: Vec<$position>) {
+            for position in inserted.into_iter().rev() {
+                // cannot be None, as nothing else has changed the list since they were inserted
+                if let Some(index) = position.index().resolve() {
+                    Skeleton::<$kind, _, _>::remove(index.skeleton.clone(), index);
+                }
+            }
+        }
+    };
+}
+
+macro_rules! relative_insert_functions {
+    (Node; ($($T:ident)?), $position:ty, $index:ty) => {
+        pub fn insert_after(&mut self, index: &$index, spacing: S$(, value: $T)?) -> $position {
+            display_unwrap!(self.try_insert_after(index, spacing$(, value ${ignore($T)})?))
+        }
+
+        pub fn insert_before(&mut self, index: &$index, spacing: S$(, value: $T)?) -> $position {
+            display_unwrap!(self.try_insert_before(index, spacing$(, value ${ignore($T)})?))
+        }
+
+        /// Inserts an element the given spacing after the element at the given index, which must
+        /// be at or before the element after that one. Instead of traversing the list from the
+        /// root, the insertion starts at the skeleton of the element at the index.
+        pub fn try_insert_after(&mut self, index: &$index, spacing: S$(, value: $T)?)
+                                -> Result<$position, RelativeInsertionError<Infallible>> {
+            let element = self.resolve_next_to(index.try_resolve()?.position())?;
+            let position = Skeleton::relative_insertion_position(&element, spacing, zero(), true)?;
+            self.insert_next_to(&element, position, position, ($(value ${ignore($T)})?),
+                |skeleton, position, _, element| Ok(Skeleton::<Node, _, _>::insert(skeleton, position, element)))
+        }
+
+        /// Inserts an element the given spacing before the element at the given index, which must
+        /// be at or after the element before that one (or at or after zero if there is none), like
+        /// [`Self::try_insert_after`].
+        pub fn try_insert_before(&mut self, index: &$index, spacing: S$(, value: $T)?)
+                                 -> Result<$position, RelativeInsertionError<Infallible>> {
+            let element = self.resolve_next_to(index.try_resolve()?.position())?;
+            let position = Skeleton::relative_insertion_position(&element, spacing, zero(), false)?;
+            self.insert_next_to(&element, position, position, ($(value ${ignore($T)})?),
+                |skeleton, position, _, element| Ok(Skeleton::<Node, _, _>::insert(skeleton, position, element)))
+        }
+
+        relative_insert_functions!(@common Node; ($($T)?), $position);
+    };
+    ($range_kind:ident; ($($T:ident)?), $position:ty, $index:ty) => {
+        paste! {
+            pub fn insert_after(&mut self, index: &$index, spacing: S, span: S$(, value: $T)?) -> $position {
+                display_unwrap!(self.try_insert_after(index, spacing, span$(, value ${ignore($T)})?))
+            }
+
+            pub fn insert_before(&mut self, index: &$index, spacing: S, span: S$(, value: $T)?) -> $position {
+                display_unwrap!(self.try_insert_before(index, spacing, span$(, value ${ignore($T)})?))
+            }
+
+            /// Inserts a range with the given span that starts the given spacing after the end of
+            /// the range at the given index, and ends at or before the next bound after that end.
+            /// Instead of traversing the list from the root, the insertion starts at the skeleton
+            /// of the range at the index.
+            pub fn try_insert_after(&mut self, index: &$index, spacing: S, span: S$(, value: $T)?)
+                                    -> Result<$position, RelativeInsertionError<[< $range_kind InsertionError >]>> {
+                let (_, end) = self.resolve_next_to(index.try_resolve()?.position())?.into_range();
+                let start = Skeleton::relative_insertion_position(&end, spacing, span, true)?;
+                self.insert_next_to(&end, start, start + span, ($(value ${ignore($T)})?),
+                    |skeleton, start, end, element| Skeleton::<$range_kind, _, _>::try_insert(skeleton, start, end - start, element))
+            }
+
+            /// Inserts a range with the given span that ends the given spacing before the start of
+            /// the range at the given index, and starts at or after the previous bound before that
+            /// start (or at or after zero if there is none), like [`Self::try_insert_after`].
+            pub fn try_insert_before(&mut self, index: &$index, spacing: S, span: S$(, value: $T)?)
+                                     -> Result<$position, RelativeInsertionError<[< $range_kind InsertionError >]>> {
+                let (start, _) = self.resolve_next_to(index.try_resolve()?.position())?.into_range();
+                let position = Skeleton::relative_insertion_position(&start, spacing, span, false)?;
+                self.insert_next_to(&start, position, position + span, ($(value ${ignore($T)})?),
+                    |skeleton, start, end, element| Skeleton::<$range_kind, _, _>::try_insert(skeleton, start, end - start, element))
+            }
+        }
+
+        relative_insert_functions!(@common $range_kind; ($($T)?), $position);
+    };
+    (@common $kind:ident; ($($T:ident)?), $position:ty) => {
+        /// Checks that the element (or bound) at the given position, which an element is to be
+        /// inserted next to, belongs to this list.
+        fn resolve_next_to<E: std::error::Error>(
+            &self,
+            next_to: EphemeralPosition<$kind, S, construction_functions!(@value_type ($($T)?))>,
+        ) -> Result<EphemeralPosition<$kind, S, construction_functions!(@value_type ($($T)?))>, RelativeInsertionError<E>> {
+            if !Rc::ptr_eq(&Skeleton::root(next_to.skeleton.clone()), &self.skeleton) {
+                return Err(RelativeInsertionError::ElementOfOtherList);
+            }
+            Ok(next_to)
+        }
+
+        /// Inserts an element that starts at `start` and ends at `end` (the same for nodes) next to
+        /// the element (or bound) at the given position, using the given insertion function.
+        fn insert_next_to<E: std::error::Error>(
+            &mut self,
+            next_to: &EphemeralPosition<$kind, S, construction_functions!(@value_type ($($T)?))>,
+            start: S,
+            end: S,
+            element: construction_functions!(@value_type ($($T)?)),
+            insert: impl FnOnce(Rc<RefCell<Skeleton<$kind, S, construction_functions!(@value_type ($($T)?))>>>, S, S,
+                                construction_functions!(@value_type ($($T)?)))
+                -> Result<Inserted<$kind, S, construction_functions!(@value_type ($($T)?))>, E>,
+        ) -> Result<$position, RelativeInsertionError<E>> {
+            self.shortcuts.get_mut().clear();
+            let (skeleton, origin) = Skeleton::relative_insertion_start(next_to, start, end);
+            let skeleton = if Rc::ptr_eq(&skeleton, &self.skeleton) {
+                self.skeleton = Skeleton::make_room(self.skeleton.clone());
+                self.skeleton.clone()
+            } else {
+                skeleton
+            };
+            let mut inserted = insert(skeleton, start - origin, end - origin, element)
+                .map_err(|error| RelativeInsertionError::Insertion { error })?;
+            inserted.position.position += origin;
+            let (skeleton, position) = Skeleton::<$kind, _, _>::balance(self.skeleton.clone(), inserted);
+            self.skeleton = skeleton;
+            Ok(position.into())
         }
     };
 }
 
-pub(super) use push_insert_functions;
\ No newline at end of file
+pub(super) use {push_insert_functions, batch_functions, relative_insert_functions};
\ No newline at end of file
@@ -0,0 +1,27 @@
+macro_rules! rank_functions {
+    ($position:ty) => {
+        /// Returns the number of elements positioned before the given position, which is the
+        /// number of the first element at or after it (see [`Self::nth`]). Ranges count as
+        /// positioned at their start. This takes logarithmic time.
+        #[must_use]
+        pub fn rank(&self, position: S) -> usize {
+            Skeleton::count_before(self.skeleton.clone(), position)
+        }
+
+        /// Returns the number of elements positioned at or after `start` and before `end`. Ranges
+        /// count as positioned at their start.
+        #[must_use]
+        pub fn count_between(&self, start: S, end: S) -> usize {
+            self.rank(end).saturating_sub(self.rank(start))
+        }
+
+        /// Returns the element with the given number, counting from zero, in the order of their
+        /// positions (for ranges, of their starts). This takes logarithmic time.
+        #[must_use]
+        pub fn nth(&self, number: usize) -> Option<$position> {
+            Skeleton::nth(self.skeleton.clone(), number).map(Into::into)
+        }
+    };
+}
+
+pub(super) use rank_functions;
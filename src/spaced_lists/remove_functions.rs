@@ -0,0 +1,22 @@
+macro_rules! remove_functions {
+    ($kind:ident; ($($T:ident)?), $index:ty) => {
+        pub fn remove(&mut self, index: $index)$( -> $T)? {
+            display_unwrap!(self.try_remove(index))
+        }
+
+        // the parentheses are only unnecessary if there is a $T
+        #[allow(unused_parens)]
+        pub fn try_remove(&mut self, index: $index) -> Result<($($T)?), RemovalError> {
+            let index = index.resolve().ok_or(RemovalError::ElementAlreadyRemoved)?;
+            if !Rc::ptr_eq(&Skeleton::root(index.skeleton.clone()), &self.skeleton) {
+                return Err(RemovalError::ElementOfOtherList);
+            }
+            // cannot be None, because the index refers to an element that hasn't been removed yet
+            self.shortcuts.get_mut().clear();
+            let element = Skeleton::<$kind, _, _>::remove(index.skeleton.clone(), index).unwrap();
+            Ok(element)
+        }
+    };
+}
+
+pub(super) use remove_functions;
@@ -0,0 +1,18 @@
+macro_rules! shortcut_functions {
+    () => {
+        /// Sets how many of the positions found by recent traversals this list remembers, so that
+        /// traversals close to one of them can start there instead of at the root. This speeds up
+        /// many traversals around the same place, but slows down others a bit, so it is disabled
+        /// (a capacity of zero) by default. Changing the list forgets all remembered positions.
+        pub fn set_shortcut_capacity(&mut self, capacity: usize) {
+            self.shortcuts.get_mut().set_capacity(capacity);
+        }
+
+        #[must_use]
+        pub fn shortcut_capacity(&self) -> usize {
+            self.shortcuts.borrow().capacity()
+        }
+    };
+}
+
+pub(super) use shortcut_functions;
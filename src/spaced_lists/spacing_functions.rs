@@ -1,42 +1,179 @@
 macro_rules! spacing_functions {
     () => {
         pub fn increase_spacing_after(&mut self, position: S, change: S) {
+            self.shortcuts.get_mut().clear();
             Skeleton::increase_spacing_after(self.skeleton.clone(), position, change);
         }
 
         pub fn increase_spacing_before(&mut self, position: S, change: S) {
+            self.shortcuts.get_mut().clear();
             Skeleton::increase_spacing_before(self.skeleton.clone(), position, change);
         }
 
         pub fn decrease_spacing_after(&mut self, position: S, change: S) {
+            self.shortcuts.get_mut().clear();
             Skeleton::decrease_spacing_after(self.skeleton.clone(), position, change);
         }
 
         pub fn decrease_spacing_before(&mut self, position: S, change: S) {
+            self.shortcuts.get_mut().clear();
             Skeleton::decrease_spacing_before(self.skeleton.clone(), position, change);
         }
 
 
         pub fn try_increase_spacing_after(&mut self, position: S, change: S) -> Result<(), SpacingError<S>> {
+            self.shortcuts.get_mut().clear();
             Skeleton::try_increase_spacing_after(self.skeleton.clone(), position, change)?;
             Ok(())
         }
 
         pub fn try_increase_spacing_before(&mut self, position: S, change: S) -> Result<(), SpacingError<S>> {
+            self.shortcuts.get_mut().clear();
             Skeleton::try_increase_spacing_before(self.skeleton.clone(), position, change)?;
             Ok(())
         }
 
         pub fn try_decrease_spacing_after(&mut self, position: S, change: S) -> Result<(), SpacingError<S>> {
+            self.shortcuts.get_mut().clear();
             Skeleton::try_decrease_spacing_after(self.skeleton.clone(), position, change)?;
             Ok(())
         }
 
         pub fn try_decrease_spacing_before(&mut self, position: S, change: S) -> Result<(), SpacingError<S>> {
+            self.shortcuts.get_mut().clear();
             Skeleton::try_decrease_spacing_before(self.skeleton.clone(), position, change)?;
             Ok(())
         }
+
+
+        pub fn apply_spacing_edits(&mut self, edits: impl IntoIterator<Item=SpacingEdit<S>>) {
+            self.shortcuts.get_mut().clear();
+            Skeleton::apply_spacing_edits(self.skeleton.clone(), edits);
+        }
+
+        /// Applies the given edits, which must be sorted by their position, in a single sweep over
+        /// the list, which is much faster than applying them one at a time. The edits are applied
+        /// from the last to the first, so that an edit doesn't move the positions that the edits
+        /// before it refer to (unless it decreases a spacing to below them). If one of them fails,
+        /// none of them are applied.
+        pub fn try_apply_spacing_edits(&mut self, edits: impl IntoIterator<Item=SpacingEdit<S>>)
+                                       -> Result<(), SpacingError<S>> {
+            self.shortcuts.get_mut().clear();
+            Skeleton::try_apply_spacing_edits(self.skeleton.clone(), edits)
+        }
+    }
+}
+
+/// Spacing changes next to the element at an index instead of at a position, which also tells
+/// apart elements at the same position. For ranges, the spacing after a range starts at its end and
+/// the spacing before it ends at its start. The spacing before the first element is its position.
+macro_rules! element_spacing_functions {
+    (@after Node, $element:expr) => {
+        $element
+    };
+    (@after $range_kind:ident, $element:expr) => {
+        $element.into_range().1
+    };
+    (@before Node, $element:expr) => {
+        $element
+    };
+    (@before $range_kind:ident, $element:expr) => {
+        $element.into_range().0
+    };
+    // resolves the given index, which must refer to an element of this list
+    (@resolve $self:ident, $index:expr) => {{
+        let element = $index.try_resolve()?.position();
+        if !Rc::ptr_eq(&Skeleton::root(element.skeleton.clone()), &$self.skeleton) {
+            return Err(SpacingError::ElementOfOtherList);
+        }
+        element
+    }};
+    ($kind:ident, $index:ty) => {
+        pub fn increase_spacing_after_element(&mut self, index: &$index, change: S) {
+            display_unwrap!(self.try_increase_spacing_after_element(index, change));
+        }
+
+        pub fn increase_spacing_before_element(&mut self, index: &$index, change: S) {
+            display_unwrap!(self.try_increase_spacing_before_element(index, change));
+        }
+
+        pub fn decrease_spacing_after_element(&mut self, index: &$index, change: S) {
+            display_unwrap!(self.try_decrease_spacing_after_element(index, change));
+        }
+
+        pub fn decrease_spacing_before_element(&mut self, index: &$index, change: S) {
+            display_unwrap!(self.try_decrease_spacing_before_element(index, change));
+        }
+
+        pub fn set_spacing_after(&mut self, index: &$index, spacing: S) {
+            display_unwrap!(self.try_set_spacing_after(index, spacing));
+        }
+
+        pub fn set_spacing_before(&mut self, index: &$index, spacing: S) {
+            display_unwrap!(self.try_set_spacing_before(index, spacing));
+        }
+
+        /// Moves all elements after the element at the given index (even ones at the same
+        /// position) back by the given change.
+        pub fn try_increase_spacing_after_element(&mut self, index: &$index, change: S) -> Result<(), SpacingError<S>> {
+            let element = element_spacing_functions!(@after $kind, element_spacing_functions!(@resolve self, index));
+            self.shortcuts.get_mut().clear();
+            Skeleton::try_change_spacing_after_element(&element, true, change)
+        }
+
+        /// Moves the element at the given index and all elements after it back by the given
+        /// change.
+        pub fn try_increase_spacing_before_element(&mut self, index: &$index, change: S) -> Result<(), SpacingError<S>> {
+            let element = element_spacing_functions!(@before $kind, element_spacing_functions!(@resolve self, index));
+            self.shortcuts.get_mut().clear();
+            Skeleton::try_change_spacing_before_element(&self.skeleton, &element, true, change)
+        }
+
+        /// Moves all elements after the element at the given index (even ones at the same
+        /// position) forwards by the given change, which can't be more than the spacing to the
+        /// next element.
+        pub fn try_decrease_spacing_after_element(&mut self, index: &$index, change: S) -> Result<(), SpacingError<S>> {
+            let element = element_spacing_functions!(@after $kind, element_spacing_functions!(@resolve self, index));
+            self.shortcuts.get_mut().clear();
+            Skeleton::try_change_spacing_after_element(&element, false, change)
+        }
+
+        /// Moves the element at the given index and all elements after it forwards by the given
+        /// change, which can't be more than the spacing to the previous element (or the position
+        /// of the element, if it is the first one).
+        pub fn try_decrease_spacing_before_element(&mut self, index: &$index, change: S) -> Result<(), SpacingError<S>> {
+            let element = element_spacing_functions!(@before $kind, element_spacing_functions!(@resolve self, index));
+            self.shortcuts.get_mut().clear();
+            Skeleton::try_change_spacing_before_element(&self.skeleton, &element, false, change)
+        }
+
+        /// Sets the spacing between the element at the given index and the next element,
+        /// increasing or decreasing it as needed.
+        pub fn try_set_spacing_after(&mut self, index: &$index, spacing: S) -> Result<(), SpacingError<S>> {
+            let element = element_spacing_functions!(@after $kind, element_spacing_functions!(@resolve self, index));
+            let current = Skeleton::spacing_after_element(&element)?;
+            self.shortcuts.get_mut().clear();
+            if spacing >= current {
+                Skeleton::try_change_spacing_after_element(&element, true, spacing - current)
+            } else {
+                Skeleton::try_change_spacing_after_element(&element, false, current - spacing)
+            }
+        }
+
+        /// Sets the spacing between the element at the given index and the previous element (or
+        /// the position of the element, if it is the first one), increasing or decreasing it as
+        /// needed.
+        pub fn try_set_spacing_before(&mut self, index: &$index, spacing: S) -> Result<(), SpacingError<S>> {
+            let element = element_spacing_functions!(@before $kind, element_spacing_functions!(@resolve self, index));
+            let current = Skeleton::spacing_before_element(&element);
+            self.shortcuts.get_mut().clear();
+            if spacing >= current {
+                Skeleton::try_change_spacing_before_element(&self.skeleton, &element, true, spacing - current)
+            } else {
+                Skeleton::try_change_spacing_before_element(&self.skeleton, &element, false, current - spacing)
+            }
+        }
     }
 }
 
-pub(super) use spacing_functions;
\ No newline at end of file
+pub(super) use {spacing_functions, element_spacing_functions};
\ No newline at end of file
@@ -0,0 +1,87 @@
+macro_rules! split_functions {
+    (@append $kind:ident) => {
+        pub fn append(&mut self, other: &mut Self, spacing: S) {
+            display_unwrap!(self.try_append(other, spacing))
+        }
+
+        /// Moves all elements of `other` behind the end of this list, such that the first of them
+        /// ends up `spacing` after it (or at `spacing`, if this list is empty), and leaves `other`
+        /// empty. The skeleton of `other` is moved as a whole, so indices into it stay valid.
+        pub fn try_append(&mut self, other: &mut Self, spacing: S) -> Result<(), AppendError<S>> {
+            self.shortcuts.get_mut().clear();
+            other.shortcuts.get_mut().clear();
+            self.skeleton = Skeleton::<$kind, _, _>::append(self.skeleton.clone(), other.skeleton.clone(), spacing)?;
+            other.skeleton = Skeleton::new(None);
+            Ok(())
+        }
+    };
+    (@split $range_kind:ident) => {
+        #[must_use]
+        pub fn split_off(&mut self, position: S) -> Self {
+            display_unwrap!(self.try_split_off(position))
+        }
+
+        /// Moves all ranges that start at or after the given position into a new list, which is
+        /// returned. Fails if a range starts before the position and ends after it (see
+        /// [`Self::split_off_splitting`]). Indices into the moved ranges stay valid.
+        pub fn try_split_off(&mut self, position: S) -> Result<Self, SplitError<S>> {
+            self.shortcuts.get_mut().clear();
+            let (left, right) = Skeleton::<$range_kind, _, _>::split_off(self.skeleton.clone(), position, None)?;
+            self.skeleton = left;
+            Ok(Self::from_skeleton(right))
+        }
+    };
+    (Node; ($($T:ident)?)) => {
+        /// Moves all elements at or after the given position into a new list, which is returned.
+        /// Indices into the moved elements stay valid.
+        #[must_use]
+        pub fn split_off(&mut self, position: S) -> Self {
+            self.shortcuts.get_mut().clear();
+            let (left, right) = Skeleton::<Node, _, _>::split_off(self.skeleton.clone(), position);
+            self.skeleton = left;
+            Self::from_skeleton(right)
+        }
+
+        split_functions!(@append Node);
+    };
+    ($range_kind:ident; ($T:ident)) => {
+        split_functions!(@split $range_kind);
+
+        /// Moves all ranges that start at or after the given position into a new list, like
+        /// [`Self::split_off`] does, but splits the ranges that contain the position in two
+        /// instead of failing: the part before the position keeps the index and value of the
+        /// range, and the part after it goes into the new list with the value that `split`
+        /// returns for the value of the range.
+        #[must_use]
+        pub fn split_off_splitting(&mut self, position: S, mut split: impl FnMut(&mut $T) -> $T) -> Self {
+            self.shortcuts.get_mut().clear();
+            // cannot fail, as ranges containing the position are split
+            let (left, right) = Skeleton::<$range_kind, _, _>::split_off(self.skeleton.clone(), position, Some(&mut split))
+                .unwrap();
+            self.skeleton = left;
+            Self::from_skeleton(right)
+        }
+
+        split_functions!(@append $range_kind);
+    };
+    ($range_kind:ident; ()) => {
+        split_functions!(@split $range_kind);
+
+        /// Moves all ranges that start at or after the given position into a new list, like
+        /// [`Self::split_off`] does, but splits the ranges that contain the position in two
+        /// instead of failing: the part before the position keeps the index of the range.
+        #[must_use]
+        pub fn split_off_splitting(&mut self, position: S) -> Self {
+            self.shortcuts.get_mut().clear();
+            // cannot fail, as ranges containing the position are split
+            let (left, right) = Skeleton::<$range_kind, _, _>::split_off(self.skeleton.clone(), position, Some(&mut |_| ()))
+                .unwrap();
+            self.skeleton = left;
+            Self::from_skeleton(right)
+        }
+
+        split_functions!(@append $range_kind);
+    };
+}
+
+pub(super) use split_functions;
@@ -1,48 +1,73 @@
+/// Traverses the list, starting at the nearest shortcut if it has any (see
+/// [`crate::skeleton::shortcuts`]). `$matches` tells whether a position that is in range is the
+/// one the traversal is looking for, and `$traverse` is the traversal from the root.
+macro_rules! shortcut_traversal {
+    ($self:ident, $target:ident, ==, $matches:expr, $traverse:expr) => {
+        // the last element <= the target is the one at the target, if there is one
+        shortcut_traversal!($self, $target, <=, $matches, $traverse).filter(|found| found.position == $target)
+    };
+    ($self:ident, $target:ident, $cmp:tt, $matches:expr, $traverse:expr) => {
+        Shortcuts::traverse(&$self.shortcuts, $target, shortcut_traversal!(@search $cmp),
+                            |position| position $cmp $target, $matches, $traverse)
+    };
+    (@search <) => { Search::Last };
+    (@search <=) => { Search::Last };
+    (@search >=) => { Search::First };
+    (@search >) => { Search::First };
+}
+
 macro_rules! unconditional_traversal_function {
-    ($kind:ident; $function:ident, $skeleton_function:ident, $position:ty) => {
+    ($kind:ident; $function:ident, $skeleton_function:ident, $cmp:tt, $bound:expr, $position:ty) => {
         paste! {
             #[must_use]
             pub fn $function(&self, position: S) -> Option<$position> {
-                Skeleton::<$kind, _, _>::[< conditional_ $skeleton_function >](self.skeleton.clone(), position, |slot| slot.is_some()).map(Into::into)
+                shortcut_traversal!(self, position, $cmp,
+                    |found| $bound.is_none_or(|bound| BoundType::of(found.index) == bound),
+                    || Skeleton::<$kind, _, _>::[< conditional_ $skeleton_function >](self.skeleton.clone(), position, |slot| slot.is_some()))
+                    .map(Into::into)
             }
         }
     };
 }
 
 macro_rules! conditional_traversal_function {
-    ($kind:ident; $function:ident, $skeleton_function:ident, $position:ty) => {
+    ($kind:ident; $function:ident, $skeleton_function:ident, $cmp:tt, $bound:expr, $position:ty) => {
         paste! {
             #[must_use]
             pub fn [< conditional_ $function >]<C: Fn(&T) -> bool>(&self, position: S, condition: C) -> Option<$position> {
-                Skeleton::<$kind, _, _>::[< conditional_ $skeleton_function >](self.skeleton.clone(), position, |slot| {
+                let slot_condition = |slot: Ref<ElementSlot<T>>| {
                     match slot.deref() {
                         ElementSlot::Some(element) => condition(element),
                         ElementSlot::None => false
                     }
-                }).map(Into::into)
+                };
+                shortcut_traversal!(self, position, $cmp,
+                    |found| $bound.is_none_or(|bound| BoundType::of(found.index) == bound) && slot_condition(found.element()),
+                    || Skeleton::<$kind, _, _>::[< conditional_ $skeleton_function >](self.skeleton.clone(), position, slot_condition))
+                    .map(Into::into)
             }
         }
     };
 }
 
 macro_rules! all_traversal_functions {
-    ($kind:ident; $macro_prefix:ident, $($function_prefix:ident)?, $($skeleton_function_prefix:ident)?, $position:ty) => {
+    ($kind:ident; $macro_prefix:ident, $($function_prefix:ident)?, $($skeleton_function_prefix:ident)?, $bound:expr, $position:ty) => {
         paste! {
-            [< $macro_prefix traversal_function >]!($kind; [< $($function_prefix)? before >], [< $($skeleton_function_prefix)? before >], $position);
-            [< $macro_prefix traversal_function >]!($kind; [< $($function_prefix)? at_or_before >], [< $($skeleton_function_prefix)? at_or_before >], $position);
-            [< $macro_prefix traversal_function >]!($kind; [< $($function_prefix)? at >], [< $($skeleton_function_prefix)? at >], $position);
-            [< $macro_prefix traversal_function >]!($kind; [< $($function_prefix)? at_or_after >], [< $($skeleton_function_prefix)? at_or_after >], $position);
-            [< $macro_prefix traversal_function >]!($kind; [< $($function_prefix)? after >], [< $($skeleton_function_prefix)? after >], $position);
+            [< $macro_prefix traversal_function >]!($kind; [< $($function_prefix)? before >], [< $($skeleton_function_prefix)? before >], <, $bound, $position);
+            [< $macro_prefix traversal_function >]!($kind; [< $($function_prefix)? at_or_before >], [< $($skeleton_function_prefix)? at_or_before >], <=, $bound, $position);
+            [< $macro_prefix traversal_function >]!($kind; [< $($function_prefix)? at >], [< $($skeleton_function_prefix)? at >], ==, $bound, $position);
+            [< $macro_prefix traversal_function >]!($kind; [< $($function_prefix)? at_or_after >], [< $($skeleton_function_prefix)? at_or_after >], >=, $bound, $position);
+            [< $macro_prefix traversal_function >]!($kind; [< $($function_prefix)? after >], [< $($skeleton_function_prefix)? after >], >, $bound, $position);
         }
     };
     (Node; $macro_prefix:ident, $position:ty) => {
-        all_traversal_functions!(Node; $macro_prefix, , , $position);
+        all_traversal_functions!(Node; $macro_prefix, , , None::<BoundType>, $position);
     };
     ($range_kind:ident; $macro_prefix:ident, $position:ty) => {
-        all_traversal_functions!($range_kind; $macro_prefix, starting_or_ending_, , $position);
-        all_traversal_functions!($range_kind; $macro_prefix, starting_, starting_, $position);
-        all_traversal_functions!($range_kind; $macro_prefix, ending_, ending_, $position);
+        all_traversal_functions!($range_kind; $macro_prefix, starting_or_ending_, , None::<BoundType>, $position);
+        all_traversal_functions!($range_kind; $macro_prefix, starting_, starting_, Some(BoundType::Start), $position);
+        all_traversal_functions!($range_kind; $macro_prefix, ending_, ending_, Some(BoundType::End), $position);
     };
 }
 
-pub(super) use {unconditional_traversal_function, conditional_traversal_function, all_traversal_functions};
\ No newline at end of file
+pub(super) use {shortcut_traversal, unconditional_traversal_function, conditional_traversal_function, all_traversal_functions};
@@ -2,22 +2,26 @@ macro_rules! trivial_accessors {
     () => {
         #[must_use]
         pub fn size(&self) -> usize {
-            self.size
+            self.skeleton.borrow().count()
         }
 
         #[must_use]
         pub fn is_empty(&self) -> bool {
-            self.size == 0
+            self.size() == 0
         }
 
+        /// The distance between [`Self::start`] and [`Self::end`].
         #[must_use]
         pub fn length(&self) -> S {
-            self.skeleton.borrow().length()
+            self.end() - self.start()
         }
 
+        /// The position of the first element, or zero if the list is empty. Like [`Self::end`],
+        /// this follows removals: once the first element is removed, it is the position of the one
+        /// after it.
         #[must_use]
         pub fn start(&self) -> S {
-            self.skeleton.borrow().offset()
+            self.first().map_or(zero(), |position| position.position())
         }
 
         #[must_use]
@@ -0,0 +1,30 @@
+macro_rules! value_functions {
+    ($kind:ident) => {
+        /// Borrows all elements of this list to iterate over them in ascending order of their
+        /// positions (for ranges, of their starts) without resolving any indices. The list can't be
+        /// changed while the returned [`Values`] exist.
+        ///
+        /// ```
+        /// use spaced_list_5::SpacedList;
+        ///
+        /// let mut list = SpacedList::new();
+        /// list.insert(4, 'b');
+        /// list.insert(2, 'a');
+        /// let values = list.iter_values().iter().map(|(position, &value)| (position, value)).collect::<Vec<_>>();
+        /// assert_eq!(values, [(2, 'a'), (4, 'b')]);
+        /// ```
+        pub fn iter_values(&self) -> Values<'_, $kind, S, T> {
+            Values::new(&self.skeleton, self.size(), value_functions!(@links_per_element $kind))
+        }
+
+        /// Borrows all elements of this list like [`Self::iter_values`], yielding mutable references
+        /// to them when iterated over with [`ValuesMut::iter_mut`].
+        pub fn iter_values_mut(&mut self) -> ValuesMut<'_, $kind, S, T> {
+            ValuesMut::new(&self.skeleton, self.size(), value_functions!(@links_per_element $kind))
+        }
+    };
+    (@links_per_element Node) => { 1 };
+    (@links_per_element $range_kind:ident) => { 2 };
+}
+
+pub(super) use value_functions;
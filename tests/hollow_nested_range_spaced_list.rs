@@ -1,5 +1,6 @@
 use std::fmt::Display;
-use spaced_list_5::{BoundType, HollowNestedRangeSpacedList, HollowPosition, NestedRange, Spacing};
+use spaced_list_5::{BoundType, HollowNestedRangeSpacedList, HollowPosition, NestedRange, NestedRangeInsertionError, RangeOrder,
+                    RelativeInsertionError, Spacing};
 
 fn print<'a, S>(iter: impl Iterator<Item=HollowPosition<NestedRange, S>>)
     where S: 'a + Spacing + Display {
@@ -30,4 +31,125 @@ fn test() {
     assert_eq!(list.ending_after(14).unwrap().position(), 15);
     assert_eq!(list.ending_after(15).unwrap().position(), 20);
     assert_eq!(list.starting_at(0).unwrap().position(), 0);
-}
\ No newline at end of file
+}
+
+#[test]
+fn removal() {
+    let mut list = HollowNestedRangeSpacedList::new();
+    let outer = list.insert(0, 20);
+    let inner = list.insert(5, 8);
+    list.insert(12, 15);
+    let innermost = list.insert(13, 14);
+    list.remove(outer.index());
+    // the remaining ranges still alternate between starts and ends
+    let bounds = list.iter().map(|bound| (bound.position(), bound.bound_type())).collect::<Vec<_>>();
+    assert_eq!(bounds, vec![
+        (5, BoundType::Start), (8, BoundType::End),
+        (12, BoundType::Start), (13, BoundType::Start), (14, BoundType::End), (15, BoundType::End),
+    ]);
+    assert!(list.starting_at(0).is_none());
+    assert!(list.ending_after(15).is_none());
+    list.remove(innermost.index());
+    list.remove(inner.index());
+    assert_eq!(list.starting_at_or_after(0).unwrap().position(), 12);
+    assert_eq!(list.ending_at_or_before(20).unwrap().position(), 15);
+    assert_eq!(list.size(), 1);
+}
+
+fn bounds(list: &HollowNestedRangeSpacedList<i32>) -> Vec<(i32, BoundType)> {
+    list.iter().map(|bound| (bound.position(), bound.bound_type())).collect()
+}
+
+#[test]
+fn insert_before_range_with_inner_ranges() {
+    let mut list = HollowNestedRangeSpacedList::new();
+    list.insert(10, 20);
+    let inner = list.insert(12, 15);
+    list.insert(0, 5);
+    // the inner range stays inside of the range it was inserted into
+    assert_eq!(bounds(&list), vec![
+        (0, BoundType::Start), (5, BoundType::End),
+        (10, BoundType::Start), (12, BoundType::Start), (15, BoundType::End), (20, BoundType::End),
+    ]);
+    assert_eq!(inner.index().position().position(), 12);
+    assert!(list.try_insert(3, 4).is_ok());
+    assert!(list.try_insert(11, 13).is_err());
+}
+
+#[test]
+fn insert_before_removed_range_with_inner_ranges() {
+    let mut list = HollowNestedRangeSpacedList::new();
+    let outer = list.insert(10, 20);
+    list.insert(12, 15);
+    list.remove(outer.index());
+    let first = list.insert(0, 5);
+    assert_eq!(bounds(&list), vec![
+        (0, BoundType::Start), (5, BoundType::End), (12, BoundType::Start), (15, BoundType::End),
+    ]);
+    assert!(list.try_insert(4, 13).is_err());
+    list.remove(first.index());
+    assert_eq!(bounds(&list), vec![(12, BoundType::Start), (15, BoundType::End)]);
+    assert_eq!(list.starting_at_or_after(0).unwrap().position(), 12);
+}
+
+#[test]
+fn ranges_of_positions() {
+    let mut list = HollowNestedRangeSpacedList::new();
+    list.insert(0, 20);
+    list.insert(5, 8);
+    list.insert(12, 15);
+    list.insert(13, 14);
+    let positions = |iter: &mut dyn Iterator<Item=HollowPosition<NestedRange, i32>>|
+        iter.map(|bound| bound.position()).collect::<Vec<_>>();
+    assert_eq!(positions(&mut list.range(5..14)), [5, 8, 12, 13]);
+    assert_eq!(positions(&mut list.starting_range(5..14)), [5, 12, 13]);
+    assert_eq!(positions(&mut list.ending_range(5..=14)), [8, 14]);
+    assert_eq!(positions(&mut list.starting_range(..)), [0, 5, 12, 13]);
+    assert_eq!(positions(&mut list.ending_range(..15)), [8, 14]);
+    assert_eq!(positions(&mut list.range_backwards(8..=20)), [20, 15, 14, 13, 12, 8]);
+    assert_eq!(positions(&mut list.starting_range_backwards(1..)), [13, 12, 5]);
+    assert_eq!(positions(&mut list.ending_range_backwards(..14)), [8]);
+    assert!(positions(&mut list.starting_range(14..)).is_empty());
+    assert_eq!(positions(&mut list.ending_range(..).rev()), [20, 15, 14, 8]);
+}
+
+#[test]
+fn nested_iter_ranges() {
+    let mut list = HollowNestedRangeSpacedList::new();
+    list.insert(0, 20);
+    list.insert(5, 8);
+    list.insert(12, 15);
+    list.insert(13, 14);
+    list.insert(22, 25);
+    let ranges = |iter: &mut dyn Iterator<Item=(HollowPosition<NestedRange, i32>, HollowPosition<NestedRange, i32>, usize)>|
+        iter.map(|(start, end, depth)| (start.position(), end.position(), depth)).collect::<Vec<_>>();
+    assert_eq!(ranges(&mut list.iter_ranges(RangeOrder::PreOrder)),
+               [(0, 20, 0), (5, 8, 1), (12, 15, 1), (13, 14, 2), (22, 25, 0)]);
+    assert_eq!(ranges(&mut list.iter_ranges(RangeOrder::PostOrder)),
+               [(5, 8, 1), (13, 14, 2), (12, 15, 1), (0, 20, 0), (22, 25, 0)]);
+    assert_eq!(ranges(&mut list.iter_ranges_backwards(RangeOrder::PreOrder)),
+               [(22, 25, 0), (13, 14, 2), (12, 15, 1), (5, 8, 1), (0, 20, 0)]);
+    assert_eq!(ranges(&mut list.into_iter_ranges_backwards(RangeOrder::PostOrder)),
+               [(22, 25, 0), (0, 20, 0), (12, 15, 1), (13, 14, 2), (5, 8, 1)]);
+}
+
+#[test]
+fn relative_insertion() {
+    let mut list = HollowNestedRangeSpacedList::new();
+    list.insert(0, 30);
+    let inner = list.insert(10, 20).index();
+    // siblings of the inner range, inside the outer one
+    list.insert_after(&inner, 2, 5);
+    list.insert_before(&inner, 1, 4);
+    assert_eq!(bounds(&list), vec![
+        (0, BoundType::Start), (5, BoundType::Start), (9, BoundType::End), (10, BoundType::Start),
+        (20, BoundType::End), (22, BoundType::Start), (27, BoundType::End), (30, BoundType::End),
+    ]);
+    // would reach past the start of the range after it
+    assert!(matches!(list.try_insert_after(&inner, 1, 2), Err(RelativeInsertionError::DoesNotFit)));
+    assert!(matches!(list.try_insert_before(&inner, 0, 6), Err(RelativeInsertionError::DoesNotFit)));
+    assert!(matches!(list.try_insert_after(&inner, 1, -1),
+                     Err(RelativeInsertionError::Insertion { error: NestedRangeInsertionError::NegativeSpan })));
+    assert_eq!(list.insert_after(&inner, 0, 2).position(), 20);
+    assert_eq!(list.size(), 5);
+}
@@ -1,6 +1,7 @@
 use std::fmt::Display;
+use std::ops::Bound;
 use itertools::Itertools;
-use spaced_list_5::{HollowRangeSpacedList, HollowPosition, Range, Spacing};
+use spaced_list_5::{HollowRangeSpacedList, HollowPosition, Range, RelativeInsertionError, Spacing, StraddlePolicy, DeletionError, SplitError};
 
 fn print<'a, S>(iter: impl Iterator<Item = (HollowPosition<Range, S>, HollowPosition<Range, S>)>)
     where S: 'a + Spacing + Display {
@@ -216,4 +217,202 @@ fn ranges() {
         assert!(list.ending_after(query_pos).is_none());
         // endregion
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn removal() {
+    let mut list: HollowRangeSpacedList<u64> = HollowRangeSpacedList::new();
+    let a = list.insert(1, 3);
+    let b = list.insert(5, 8);
+    let c = list.insert(10, 11);
+    list.remove(b.index());
+    assert_eq!(list.iter_ranges().map(|(start, end)| (start.position(), end.position())).collect_vec(),
+               vec![(1, 3), (10, 11)]);
+    assert!(list.starting_at(5).is_none());
+    assert!(list.ending_at(8).is_none());
+    // removing through the end bound removes the whole range
+    list.remove(c.index().into_range().1);
+    assert_eq!(list.end(), 3);
+    assert!(list.try_remove(c.index()).is_err());
+    list.insert(4, 6);
+    list.remove(a.index());
+    assert_eq!(list.iter_ranges().map(|(start, end)| (start.position(), end.position())).collect_vec(),
+               vec![(4, 6)]);
+    assert_eq!(list.size(), 1);
+}
+
+#[test]
+fn stale_indices() {
+    let mut list: HollowRangeSpacedList<u64> = HollowRangeSpacedList::new();
+    let a = list.insert(4, 6);
+    let b = list.insert(1, 2);
+    let (start, end) = a.clone().into_range();
+    assert_eq!(start.try_position().unwrap(), 4);
+    assert_eq!(end.index().try_position().unwrap().position(), 6);
+    list.remove(a.index());
+    assert!(!start.is_valid());
+    assert!(end.try_element().is_err());
+    assert!(b.index().try_element().is_ok());
+}
+
+#[test]
+fn overlap_queries() {
+    let mut list: HollowRangeSpacedList<u64> = HollowRangeSpacedList::new();
+    for (start, end) in [(1, 3), (3, 5), (5, 5), (5, 7), (10, 12)] {
+        list.insert(start, end);
+    }
+    let ranges = |iter: &mut dyn Iterator<Item=(HollowPosition<Range, u64>, HollowPosition<Range, u64>)>|
+        iter.map(|(start, end)| (start.position(), end.position())).collect_vec();
+    assert_eq!(ranges(&mut list.containing(2)), [(1, 3)]);
+    assert_eq!(ranges(&mut list.containing(3)), [(1, 3), (3, 5)]);
+    assert_eq!(ranges(&mut list.containing(5)), [(3, 5), (5, 5), (5, 7)]);
+    assert!(ranges(&mut list.containing(8)).is_empty());
+    assert!(ranges(&mut list.containing(13)).is_empty());
+    assert_eq!(ranges(&mut list.overlapping(4..10)), [(3, 5), (5, 5), (5, 7)]);
+    assert_eq!(ranges(&mut list.overlapping(4..=10)), [(3, 5), (5, 5), (5, 7), (10, 12)]);
+    assert_eq!(ranges(&mut list.overlapping((Bound::Excluded(5), Bound::Unbounded))), [(5, 7), (10, 12)]);
+    assert_eq!(ranges(&mut list.overlapping(..)).len(), 5);
+    assert_eq!(ranges(&mut list.contained_in(3..=7)), [(3, 5), (5, 5), (5, 7)]);
+    assert_eq!(ranges(&mut list.contained_in(2..7)), [(3, 5), (5, 5)]);
+    assert_eq!(ranges(&mut list.contained_in(..5)), [(1, 3)]);
+    assert_eq!(ranges(&mut list.contained_in(6..)), [(10, 12)]);
+    assert!(ranges(&mut list.contained_in(6..12)).is_empty());
+    // empty windows contain no positions, so nothing overlaps or is contained in them
+    assert!(ranges(&mut list.overlapping(3..3)).is_empty());
+    assert!(ranges(&mut list.overlapping(5..5)).is_empty());
+    assert!(ranges(&mut list.contained_in(5..5)).is_empty());
+    assert_eq!(ranges(&mut list.containing(5).rev()), [(5, 7), (5, 5), (3, 5)]);
+    assert_eq!(ranges(&mut list.contained_in(..).rev()), [(10, 12), (5, 7), (5, 5), (3, 5), (1, 3)]);
+    let mut overlapping = list.overlapping(2..=10);
+    assert_eq!(ranges_of(overlapping.next().into_iter()), [(1, 3)]);
+    assert_eq!(ranges_of(overlapping.next_back().into_iter()), [(10, 12)]);
+    assert_eq!(ranges(&mut overlapping), [(3, 5), (5, 5), (5, 7)]);
+    assert!(overlapping.next_back().is_none());
+}
+
+#[test]
+fn overlap_queries_after_inserting_at_front() {
+    let mut list: HollowRangeSpacedList<u64> = HollowRangeSpacedList::new();
+    list.insert(3, 5);
+    list.insert(6, 8);
+    // moves the first range out of the first slots, which the new one then takes
+    list.insert(1, 2);
+    assert_eq!(ranges_of(list.overlapping(0..6)), [(1, 2), (3, 5)]);
+    assert_eq!(ranges_of(list.overlapping(0..6).rev()), [(3, 5), (1, 2)]);
+    assert_eq!(ranges_of(list.contained_in(0..=5)), [(1, 2), (3, 5)]);
+    assert_eq!(ranges_of(list.contained_in(0..=5).rev()), [(3, 5), (1, 2)]);
+    assert_eq!(ranges_of(list.containing(2)), [(1, 2)]);
+    assert_eq!(ranges_of(list.overlapping(..)), [(1, 2), (3, 5), (6, 8)]);
+}
+
+#[test]
+fn double_ended_range_iteration() {
+    let mut list: HollowRangeSpacedList<u64> = HollowRangeSpacedList::new();
+    for start in (0..50).rev() {
+        list.insert(start * 3, start * 3 + 2);
+    }
+    let mut iter = list.iter_ranges();
+    assert_eq!(iter.len(), 50);
+    let (first_start, first_end) = iter.next().unwrap();
+    let (last_start, last_end) = iter.next_back().unwrap();
+    assert_eq!((first_start.position(), first_end.position()), (0, 2));
+    assert_eq!((last_start.position(), last_end.position()), (147, 149));
+    assert_eq!(iter.len(), 48);
+    assert_eq!(ranges_of(list.iter_ranges_backwards()),
+               (0..50).rev().map(|start| (start * 3, start * 3 + 2)).collect_vec());
+    assert_eq!(list.iter().len(), 100);
+    assert_eq!((&list).into_iter().rev().map(|bound| bound.position()).take(2).collect_vec(), [149, 147]);
+}
+
+fn ranges_of(iter: impl Iterator<Item=(HollowPosition<Range, u64>, HollowPosition<Range, u64>)>) -> Vec<(u64, u64)> {
+    iter.map(|(start, end)| (start.position(), end.position())).collect()
+}
+
+#[test]
+fn relative_insertion() {
+    let mut list: HollowRangeSpacedList<u64> = HollowRangeSpacedList::new();
+    let first = list.insert(10, 20).index();
+    let second = list.insert(40, 50).index();
+    list.insert_after(&first, 5, 10);
+    list.insert_before(&first, 2, 3);
+    list.insert_before(&second, 0, 4);
+    assert_eq!(ranges_of(list.iter_ranges()), vec![(5, 8), (10, 20), (25, 35), (36, 40), (40, 50)]);
+    assert!(matches!(list.try_insert_after(&first, 1, 5), Err(RelativeInsertionError::DoesNotFit)));
+    assert!(matches!(list.try_insert_before(&first, 0, 3), Err(RelativeInsertionError::DoesNotFit)));
+    assert_eq!(list.insert_after(&second, 100, 1).position(), 150);
+    let zeroth = list.first().unwrap().index();
+    assert!(matches!(list.try_insert_before(&zeroth, 3, 3), Err(RelativeInsertionError::DoesNotFit)));
+    assert_eq!(list.insert_before(&zeroth, 2, 3).position(), 0);
+    let mut other = HollowRangeSpacedList::new();
+    other.insert(0, 1);
+    assert!(matches!(other.try_insert_after(&first, 1, 1), Err(RelativeInsertionError::ElementOfOtherList)));
+    assert_eq!(other.size(), 1);
+}
+
+#[test]
+fn element_spacing() {
+    let mut list: HollowRangeSpacedList<u64> = HollowRangeSpacedList::new();
+    let first = list.insert(10, 20).index();
+    let second = list.insert(30, 35).index();
+    let third = list.insert(40, 50).index();
+    // the spacing after a range starts at its end, and the spacing before it ends at its start
+    list.set_spacing_after(&first, 2);
+    assert_eq!(ranges_of(list.iter_ranges()), vec![(10, 20), (22, 27), (32, 42)]);
+    list.set_spacing_before(&third, 0);
+    assert_eq!(ranges_of(list.iter_ranges()), vec![(10, 20), (22, 27), (27, 37)]);
+    list.increase_spacing_before_element(&second, 3);
+    list.decrease_spacing_before_element(&first, 10);
+    assert_eq!(ranges_of(list.iter_ranges()), vec![(0, 10), (15, 20), (20, 30)]);
+    assert!(list.try_decrease_spacing_after_element(&second, 1).is_err());
+}
+
+#[test]
+fn delete_region() {
+    let build = || {
+        let mut list: HollowRangeSpacedList<u64> = HollowRangeSpacedList::new();
+        for (start, end) in [(0, 10), (12, 14), (15, 15), (16, 25), (30, 40)] {
+            list.insert(start, end);
+        }
+        list
+    };
+    let mut list = build();
+    assert_eq!(list.delete_region(5, 20, StraddlePolicy::Clip), vec![(12, 14), (15, 15)]);
+    assert_eq!(ranges_of(list.iter_ranges()), vec![(0, 5), (5, 10), (15, 25)]);
+    let mut list = build();
+    assert_eq!(list.delete_region(5, 20, StraddlePolicy::Remove), vec![(0, 10), (12, 14), (15, 15), (16, 25)]);
+    assert_eq!(ranges_of(list.iter_ranges()), vec![(15, 25)]);
+    let mut list = build();
+    assert!(matches!(list.try_delete_region(5, 20, StraddlePolicy::Error),
+                     Err(DeletionError::RangeStraddlesRegion { start: 0, end: 10 })));
+    assert!(matches!(list.try_delete_region(20, 5, StraddlePolicy::Error),
+                     Err(DeletionError::RegionEndsBeforeStart { start: 20, end: 5 })));
+    // ranges that only touch the region are neither inside of it nor straddle it
+    assert_eq!(list.delete_region(10, 16, StraddlePolicy::Error), vec![(12, 14), (15, 15)]);
+    assert_eq!(ranges_of(list.iter_ranges()), vec![(0, 10), (10, 19), (24, 34)]);
+    assert_eq!(list.delete_region(2, 4, StraddlePolicy::Clip), vec![]);
+    assert_eq!(ranges_of(list.iter_ranges()), vec![(0, 8), (8, 17), (22, 32)]);
+}
+
+#[test]
+fn split_off_and_append() {
+    let mut list: HollowRangeSpacedList<u64> = HollowRangeSpacedList::new();
+    let indices = [(0, 10), (12, 14), (15, 15), (16, 25), (30, 40)].into_iter()
+        .map(|(start, end)| list.insert(start, end).index())
+        .collect::<Vec<_>>();
+    assert!(matches!(list.try_split_off(20), Err(SplitError::PositionInsideRange { position: 20, start: 16, end: 25 })));
+    let mut right = list.split_off(15);
+    assert_eq!(ranges_of(list.iter_ranges()), vec![(0, 10), (12, 14)]);
+    assert_eq!(ranges_of(right.iter_ranges()), vec![(15, 15), (16, 25), (30, 40)]);
+    let mut split = right.split_off_splitting(20);
+    assert_eq!(ranges_of(right.iter_ranges()), vec![(15, 15), (16, 20)]);
+    assert_eq!(ranges_of(split.iter_ranges()), vec![(20, 25), (30, 40)]);
+    // the part before the position keeps the index of a split range
+    assert_eq!(indices[3].position().position(), 16);
+    assert_eq!(indices[4].position().position(), 30);
+    list.append(&mut split, 1);
+    assert_eq!(ranges_of(list.iter_ranges()), vec![(0, 10), (12, 14), (15, 20), (25, 35)]);
+    assert_eq!(indices[4].position().position(), 25);
+    list.append(&mut right, 0);
+    assert_eq!(ranges_of(list.iter_ranges()), vec![(0, 10), (12, 14), (15, 20), (25, 35), (35, 35), (36, 40)]);
+    assert_eq!(list.size(), 6);
+}
@@ -381,3 +381,31 @@ fn queries() {
         // endregion
     }
 }
+
+#[test]
+fn randomized_removal() {
+    let mut list: HollowSpacedList<i32> = HollowSpacedList::new();
+    let mut set: BTreeSet<i32> = BTreeSet::new();
+    let mut rng = StdRng::seed_from_u64(random());
+    for _ in 0..2_000 {
+        let pos = rng.gen_range(-10_000..10_000);
+        if set.insert(pos) {
+            list.insert(pos);
+        }
+    }
+    let indices = list.iter().map(|pos| (pos.position(), pos.index())).collect_vec();
+    for (pos, index) in indices {
+        if rng.gen_bool(0.7) {
+            set.remove(&pos);
+            list.remove(index);
+        }
+    }
+    assert_eq!(list.size(), set.len());
+    assert_eq!(list.iter().map(|pos| pos.position()).collect_vec(), set.iter().copied().collect_vec());
+    assert_eq!(list.iter_backwards().map(|pos| pos.position()).collect_vec(), set.iter().rev().copied().collect_vec());
+    assert_eq!(list.last().map(|pos| pos.position()), set.last().copied());
+    for query in (-10_000..10_000).step_by(97) {
+        assert_eq!(list.at_or_after(query).map(|pos| pos.position()), set.range(query..).next().copied());
+        assert_eq!(list.before(query).map(|pos| pos.position()), set.range(..query).next_back().copied());
+    }
+}
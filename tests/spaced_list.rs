@@ -1,4 +1,6 @@
-use spaced_list_5::SpacedList;
+use std::ops::Bound;
+
+use spaced_list_5::{Node, Position, RelativeInsertionError, RemovalError, SpacedList, SpacingError, StaleIndexError};
 
 #[test]
 fn conditional_traversal_test() {
@@ -10,4 +12,264 @@ fn conditional_traversal_test() {
     assert_eq!(list.conditional_after(0, |str| str.starts_with('b')).unwrap().element().unwrap(), "bar");
     assert_eq!(list.conditional_before(10, |str| str.starts_with('b')).unwrap().element().unwrap(), "baz");
     assert_eq!(list.conditional_before(10, |str| str.starts_with('f')).unwrap().element().unwrap(), "foo");
-}
\ No newline at end of file
+}
+
+#[test]
+fn removal() {
+    let mut list = SpacedList::new();
+    let a = list.insert(2, 'a');
+    let b = list.insert(4, 'b');
+    let c = list.insert(6, 'c');
+    let d = list.insert(8, 'd');
+    assert_eq!(list.remove(b.index()), 'b');
+    assert!(list.try_remove(b.index()).is_err());
+    let mut other = SpacedList::new();
+    other.insert(2, 'a');
+    assert!(matches!(other.try_remove(a.index()), Err(RemovalError::ElementOfOtherList)));
+    assert_eq!(other.size(), 1);
+    assert_eq!(list.remove(d.index()), 'd');
+    assert_eq!(list.size(), 2);
+    assert_eq!(list.end(), 6);
+    assert_eq!(list.iter().map(|pos| (pos.position(), *pos.element().as_ref().unwrap())).collect::<Vec<_>>(),
+               vec![(2, 'a'), (6, 'c')]);
+    assert_eq!(list.start(), 2);
+    assert_eq!(list.remove(a.index()), 'a');
+    assert_eq!(list.first().unwrap().position(), 6);
+    assert_eq!(list.start(), 6);
+    assert_eq!(list.length(), 0);
+    assert_eq!(list.at_or_after(0).unwrap().position(), 6);
+    assert!(list.before(6).is_none());
+    assert_eq!(list.remove(c.index()), 'c');
+    assert!(list.is_empty());
+    assert!(list.first().is_none());
+    list.push(3, 'e');
+    assert_eq!(list.first().unwrap().position(), 3);
+}
+
+#[test]
+fn stale_indices() {
+    let mut list = SpacedList::new();
+    let a = list.insert(2, 'a');
+    let b = list.insert(4, 'b');
+    list.insert(6, 'c');
+    // moves 'a' into a sub, but its index and position keep referring to it
+    list.insert(1, 'd');
+    assert_eq!(*a.index().try_element().unwrap(), Some('a'));
+    assert_eq!(a.try_position().unwrap(), 2);
+    list.increase_spacing_before(2, 3);
+    assert_eq!(a.position(), 2);
+    assert_eq!(a.try_position().unwrap(), 5);
+
+    list.remove(b.index());
+    assert!(!b.is_valid());
+    assert!(!b.index().is_valid());
+    assert!(matches!(b.index().try_element(), Err(StaleIndexError::ElementRemoved)));
+    // the new element takes the slot of 'b'
+    let e = list.insert(7, 'e');
+    assert!(e.is_valid());
+    assert!(matches!(b.try_element(), Err(StaleIndexError::SlotReused)));
+    assert!(matches!(b.index().try_position(), Err(StaleIndexError::SlotReused)));
+    assert!(a.is_valid());
+}
+
+#[test]
+fn ranges_of_positions() {
+    let mut list = SpacedList::new();
+    for (position, element) in [(1, 'a'), (3, 'b'), (3, 'c'), (6, 'd'), (10, 'e')] {
+        list.insert(position, element);
+    }
+    let elements = |iter: &mut dyn Iterator<Item=Position<Node, i32, char>>|
+        iter.map(|pos| *pos.element().as_ref().unwrap()).collect::<String>();
+    assert_eq!(elements(&mut list.range(3..10)), "bcd");
+    assert_eq!(elements(&mut list.range(3..=10)), "bcde");
+    assert_eq!(elements(&mut list.range(..6)), "abc");
+    assert_eq!(elements(&mut list.range((Bound::Excluded(3), Bound::Unbounded))), "de");
+    assert_eq!(elements(&mut list.range(..)), "abcde");
+    assert_eq!(elements(&mut list.range(4..6)), "");
+    assert_eq!(elements(&mut list.range(11..)), "");
+    assert_eq!(elements(&mut list.range_backwards(2..=6)), "dcb");
+    assert_eq!(elements(&mut list.range_backwards(..3)), "a");
+    assert_eq!(elements(&mut list.range_backwards(..)), "edcba");
+    assert_eq!(elements(&mut list.range(6..6)), "");
+    let mut range = list.range(1..=6);
+    assert_eq!(range.next().unwrap().position(), 1);
+    assert_eq!(range.next_back().unwrap().position(), 6);
+    assert_eq!(elements(&mut range.by_ref().rev()), "cb");
+    assert!(range.next().is_none());
+}
+
+#[test]
+fn ranges_after_inserting_at_front() {
+    let mut list = SpacedList::new();
+    list.insert(3, 'b');
+    list.insert(6, 'c');
+    // moves 'b' out of the first slot, which 'a' then takes
+    list.insert(2, 'a');
+    let elements = |iter: &mut dyn Iterator<Item=Position<Node, i32, char>>|
+        iter.map(|pos| *pos.element().as_ref().unwrap()).collect::<String>();
+    assert_eq!(elements(&mut list.range(0..5)), "ab");
+    assert_eq!(elements(&mut list.range_backwards(0..=5)), "ba");
+    assert_eq!(elements(&mut list.range(..)), "abc");
+}
+
+#[test]
+fn double_ended_iteration() {
+    let mut list = SpacedList::new();
+    for position in (0..200).rev() {
+        list.insert(position * 2, position);
+    }
+    let removed = list.insert(1, -1);
+    list.remove(removed.index());
+    let mut iter = list.iter();
+    assert_eq!(iter.len(), 200);
+    let mut front = vec![];
+    let mut back = vec![];
+    // alternate between both ends until they meet in the middle
+    while let Some(position) = iter.next() {
+        front.push(*position.element().as_ref().unwrap());
+        assert_eq!(iter.len(), 200 - front.len() - back.len());
+        if let Some(position) = iter.next_back() {
+            back.push(*position.element().as_ref().unwrap());
+        }
+    }
+    assert!(iter.next_back().is_none());
+    assert_eq!(front, (0..100).collect::<Vec<_>>());
+    assert_eq!(back, (100..200).rev().collect::<Vec<_>>());
+    assert_eq!(list.iter_backwards().map(|pos| pos.position()).collect::<Vec<_>>(),
+               (0..200).rev().map(|position| position * 2).collect::<Vec<_>>());
+    let mut previous = None;
+    for position in &list {
+        assert_eq!(position.position(), previous.map_or(0, |previous| previous + 2));
+        previous = Some(position.position());
+    }
+    assert_eq!(list.into_iter().next_back().unwrap().position(), 398);
+}
+
+#[test]
+fn relative_insertion() {
+    let mut list = SpacedList::new();
+    let mut expected = vec![];
+    let mut indices = vec![];
+    for position in (0..100).rev() {
+        indices.push(list.insert(10 + position * 10, position).index());
+        expected.push((10 + position * 10, position));
+    }
+    // fills the gaps from both sides of every element, so that the insertions land in subs
+    for (element, index) in indices.iter().enumerate() {
+        let position = list.insert_after(index, 3, 1_000 + element as i32).position();
+        expected.push((position, 1_000 + element as i32));
+        let position = list.insert_before(index, 3, 2_000 + element as i32).position();
+        expected.push((position, 2_000 + element as i32));
+    }
+    expected.sort();
+    assert_eq!(list.iter().map(|pos| (pos.position(), *pos.element().as_ref().unwrap())).collect::<Vec<_>>(),
+               expected);
+
+    let last = list.last().unwrap().index();
+    assert_eq!(list.insert_after(&last, 100, -1).position(), 1_103);
+    let first = list.first().unwrap().index();
+    assert!(matches!(list.try_insert_before(&first, -1, -2), Err(RelativeInsertionError::NegativeSpacing)));
+    // the spacing before the first element, at 7, is its position
+    assert!(matches!(list.try_insert_before(&first, 8, -2), Err(RelativeInsertionError::DoesNotFit)));
+    assert_eq!(list.insert_before(&first, 7, -2).position(), 0);
+    // the element at 10 is followed by the one at 13
+    assert!(matches!(list.try_insert_after(&indices[99], 4, -2), Err(RelativeInsertionError::DoesNotFit)));
+    assert_eq!(list.insert_after(&indices[99], 2, -2).position(), 12);
+    list.remove(indices[50].clone());
+    assert!(matches!(list.try_insert_after(&indices[50], 1, -3),
+                     Err(RelativeInsertionError::StaleIndex(StaleIndexError::ElementRemoved))));
+    assert_eq!(list.size(), 300 + 3 - 1);
+    let mut other = SpacedList::new();
+    other.insert(0, 0);
+    assert!(matches!(other.try_insert_after(&last, 1, -4), Err(RelativeInsertionError::ElementOfOtherList)));
+    assert_eq!(other.size(), 1);
+
+    let mut list: SpacedList<u32, char> = SpacedList::new();
+    let a = list.insert(2, 'a').index();
+    let b = list.insert(5, 'b').index();
+    assert!(matches!(list.try_insert_before(&a, 3, 'c'), Err(RelativeInsertionError::DoesNotFit)));
+    assert!(matches!(list.try_insert_before(&b, 4, 'c'), Err(RelativeInsertionError::DoesNotFit)));
+    assert_eq!(list.insert_before(&a, 2, 'c').position(), 0);
+    assert_eq!(list.insert_before(&b, 3, 'd').position(), 2);
+    assert_eq!(list.size(), 4);
+}
+
+#[test]
+fn element_spacing() {
+    let mut list: SpacedList<u32, char> = SpacedList::new();
+    let a = list.insert(10, 'a').index();
+    let b = list.insert(10, 'b').index();
+    let c = list.insert(20, 'c').index();
+    let positions = |list: &SpacedList<u32, char>|
+        list.iter().map(|pos| (pos.position(), *pos.element().as_ref().unwrap())).collect::<Vec<_>>();
+    let first_value = *list.first().unwrap().element().as_ref().unwrap();
+    let (first, second, second_value) = if first_value == 'a' { (&a, &b, 'b') } else { (&b, &a, 'a') };
+    // only the elements after the first one at 10 move, including the other one at 10
+    list.increase_spacing_after_element(first, 5);
+    assert_eq!(positions(&list), [(10, first_value), (15, second_value), (25, 'c')]);
+    list.set_spacing_before(&c, 2);
+    assert_eq!(positions(&list), [(10, first_value), (15, second_value), (17, 'c')]);
+    list.set_spacing_after(first, 0);
+    assert_eq!(positions(&list), [(10, first_value), (10, second_value), (12, 'c')]);
+    list.set_spacing_before(first, 3);
+    assert_eq!(positions(&list), [(3, first_value), (3, second_value), (5, 'c')]);
+    list.decrease_spacing_before_element(&c, 1);
+    list.increase_spacing_before_element(second, 1);
+    assert_eq!(positions(&list), [(3, first_value), (4, second_value), (5, 'c')]);
+    assert!(matches!(list.try_decrease_spacing_after_element(second, 2),
+                     Err(SpacingError::SpacingNotLargeEnough { change: 2, spacing: 1, .. })));
+    assert!(matches!(list.try_set_spacing_after(&c, 1), Err(SpacingError::PositionAtOrAfterList { position: 5 })));
+    list.remove(c.clone());
+    assert!(matches!(list.try_increase_spacing_before_element(&c, 1),
+                     Err(SpacingError::StaleIndex(StaleIndexError::ElementRemoved))));
+    assert!(matches!(list.try_decrease_spacing_before_element(first, 4),
+                     Err(SpacingError::SpacingNotLargeEnough { change: 4, spacing: 3, .. })));
+    list.decrease_spacing_before_element(first, 3);
+    assert_eq!(positions(&list), [(0, first_value), (1, second_value)]);
+
+    let mut other: SpacedList<u32, char> = SpacedList::new();
+    other.insert(1, 'd');
+    assert!(matches!(other.try_set_spacing_after(first, 5), Err(SpacingError::ElementOfOtherList)));
+    assert!(matches!(other.try_set_spacing_before(second, 5), Err(SpacingError::ElementOfOtherList)));
+    assert_eq!(positions(&list), [(0, first_value), (1, second_value)]);
+    assert_eq!(positions(&other), [(1, 'd')]);
+}
+
+#[test]
+fn delete_region() {
+    let mut list = SpacedList::new();
+    let indices = (0..10).map(|element| list.insert(element * 10, element).index()).collect::<Vec<_>>();
+    assert_eq!(list.delete_region(25, 60), vec![(30, 3), (40, 4), (50, 5)]);
+    assert_eq!(list.iter().map(|pos| (pos.position(), *pos.element().as_ref().unwrap())).collect::<Vec<_>>(),
+               vec![(0, 0), (10, 1), (20, 2), (25, 6), (35, 7), (45, 8), (55, 9)]);
+    // indices of the elements that moved still refer to them
+    assert_eq!(indices[7].position().position(), 35);
+    assert!(!indices[4].is_valid());
+    assert_eq!(list.delete_region(0, 0), vec![]);
+    assert_eq!(list.delete_region(0, 1_000), vec![(0, 0), (10, 1), (20, 2), (25, 6), (35, 7), (45, 8), (55, 9)]);
+    assert_eq!(list.size(), 0);
+}
+
+#[test]
+fn split_off_and_append() {
+    let mut list = SpacedList::new();
+    let indices = (0..10).map(|element| list.insert(element * 10, element).index()).collect::<Vec<_>>();
+    let mut right = list.split_off(45);
+    assert_eq!(list.iter().map(|pos| pos.position()).collect::<Vec<_>>(), vec![0, 10, 20, 30, 40]);
+    assert_eq!(right.iter().map(|pos| pos.position()).collect::<Vec<_>>(), vec![50, 60, 70, 80, 90]);
+    // indices of the moved elements refer to them in the new list
+    assert_eq!(indices[7].position().position(), 70);
+    assert_eq!(right.remove(indices[5].clone()), 5);
+    list.append(&mut right, 5);
+    assert!(right.is_empty());
+    assert_eq!(list.iter().map(|pos| (pos.position(), *pos.element().as_ref().unwrap())).collect::<Vec<_>>(),
+               vec![(0, 0), (10, 1), (20, 2), (30, 3), (40, 4), (45, 6), (55, 7), (65, 8), (75, 9)]);
+    assert_eq!(indices[9].position().position(), 75);
+    assert_eq!(list.size(), 9);
+    assert!(list.split_off(100).is_empty());
+    let mut all = list.split_off(0);
+    assert!(list.is_empty());
+    list.append(&mut all, 3);
+    assert_eq!(list.first().unwrap().position(), 3);
+    assert!(list.try_append(&mut SpacedList::new(), -1).is_ok());
+}